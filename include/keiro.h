@@ -0,0 +1,209 @@
+#ifndef KEIRO_H
+#define KEIRO_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call.
+typedef enum KeiroStatus {
+  // The call succeeded
+  KEIRO_STATUS_OK = 0,
+  // A pointer argument is null
+  KEIRO_STATUS_NULL_POINTER,
+  // A string argument is not valid UTF-8
+  KEIRO_STATUS_INVALID_UTF8,
+  // The serialized scenario cannot be read
+  KEIRO_STATUS_INVALID_SCENARIO,
+  // The scenario fails validation
+  KEIRO_STATUS_INVALID_INPUT,
+  // The solver found no routing
+  KEIRO_STATUS_NO_ROUTE,
+  // The solver has not found a routing yet
+  KEIRO_STATUS_NOT_SOLVED,
+  // An index is out of range
+  KEIRO_STATUS_OUT_OF_RANGE,
+  // The call panicked
+  KEIRO_STATUS_PANIC,
+} KeiroStatus;
+
+// The type of the action of a path.
+typedef enum KeiroActionType {
+  // A scheduled action
+  KEIRO_ACTION_TYPE_SCHEDULED,
+  // An evasive action
+  KEIRO_ACTION_TYPE_EVASIVE,
+  // An idle action
+  KEIRO_ACTION_TYPE_IDLE,
+  // The agent is parked during an unavailability window
+  KEIRO_ACTION_TYPE_PARKED,
+} KeiroActionType;
+
+// A solver context: the scenario built up by the caller and the routing
+// found for it.
+typedef struct KeiroSolver KeiroSolver;
+
+// A path of an agent, without its segments.
+typedef struct KeiroPath {
+  // The type of the action
+  enum KeiroActionType type;
+  // The x-coordinate of the target
+  double target_x;
+  // The y-coordinate of the target
+  double target_y;
+  // The start time of the path
+  double t_start;
+  // The end time of the path
+  double t_end;
+  // The index of the scheduled action the solver was executing when it
+  // planned the path, or -1
+  int64_t step;
+  // The number of segments of the path
+  size_t segments;
+} KeiroPath;
+
+// A segment of a path.
+typedef struct KeiroSegment {
+  // The x-coordinate of the start
+  double start_x;
+  // The y-coordinate of the start
+  double start_y;
+  // The x-coordinate of the end
+  double end_x;
+  // The y-coordinate of the end
+  double end_y;
+  // The duration of the segment
+  double duration;
+} KeiroSegment;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a solver context with an empty scenario. Free it with
+// `keiro_solver_free`.
+struct KeiroSolver *keiro_solver_new(void);
+
+// Free a solver context.
+//
+// # Safety
+//
+// The pointer is null or was returned by `keiro_solver_new` and is not
+// used afterwards.
+void keiro_solver_free(struct KeiroSolver *solver);
+
+// The message of the last failure, empty once a later call that changes
+// the context succeeds, and valid until the next call with the context.
+//
+// # Safety
+//
+// The pointer was returned by `keiro_solver_new`.
+const char *keiro_solver_last_error(const struct KeiroSolver *solver);
+
+// Replace the scenario by a serialized one, as YAML or JSON.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and the scenario is a
+// null-terminated string.
+enum KeiroStatus keiro_solver_load_scenario(struct KeiroSolver *solver, const char *scenario);
+
+// Add an agent with a polygonal reach, given as `points` pairs of x- and
+// y-coordinates.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new`, the name is a
+// null-terminated string and the reach holds `2 * points` numbers.
+enum KeiroStatus keiro_solver_add_agent(struct KeiroSolver *solver,
+                                        const char *name,
+                                        const double *reach,
+                                        size_t points,
+                                        double x,
+                                        double y,
+                                        double velocity_x,
+                                        double velocity_y,
+                                        double safety_x,
+                                        int64_t order);
+
+// Add a scheduled action of the named agent, or of the agent it is assigned
+// to if the agent is null.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and the agent is null or a
+// null-terminated string.
+enum KeiroStatus keiro_solver_add_action(struct KeiroSolver *solver,
+                                         const char *agent,
+                                         double x,
+                                         double y,
+                                         double duration);
+
+// Validate and solve the scenario.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new`.
+enum KeiroStatus keiro_solver_solve(struct KeiroSolver *solver);
+
+// The number of agents of the routing.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and `count` points to
+// writable memory.
+enum KeiroStatus keiro_solver_agent_count(const struct KeiroSolver *solver, size_t *count);
+
+// The name of an agent of the routing, valid until the context is solved
+// again or freed.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and `name` points to
+// writable memory.
+enum KeiroStatus keiro_solver_agent_name(const struct KeiroSolver *solver,
+                                         size_t agent,
+                                         const char **name);
+
+// The number of paths of an agent of the routing.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and `count` points to
+// writable memory.
+enum KeiroStatus keiro_solver_path_count(const struct KeiroSolver *solver,
+                                         size_t agent,
+                                         size_t *count);
+
+// A path of an agent of the routing.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and `out` points to
+// writable memory.
+enum KeiroStatus keiro_solver_path(const struct KeiroSolver *solver,
+                                   size_t agent,
+                                   size_t path,
+                                   struct KeiroPath *out);
+
+// A segment of a path of an agent of the routing.
+//
+// # Safety
+//
+// The solver was returned by `keiro_solver_new` and `out` points to
+// writable memory.
+enum KeiroStatus keiro_solver_segment(const struct KeiroSolver *solver,
+                                      size_t agent,
+                                      size_t path,
+                                      size_t segment,
+                                      struct KeiroSegment *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KEIRO_H */
@@ -0,0 +1,157 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use geo::Coord;
+
+use crate::actions::{
+    config::SolverConfig,
+    data::Agent,
+    graphs::{free_region, road::RoadGraph, Navigator},
+    scenario::ScenarioAction,
+    site::Site,
+    validation::within_reach,
+    Routing,
+};
+
+/// Assigns the actions of a schedule to agents, in the order they are
+/// executed. Every agent works off the actions queued for it; an action left
+/// unassigned goes to the capable agent that completes it first after its
+/// queue, so that the work spreads over the agents. Every action takes its
+/// duration plus the buffer the solver reserves after it. Travel is
+/// estimated with the planner of the agent amid the static obstacles of the
+/// site, or on the road network for agents bound to it.
+/// An agent that becomes unavailable before it completes an action is taken
+/// to start it once the window ends, from its parking.
+pub struct Assigner<'a> {
+    /// The agents to assign the actions to
+    agents: &'a [Agent],
+    /// The site the agents operate on
+    site: &'a Site,
+    /// The navigation structures per agent, built when first needed
+    nav: RefCell<HashMap<usize, Navigator>>,
+    /// The road network of the site
+    roads: RoadGraph,
+    /// The settings of the solver, for the tolerance up to which a target
+    /// outside the reach counts as in it and the buffers after actions
+    config: SolverConfig,
+    /// The time each agent completes its queue, and its position then
+    queues: Vec<(f64, Coord)>,
+}
+
+impl<'a> Assigner<'a> {
+    /// An assigner for the agents on the site, with their queues empty.
+    pub fn new(agents: &'a [Agent], site: &'a Site, config: &SolverConfig) -> Assigner<'a> {
+        Assigner {
+            agents,
+            site,
+            nav: RefCell::new(HashMap::new()),
+            roads: RoadGraph::new(&site.roads),
+            config: *config,
+            queues: agents.iter().map(|a| (0.0, a.position)).collect(),
+        }
+    }
+
+    /// Queue the agents after the routing, each at the end of its last path.
+    /// Agents are matched by name; agents not in the routing stay as they
+    /// are.
+    pub fn after(mut self, routing: &Routing) -> Assigner<'a> {
+        for (a, paths) in routing.routes.iter() {
+            let Some(last) = paths.last() else {
+                continue;
+            };
+            if let Some(j) = self.agents.iter().position(|ag| ag.name == a.name) {
+                self.queues[j] = (last.t_end, last.action.target);
+            }
+        }
+        self
+    }
+
+    /// The estimated time the agent completes the action after the actions
+    /// queued for it, unless the agent lacks a required capability or cannot
+    /// reach the target.
+    pub fn completion(&self, agent: usize, action: &ScenarioAction) -> Option<f64> {
+        let a = &self.agents[agent];
+        if !a.can_execute(&action.requires)
+            || !(a.on_roads() || within_reach(a, action.target, self.config.tolerance))
+        {
+            return None;
+        }
+        self.finish(agent, action)
+    }
+
+    /// The index of the agent that completes the action first, if any agent
+    /// can execute it.
+    pub fn assign(&self, action: &ScenarioAction) -> Option<usize> {
+        (0..self.agents.len())
+            .filter_map(|j| Some((j, self.completion(j, action)?)))
+            .min_by(|(_, t1), (_, t2)| t1.total_cmp(t2))
+            .map(|(j, _)| j)
+    }
+
+    /// Queue the action to the agent. An agent that cannot get to the target
+    /// is never done with its queue, so no further action is assigned to it.
+    pub fn queue(&mut self, agent: usize, action: &ScenarioAction) {
+        let t = self.finish(agent, action);
+        self.queues[agent] = (t.unwrap_or(f64::INFINITY), action.target);
+    }
+
+    /// The time the agent finishes the action and its buffer after its
+    /// queue, parked during the unavailability windows that would interrupt
+    /// it.
+    fn finish(&self, agent: usize, action: &ScenarioAction) -> Option<f64> {
+        let a = &self.agents[agent];
+        let (mut t, mut position) = self.queues[agent];
+        let duration = action.duration
+            + self
+                .config
+                .scheduled_buffer(action.duration, action.std_dev);
+        loop {
+            let t_end = t + self.travel(agent, position, action.target)? + duration;
+            match a
+                .unavailable
+                .iter()
+                .find(|u| u.window.end > t && u.window.start < t_end)
+            {
+                Some(u) => {
+                    position = u.parking.closest(position);
+                    t = u.window.end;
+                }
+                None => return Some(t_end),
+            }
+        }
+    }
+
+    /// The time the agent travels from `start` to `target`, if it gets
+    /// there at all.
+    fn travel(&self, agent: usize, start: Coord, target: Coord) -> Option<f64> {
+        let a = &self.agents[agent];
+        if a.on_roads() {
+            let (from, to) = (self.roads.nearest(start)?, self.roads.nearest(target)?);
+            let route = self.roads.find_route(from, to, &a.velocity, &[])?;
+            return Some(
+                route
+                    .iter()
+                    .map(|&e| self.roads.travel_time(e, &a.velocity))
+                    .sum(),
+            );
+        }
+        let path = self
+            .nav
+            .borrow_mut()
+            .entry(agent)
+            .or_insert_with(|| {
+                let areas = self
+                    .site
+                    .obstacles
+                    .iter()
+                    .filter(|o| o.window.is_none())
+                    .map(|o| &o.area)
+                    .collect::<Vec<_>>();
+                Navigator::new(a, free_region(&a.region(), &areas))
+            })
+            .find_path(start, target)?;
+        Some(path.iter().map(|s| s.duration).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests;
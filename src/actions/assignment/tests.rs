@@ -0,0 +1,245 @@
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+
+use crate::actions::{
+    assignment::Assigner,
+    config::SolverConfig,
+    data::{Agent, ConstVel2D, Parking, Planner, Unavailability},
+    fixtures::agent,
+    scenario::ScenarioAction,
+    site::{Obstacle, RoadEdge, RoadNetwork, RoadNode, Site, TimeWindow},
+};
+
+fn capable(name: &str, x: f64, order: i64, capability: &str) -> Agent {
+    Agent {
+        capabilities: vec![String::from(capability)],
+        ..agent(name, x, order)
+    }
+}
+
+fn at(x: f64) -> Coord {
+    Coord { x, y: 5.0 }
+}
+
+fn action(target: Coord, duration: f64, requires: &[&str]) -> ScenarioAction {
+    ScenarioAction {
+        agent: None,
+        target,
+        duration,
+        requires: requires.iter().map(|c| String::from(*c)).collect(),
+        std_dev: 0.0,
+    }
+}
+
+#[test]
+fn test_assign_capabilities() {
+    let agents = [
+        capable("crane", 10.0, 0, "spreader-20ft"),
+        capable("gantry", 90.0, 1, "spreader-40ft"),
+    ];
+    let site = Site::default();
+    let assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+
+    assert_eq!(assigner.assign(&action(at(20.0), 5.0, &[])), Some(0));
+    assert_eq!(
+        assigner.assign(&action(at(20.0), 5.0, &["spreader-40ft"])),
+        Some(1)
+    );
+    assert_eq!(assigner.assign(&action(at(20.0), 5.0, &["lift"])), None);
+    // No agent reaches a target off the rail.
+    assert_eq!(
+        assigner.assign(&action(Coord { x: 20.0, y: 50.0 }, 5.0, &[])),
+        None
+    );
+}
+
+#[test]
+fn test_assign_spreads_load() {
+    let agents = [agent("agent-0", 10.0, 0), agent("agent-1", 90.0, 1)];
+    let site = Site::default();
+    let mut assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+
+    // The first action goes to the nearby agent, which completes it at 35.
+    assert_eq!(
+        assigner.completion(0, &action(at(20.0), 30.0, &[])),
+        Some(35.0)
+    );
+    assert_eq!(assigner.assign(&action(at(20.0), 30.0, &[])), Some(0));
+    assigner.queue(0, &action(at(20.0), 30.0, &[]));
+
+    // The second one goes to the other agent, which gets there later but
+    // completes it first, as the nearby agent is still busy.
+    assert_eq!(
+        assigner.completion(0, &action(at(25.0), 30.0, &[])),
+        Some(67.5)
+    );
+    assert_eq!(
+        assigner.completion(1, &action(at(25.0), 30.0, &[])),
+        Some(62.5)
+    );
+    assert_eq!(assigner.assign(&action(at(25.0), 30.0, &[])), Some(1));
+}
+
+#[test]
+fn test_assign_buffers() {
+    let agents = [agent("agent-0", 10.0, 0), agent("agent-1", 90.0, 1)];
+    let site = Site::default();
+    let config = SolverConfig {
+        slack: 0.5,
+        deviations: 2.0,
+        ..SolverConfig::default()
+    };
+    let mut assigner = Assigner::new(&agents, &site, &config);
+
+    // The agent is done with the action and its buffer of 5 + 5 at 25.
+    let first = ScenarioAction {
+        std_dev: 2.5,
+        ..action(at(20.0), 10.0, &[])
+    };
+    assert_eq!(assigner.completion(0, &first), Some(25.0));
+    assigner.queue(0, &first);
+
+    // The next action is buffered by half its duration as well.
+    assert_eq!(
+        assigner.completion(0, &action(at(25.0), 10.0, &[])),
+        Some(42.5)
+    );
+    assert_eq!(
+        assigner.completion(1, &action(at(25.0), 10.0, &[])),
+        Some(47.5)
+    );
+}
+
+#[test]
+fn test_assign_unavailable() {
+    let mut parked = agent("agent-0", 10.0, 0);
+    parked.unavailable = vec![Unavailability {
+        window: TimeWindow {
+            start: 5.0,
+            end: 50.0,
+        },
+        parking: Parking::Position(at(0.0)),
+    }];
+    let agents = [parked, agent("agent-1", 90.0, 1)];
+    let site = Site::default();
+    let assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+
+    // The nearby agent is parked before it completes the action, and only
+    // starts it from its parking once the window ends.
+    assert_eq!(
+        assigner.completion(0, &action(at(20.0), 5.0, &[])),
+        Some(65.0)
+    );
+    assert_eq!(
+        assigner.completion(1, &action(at(20.0), 5.0, &[])),
+        Some(40.0)
+    );
+    assert_eq!(assigner.assign(&action(at(20.0), 5.0, &[])), Some(1));
+}
+
+#[test]
+fn test_assign_around_obstacles() {
+    let agents = [agent("agent-0", 10.0, 0), agent("agent-1", 90.0, 1)];
+    let site = Site {
+        obstacles: vec![Obstacle {
+            name: String::from("wall"),
+            area: Polygon::new(
+                LineString::from(vec![(40.0, 0.0), (60.0, 0.0), (60.0, 10.0), (40.0, 10.0)]),
+                vec![],
+            ),
+            window: None,
+        }],
+        ..Site::default()
+    };
+    let mut assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+    assigner.queue(1, &action(at(90.0), 100.0, &[]));
+
+    // The wall cuts the rail in two, so the target is only within reach of
+    // the busy agent.
+    assert_eq!(assigner.completion(0, &action(at(70.0), 5.0, &[])), None);
+    assert_eq!(
+        assigner.completion(1, &action(at(70.0), 5.0, &[])),
+        Some(115.0)
+    );
+    assert_eq!(assigner.assign(&action(at(70.0), 5.0, &[])), Some(1));
+}
+
+#[test]
+fn test_assign_through_corridor() {
+    let square = |x0: f64| {
+        Polygon::new(
+            LineString::from(vec![
+                (x0, 0.0),
+                (x0 + 10.0, 0.0),
+                (x0 + 10.0, 10.0),
+                (x0, 10.0),
+            ]),
+            vec![],
+        )
+    };
+    let mut split = agent("agent-0", 5.0, 0);
+    split.reach = MultiPolygon(vec![square(0.0), square(20.0)]);
+    let site = Site::default();
+
+    // Without a corridor, the agent cannot get to the other part of its
+    // reach.
+    let agents = [split.clone()];
+    let assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+    assert_eq!(assigner.assign(&action(at(25.0), 2.0, &[])), None);
+
+    // Through the corridor, it gets there after 10.
+    split.corridors = Polygon::new(
+        LineString::from(vec![(10.0, 4.0), (20.0, 4.0), (20.0, 6.0), (10.0, 6.0)]),
+        vec![],
+    )
+    .into();
+    let agents = [split];
+    let assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+    assert_eq!(
+        assigner.completion(0, &action(at(25.0), 2.0, &[])),
+        Some(12.0)
+    );
+    assert_eq!(assigner.assign(&action(at(25.0), 2.0, &[])), Some(0));
+}
+
+#[test]
+fn test_assign_on_roads() {
+    let node = |name: &str, x: f64| RoadNode {
+        name: String::from(name),
+        position: Coord { x, y: 0.0 },
+    };
+    let road = |from: &str, to: &str| RoadEdge {
+        from: String::from(from),
+        to: String::from(to),
+        speed: 1.0,
+        capacity: 1,
+    };
+    let site = Site {
+        roads: RoadNetwork {
+            nodes: vec![node("a", 0.0), node("b", 10.0), node("c", 30.0)],
+            // The road from a to b is one-way the other way round.
+            edges: vec![
+                road("b", "a"),
+                road("a", "c"),
+                road("c", "b"),
+                road("b", "c"),
+            ],
+            clearance: 1.0,
+        },
+        ..Site::default()
+    };
+    let road_agent = |name: &str, x: f64, order: i64| Agent {
+        reach: MultiPolygon(vec![]),
+        position: Coord { x, y: 0.0 },
+        velocity: ConstVel2D { x: 5.0, y: 5.0 },
+        planner: Planner::Road,
+        ..agent(name, x, order)
+    };
+    let agents = [road_agent("agv-0", 0.0, 0), road_agent("agv-1", 30.0, 1)];
+    let assigner = Assigner::new(&agents, &site, &SolverConfig::default());
+    let b = Coord { x: 10.0, y: 0.0 };
+
+    // The agent closest to b has to drive around by c.
+    assert_eq!(assigner.completion(0, &action(b, 5.0, &[])), Some(55.0));
+    assert_eq!(assigner.completion(1, &action(b, 5.0, &[])), Some(25.0));
+    assert_eq!(assigner.assign(&action(b, 5.0, &[])), Some(1));
+}
@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use geo::Coord;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{ActionType, ConstVel2D, Path, Segment},
+    graphs::timer,
+    units::{Scale, Transform},
+    Routing,
+};
+
+/// A command for the controller of an agent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Command {
+    /// Move to the point.
+    MoveTo {
+        /// The x-coordinate of the point
+        x: f64,
+        /// The y-coordinate of the point
+        y: f64,
+    },
+    /// Move to the point to make way for another agent.
+    EvadeTo {
+        /// The x-coordinate of the point
+        x: f64,
+        /// The y-coordinate of the point
+        y: f64,
+    },
+    /// Stay where the agent is until the given time.
+    Wait {
+        /// The time to wait until
+        until: f64,
+    },
+    /// Execute a scheduled action where the agent is. The command may last
+    /// longer than the action, by the buffer the solver planned for it.
+    Execute {
+        /// The index of the scheduled action
+        action: Option<usize>,
+        /// The planned duration of the action
+        duration: f64,
+    },
+    /// Stay parked until the agent is available again.
+    Park {
+        /// The time to stay parked until
+        until: f64,
+    },
+}
+
+/// A command of another agent that has to be completed first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncPoint {
+    /// The name of the other agent
+    pub agent: String,
+    /// The index of the command in the stream of the other agent
+    pub seq: usize,
+}
+
+/// A command in the stream of an agent, with its planned times.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedCommand {
+    /// The name of the agent
+    pub agent: String,
+    /// The index of the command in the stream of the agent
+    pub seq: usize,
+    /// The planned start time of the command
+    pub t_start: f64,
+    /// The planned end time of the command
+    pub t_end: f64,
+    /// The command
+    #[serde(flatten)]
+    pub command: Command,
+    /// The commands of other agents that have to be completed before this
+    /// command starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<SyncPoint>,
+}
+
+/// The header of the CSV export.
+pub const CSV_HEADER: &str = "agent,seq,command,t_start,t_end,x,y,until,action,duration,after";
+
+/// A command with the range along the x-axis the agent covers meanwhile,
+/// and the segment it moves along, if any.
+struct Occupancy {
+    command: TimedCommand,
+    x_min: f64,
+    x_max: f64,
+    segment: Option<Segment>,
+}
+
+/// The commands for a path, with the position the agent is at before it.
+fn path_commands(agent: &str, path: &Path, position: Coord, out: &mut Vec<Occupancy>) {
+    let x = path.moves.last().map_or(position.x, |s| s.end.x);
+    let mut push = |t_start: f64, t_end: f64, command: Command, segment: Option<&Segment>| {
+        let (from, to) = segment.map_or((x, x), |s| (s.start.x, s.end.x));
+        out.push(Occupancy {
+            command: TimedCommand {
+                agent: agent.to_string(),
+                seq: out.len(),
+                t_start,
+                t_end,
+                command,
+                after: vec![],
+            },
+            x_min: f64::min(from, to),
+            x_max: f64::max(from, to),
+            segment: segment.cloned(),
+        })
+    };
+    let mut clock = path.t_start;
+    // Segments that neither move nor take time are no commands at all.
+    for s in path
+        .moves
+        .iter()
+        .filter(|s| s.start != s.end || s.duration > 0.0)
+    {
+        let t_end = clock + s.duration;
+        let command = if s.start == s.end {
+            // Agents on roads wait in place with segments of their own.
+            Command::Wait { until: t_end }
+        } else if path.action.r#type == ActionType::Evasive {
+            Command::EvadeTo {
+                x: s.end.x,
+                y: s.end.y,
+            }
+        } else {
+            Command::MoveTo {
+                x: s.end.x,
+                y: s.end.y,
+            }
+        };
+        push(clock, t_end, command, Some(s));
+        clock = t_end;
+    }
+    let (t_end, until) = (path.t_end, path.t_end);
+    match path.action.r#type {
+        ActionType::Scheduled => {
+            let (action, duration) = (path.step, path.action.duration);
+            push(clock, t_end, Command::Execute { action, duration }, None)
+        }
+        ActionType::Parked if t_end > clock => push(clock, t_end, Command::Park { until }, None),
+        ActionType::Idle | ActionType::Evasive if t_end > clock => {
+            push(clock, t_end, Command::Wait { until }, None)
+        }
+        _ => {}
+    }
+}
+
+/// The time after the start of the segment at which the agent comes
+/// closer than the tolerance to the range from `lo` to `hi` along the
+/// x-axis, if it does.
+fn entry(s: &Segment, vel: &ConstVel2D, lo: f64, hi: f64, tol: f64) -> Option<f64> {
+    let (x0, x1) = (s.start.x, s.end.x);
+    if f64::max(x0, x1) <= lo + tol || f64::min(x0, x1) >= hi - tol {
+        return None;
+    }
+    if lo + tol < x0 && x0 < hi - tol {
+        return Some(0.0);
+    }
+    let x = if x0 <= lo + tol { lo } else { hi };
+    let speed = vel.x * timer(s.start, s.end, vel) / s.duration;
+    Some(f64::min((x - x0).abs() / speed, s.duration))
+}
+
+/// Whether an agent moving along the segment follows another agent moving
+/// along `other` in the same direction, from `x` ahead of it on.
+fn follows(s: &Segment, other: &Segment, x: f64) -> bool {
+    let dir = (s.end.x - s.start.x).signum();
+    (other.end.x - other.start.x) * dir > 0.0 && (x - s.start.x) * dir > 0.0
+}
+
+/// The time after the start of the segment at which the agent gets to `x`
+/// along the x-axis, up to the tolerance, if it does.
+fn reaches(s: &Segment, vel: &ConstVel2D, x: f64, tol: f64) -> Option<f64> {
+    let dir = (s.end.x - s.start.x).signum();
+    if (x - s.start.x) * dir <= tol {
+        return Some(0.0);
+    }
+    if (s.end.x - x) * dir < -tol {
+        return None;
+    }
+    let speed = vel.x * timer(s.start, s.end, vel) / s.duration;
+    Some(f64::min((x - s.start.x).abs() / speed, s.duration))
+}
+
+/// The pieces of a move along `s`, from `t` after its start on, in which
+/// the agent follows another agent moving along `other` at distance `d`.
+/// Each piece is given by the time after the start of `s` at which it
+/// starts, with the time after the start of `other` at which the other
+/// agent has got a distance `d` beyond the end of the piece, if it does.
+/// A piece goes as far as the other agent is ahead by the plan, but at
+/// least the distance `d`, so that the agent follows without delay unless
+/// it follows closely.
+fn following(
+    (s, vel): (&Segment, &ConstVel2D),
+    (other, other_vel): (&Segment, &ConstVel2D),
+    (t, offset): (f64, f64),
+    d: f64,
+    tol: f64,
+) -> Vec<(f64, Option<f64>)> {
+    let dir = (s.end.x - s.start.x).signum();
+    let mut pieces = vec![];
+    let mut t = t;
+    loop {
+        let x = s.position_at(vel, t).x;
+        let remaining = (s.end.x - x) * dir;
+        if remaining <= tol {
+            return pieces;
+        }
+        let ahead = (other.position_at(other_vel, t + offset).x - x) * dir - d;
+        let step = if d <= tol {
+            remaining
+        } else {
+            f64::min(f64::max(ahead, d), remaining)
+        };
+        let x = x + dir * step;
+        let other_t = reaches(other, other_vel, x + dir * d, tol);
+        pieces.push((t, other_t));
+        match (other_t, reaches(s, vel, x, tol)) {
+            (Some(_), Some(next)) if step < remaining => t = next,
+            _ => return pieces,
+        }
+    }
+}
+
+impl Routing {
+    /// The ordered command streams of the agents, one after the other in
+    /// the order of the routing. Routes have to be in solver coordinates,
+    /// as the agents keep their distance along its x-axis.
+    ///
+    /// Where a move takes an agent within the safety distance of the range
+    /// another agent covered during a command planned to be completed by
+    /// then, the move is split at that point, and the rest waits for the
+    /// command to be completed. If the agent follows the other agent moving
+    /// ahead of it, the move of the other agent is split as well, and each
+    /// piece of the rest waits for the other agent to have got the safety
+    /// distance beyond where the piece ends.
+    /// Controllers that honour these sync points keep the order in which the
+    /// agents pass each zone even if their timing drifts. Agents on roads are
+    /// not synchronised, as the solver keeps them apart by reserving the
+    /// roads.
+    pub fn commands(&self, config: &SolverConfig) -> Vec<TimedCommand> {
+        let tol = config.tolerance;
+        let streams = self
+            .routes
+            .iter()
+            .map(|(agent, paths)| {
+                let mut out = vec![];
+                let mut position = agent.position;
+                for p in paths.iter() {
+                    path_commands(&agent.name, p, position, &mut out);
+                    position = p.action.target;
+                }
+                out
+            })
+            .collect::<Vec<_>>();
+        // The times after their start at which the commands are split, each
+        // with the pieces of commands of other agents to wait for from then
+        // on. A piece is given by the time after the start of its command at
+        // which it ends, infinite for the last one.
+        let mut splits = streams
+            .iter()
+            .map(|s| vec![Vec::<(f64, Vec<(usize, usize, f64)>)>::new(); s.len()])
+            .collect::<Vec<_>>();
+        for (i, (a, _)) in self.routes.iter().enumerate() {
+            if a.on_roads() {
+                continue;
+            }
+            // The last piece of each other agent waited for, as the commands
+            // of the agent run one after the other.
+            let mut synced = HashMap::<usize, (usize, f64)>::new();
+            for (k, o) in streams[i].iter().enumerate() {
+                let Some(s) = o.segment.as_ref().filter(|_| {
+                    matches!(
+                        o.command.command,
+                        Command::MoveTo { .. } | Command::EvadeTo { .. }
+                    )
+                }) else {
+                    continue;
+                };
+                let mut waits = vec![];
+                for (j, (b, _)) in self.routes.iter().enumerate() {
+                    if i == j || b.on_roads() {
+                        continue;
+                    }
+                    let mut candidates = vec![];
+                    for (seq, p) in streams[j].iter().enumerate() {
+                        let d = config.safety_distance_at(
+                            a,
+                            b,
+                            s.x_speed(&a.velocity),
+                            p.segment.as_ref().map_or(0.0, |o| o.x_speed(&b.velocity)),
+                        );
+                        let Some(t) = entry(s, &a.velocity, p.x_min - d, p.x_max + d, tol) else {
+                            continue;
+                        };
+                        let at = o.command.t_start + t + tol;
+                        if p.command.t_end <= at {
+                            candidates.push((t, (seq, f64::INFINITY)));
+                        } else if let Some(other) = p.segment.as_ref().filter(|other| {
+                            let x = other.position_at(&b.velocity, at - p.command.t_start).x;
+                            p.command.t_start < at && follows(s, other, x)
+                        }) {
+                            // The agent follows the other one, which has to
+                            // be ahead by the safety distance at the end of
+                            // each piece of the move before it starts.
+                            let offset = o.command.t_start - p.command.t_start;
+                            let pieces = following(
+                                (s, &a.velocity),
+                                (other, &b.velocity),
+                                (t, offset),
+                                d,
+                                tol,
+                            );
+                            for (t, end) in pieces {
+                                match end {
+                                    Some(end) if end <= tol && seq > 0 => {
+                                        candidates.push((t, (seq - 1, f64::INFINITY)))
+                                    }
+                                    Some(end) if end <= tol => {}
+                                    Some(end)
+                                        if end < p.command.t_end - p.command.t_start - tol =>
+                                    {
+                                        splits[j][seq].push((end, vec![]));
+                                        candidates.push((t, (seq, end)))
+                                    }
+                                    _ => candidates.push((t, (seq, f64::INFINITY))),
+                                }
+                            }
+                        }
+                    }
+                    candidates.sort_by(|c1, c2| {
+                        c1.0.total_cmp(&c2.0)
+                            .then(c2.1 .0.cmp(&c1.1 .0))
+                            .then(c2.1 .1.total_cmp(&c1.1 .1))
+                    });
+                    // A piece is waited for with any later one entered at the
+                    // same time or before.
+                    let mut kept = vec![];
+                    for (t, (seq, end)) in candidates {
+                        let later = |&(m, e): &(usize, f64)| seq > m || (seq == m && end > e);
+                        if synced.get(&j).is_none_or(later) {
+                            kept.push((t, (seq, end)));
+                            synced.insert(j, (seq, end));
+                        }
+                    }
+                    waits.extend(kept.into_iter().map(|(t, (seq, end))| (t, (j, seq, end))));
+                }
+                waits.sort_by(|w1, w2| w1.0.total_cmp(&w2.0));
+                for (t, w) in waits {
+                    let t = if t <= tol { 0.0 } else { t };
+                    splits[i][k].push((t, vec![w]));
+                }
+            }
+        }
+        // Merge the splits of each command that are within the tolerance.
+        for ss in splits.iter_mut().flatten() {
+            ss.sort_by(|s1, s2| s1.0.total_cmp(&s2.0));
+            ss.dedup_by(|(t, ws), (last, m)| {
+                let merge = *t - *last <= tol;
+                if merge {
+                    m.append(ws);
+                }
+                merge
+            });
+        }
+        // The index of the first piece of each command once split.
+        let first = splits
+            .iter()
+            .map(|ss| {
+                ss.iter()
+                    .scan(0, |n, s| {
+                        let f = *n;
+                        *n += 1 + s.iter().filter(|(t, _)| *t > 0.0).count();
+                        Some(f)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut result = vec![];
+        for (i, (a, _)) in self.routes.iter().enumerate() {
+            let start = result.len();
+            for (k, o) in streams[i].iter().enumerate() {
+                let sync = |ws: &[(usize, usize, f64)]| {
+                    ws.iter()
+                        .map(|&(j, seq, end)| {
+                            let before = splits[j][seq]
+                                .iter()
+                                .filter(|(t, _)| *t > 0.0 && *t < end - tol)
+                                .count();
+                            SyncPoint {
+                                agent: self.routes[j].0.name.clone(),
+                                seq: first[j][seq] + before,
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let mut c = o.command.clone();
+                c.seq = result.len() - start;
+                for (t, ws) in splits[i][k].iter() {
+                    if *t == 0.0 {
+                        c.after = sync(ws);
+                        continue;
+                    }
+                    let s = o.segment.as_ref().unwrap();
+                    let p = s.position_at(&a.velocity, *t);
+                    let rest = TimedCommand {
+                        seq: c.seq + 1,
+                        t_start: o.command.t_start + t,
+                        after: sync(ws),
+                        ..c.clone()
+                    };
+                    c.t_end = rest.t_start;
+                    c.command = match c.command {
+                        Command::EvadeTo { .. } => Command::EvadeTo { x: p.x, y: p.y },
+                        _ => Command::MoveTo { x: p.x, y: p.y },
+                    };
+                    result.push(c);
+                    c = rest;
+                }
+                result.push(c);
+            }
+        }
+        result
+    }
+}
+
+impl Transform {
+    /// The command with the points it moves to transformed.
+    pub fn command(&self, c: &TimedCommand) -> TimedCommand {
+        let t = |x: f64, y: f64| self.apply(Coord { x, y });
+        let command = match c.command {
+            Command::MoveTo { x, y } => {
+                let p = t(x, y);
+                Command::MoveTo { x: p.x, y: p.y }
+            }
+            Command::EvadeTo { x, y } => {
+                let p = t(x, y);
+                Command::EvadeTo { x: p.x, y: p.y }
+            }
+            ref other => other.clone(),
+        };
+        TimedCommand {
+            command,
+            ..c.clone()
+        }
+    }
+}
+
+impl Scale {
+    /// The command with the points it moves to and its times converted.
+    pub fn command(&self, c: &TimedCommand) -> TimedCommand {
+        let command = match c.command {
+            Command::MoveTo { x, y } => Command::MoveTo {
+                x: x * self.length,
+                y: y * self.length,
+            },
+            Command::EvadeTo { x, y } => Command::EvadeTo {
+                x: x * self.length,
+                y: y * self.length,
+            },
+            Command::Wait { until } => Command::Wait {
+                until: until * self.time,
+            },
+            Command::Park { until } => Command::Park {
+                until: until * self.time,
+            },
+            ref other => other.clone(),
+        };
+        TimedCommand {
+            t_start: c.t_start * self.time,
+            t_end: c.t_end * self.time,
+            command,
+            ..c.clone()
+        }
+    }
+}
+
+/// The commands as JSON lines, one object per command.
+pub fn to_json_lines(commands: &[TimedCommand]) -> String {
+    commands
+        .iter()
+        .map(|c| serde_json::to_string(c).unwrap() + "\n")
+        .collect()
+}
+
+/// Quote a CSV field if it needs to.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// The commands as CSV with a header. Fields that do not apply to a
+/// command are empty, and the sync points are listed as `agent#seq`,
+/// separated by semicolons.
+pub fn to_csv(commands: &[TimedCommand]) -> String {
+    let mut out = format!("{}\n", CSV_HEADER);
+    for c in commands {
+        let (name, x, y, until, action, duration) = match &c.command {
+            Command::MoveTo { x, y } => ("MOVE_TO", Some(*x), Some(*y), None, None, None),
+            Command::EvadeTo { x, y } => ("EVADE_TO", Some(*x), Some(*y), None, None, None),
+            Command::Wait { until } => ("WAIT", None, None, Some(*until), None, None),
+            Command::Execute { action, duration } => {
+                ("EXECUTE", None, None, None, *action, Some(*duration))
+            }
+            Command::Park { until } => ("PARK", None, None, Some(*until), None, None),
+        };
+        let opt = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        let after = c
+            .after
+            .iter()
+            .map(|s| format!("{}#{}", s.agent, s.seq))
+            .collect::<Vec<_>>()
+            .join(";");
+        out.push_str(
+            &[
+                csv_field(&c.agent),
+                c.seq.to_string(),
+                name.to_string(),
+                c.t_start.to_string(),
+                c.t_end.to_string(),
+                opt(x),
+                opt(y),
+                opt(until),
+                action.map(|a| a.to_string()).unwrap_or_default(),
+                opt(duration),
+                csv_field(&after),
+            ]
+            .join(","),
+        );
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,262 @@
+use crate::actions::{
+    commands::{to_csv, to_json_lines, Command, SyncPoint, TimedCommand},
+    config::SolverConfig,
+    data::{Agent, ConstVel2D, Schedule},
+    fixtures::{self, action},
+    routes,
+    units::{Scale, Transform},
+};
+
+/// The agents of the fixtures, slowed down to a unit speed to keep the
+/// times round.
+fn agent(name: &str, x: f64, order: i64) -> Agent {
+    Agent {
+        velocity: ConstVel2D { x: 1.0, y: 1.0 },
+        ..fixtures::agent(name, x, order)
+    }
+}
+
+fn command(agent: &str, seq: usize, t: (f64, f64), command: Command) -> TimedCommand {
+    TimedCommand {
+        agent: String::from(agent),
+        seq,
+        t_start: t.0,
+        t_end: t.1,
+        command,
+        after: vec![],
+    }
+}
+
+#[test]
+fn test_commands() {
+    let west = agent("west", 10.0, 0);
+    let east = agent("east", 90.0, 1);
+    let schedule = Schedule {
+        actions: vec![action(&west, 50.0, 10.0), action(&east, 50.0, 10.0)],
+    };
+    let routing = routes(&[west, east], schedule).unwrap();
+    let commands = routing.commands(&SolverConfig::default());
+
+    // East sets off while west is still at work, and enters the safety
+    // distance of west only once west has finished and evaded, as it would
+    // follow west too closely otherwise.
+    assert_eq!(
+        commands,
+        vec![
+            command("west", 0, (0.0, 40.0), Command::MoveTo { x: 50.0, y: 5.0 }),
+            command(
+                "west",
+                1,
+                (40.0, 50.0),
+                Command::Execute {
+                    action: Some(0),
+                    duration: 10.0
+                }
+            ),
+            command(
+                "west",
+                2,
+                (50.0, 60.0),
+                Command::EvadeTo { x: 40.0, y: 5.0 }
+            ),
+            command("east", 0, (0.0, 20.0), Command::Wait { until: 20.0 }),
+            command("east", 1, (20.0, 50.0), Command::MoveTo { x: 60.0, y: 5.0 }),
+            TimedCommand {
+                after: vec![SyncPoint {
+                    agent: String::from("west"),
+                    seq: 2
+                }],
+                ..command("east", 2, (50.0, 60.0), Command::MoveTo { x: 50.0, y: 5.0 })
+            },
+            command(
+                "east",
+                3,
+                (60.0, 70.0),
+                Command::Execute {
+                    action: Some(1),
+                    duration: 10.0
+                }
+            ),
+        ]
+    );
+
+    let lines = to_json_lines(&commands);
+    assert_eq!(
+        lines.lines().nth(5).unwrap(),
+        r#"{"agent":"east","seq":2,"t_start":50.0,"t_end":60.0,"command":"MOVE_TO","x":50.0,"y":5.0,"after":[{"agent":"west","seq":2}]}"#
+    );
+    let parsed = lines
+        .lines()
+        .map(|l| serde_json::from_str::<TimedCommand>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(parsed, commands);
+
+    let csv = to_csv(&commands);
+    let rows = csv.lines().collect::<Vec<_>>();
+    assert_eq!(
+        rows[0],
+        "agent,seq,command,t_start,t_end,x,y,until,action,duration,after"
+    );
+    assert_eq!(rows[2], "west,1,EXECUTE,40,50,,,,0,10,");
+    assert_eq!(rows[4], "east,0,WAIT,0,20,,,20,,,");
+    assert_eq!(rows[6], "east,2,MOVE_TO,50,60,50,5,,,,west#2");
+}
+
+#[test]
+fn test_transform_command() {
+    let shift = Transform {
+        a: 1.0,
+        b: 0.0,
+        xoff: 100.0,
+        d: 0.0,
+        e: 1.0,
+        yoff: -5.0,
+    };
+    let c = command("crane", 0, (0.0, 10.0), Command::EvadeTo { x: 5.0, y: 5.0 });
+    assert_eq!(
+        shift.command(&c).command,
+        Command::EvadeTo { x: 105.0, y: 0.0 }
+    );
+    let c = command(
+        "crane, north",
+        1,
+        (10.0, 20.0),
+        Command::Wait { until: 20.0 },
+    );
+    assert_eq!(shift.command(&c), c);
+    let to_ms = Scale {
+        length: 1000.0,
+        time: 1000.0,
+    };
+    assert_eq!(to_ms.command(&c).command, Command::Wait { until: 20000.0 });
+    assert_eq!(to_ms.command(&c).t_start, 10000.0);
+    assert!(to_csv(&[c]).ends_with("\"crane, north\",1,WAIT,10,20,,,20,,,\n"));
+}
+
+#[test]
+fn test_commands_three_agents() {
+    let west = agent("west", 10.0, 0);
+    let middle = agent("middle", 50.0, 1);
+    let east = agent("east", 90.0, 2);
+    let schedule = Schedule {
+        actions: vec![
+            action(&west, 60.0, 10.0),
+            action(&east, 40.0, 10.0),
+            action(&middle, 50.0, 10.0),
+        ],
+    };
+    let routing = routes(&[west, middle, east], schedule).unwrap();
+    let commands = routing.commands(&SolverConfig::default());
+    let after = |agent: &str| {
+        commands
+            .iter()
+            .filter(|c| c.agent == agent)
+            .map(|c| {
+                c.after
+                    .iter()
+                    .map(|s| format!("{}#{}", s.agent, s.seq))
+                    .collect::<Vec<_>>()
+                    .join(";")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // West works at 60 and then evades to 20, followed by middle and east
+    // in a convoy. The moves are split at every safety distance, and each
+    // piece waits for the pieces of the agents ahead of it, east for both
+    // west and middle.
+    assert_eq!(after("west"), vec!["", "middle#0", "", "", "", "", ""]);
+    assert_eq!(
+        after("middle"),
+        vec!["", "", "west#3", "west#4", "west#5", "west#6", "", "east#7", "east#8", ""]
+    );
+    assert_eq!(
+        after("east"),
+        vec![
+            "",
+            "",
+            "middle#2",
+            "west#3;middle#3",
+            "west#4;middle#4",
+            "west#5;middle#5",
+            "",
+            "west#6",
+            ""
+        ]
+    );
+    assert!(commands
+        .iter()
+        .filter(|c| matches!(c.command, Command::EvadeTo { .. }) && c.agent != "middle")
+        .all(|c| c.t_end - c.t_start == 10.0));
+    assert_eq!(
+        commands
+            .iter()
+            .filter(|c| c.agent == "middle")
+            .map(|c| c.command.clone())
+            .collect::<Vec<_>>()[2..6],
+        [60.0, 50.0, 40.0, 30.0].map(|x| Command::EvadeTo { x, y: 5.0 })
+    );
+}
+
+#[test]
+fn test_export() {
+    let commands = vec![
+        command("crane", 0, (0.0, 2.5), Command::MoveTo { x: 1.5, y: 5.0 }),
+        TimedCommand {
+            after: vec![
+                SyncPoint {
+                    agent: String::from("gantry"),
+                    seq: 0,
+                },
+                SyncPoint {
+                    agent: String::from("crane, \"north\""),
+                    seq: 3,
+                },
+            ],
+            ..command("crane", 1, (2.5, 4.0), Command::EvadeTo { x: -1.0, y: 5.0 })
+        },
+        command("crane", 2, (4.0, 6.0), Command::Wait { until: 6.0 }),
+        command(
+            "crane",
+            3,
+            (6.0, 9.0),
+            Command::Execute {
+                action: None,
+                duration: 2.0,
+            },
+        ),
+        command("crane", 4, (9.0, 20.0), Command::Park { until: 20.0 }),
+    ];
+
+    assert_eq!(
+        to_csv(&commands),
+        [
+            "agent,seq,command,t_start,t_end,x,y,until,action,duration,after",
+            "crane,0,MOVE_TO,0,2.5,1.5,5,,,,",
+            "crane,1,EVADE_TO,2.5,4,-1,5,,,,\"gantry#0;crane, \"\"north\"\"#3\"",
+            "crane,2,WAIT,4,6,,,6,,,",
+            "crane,3,EXECUTE,6,9,,,,,2,",
+            "crane,4,PARK,9,20,,,20,,,",
+            "",
+        ]
+        .join("\n")
+    );
+
+    let lines = to_json_lines(&commands);
+    assert_eq!(
+        lines.lines().collect::<Vec<_>>(),
+        vec![
+            r#"{"agent":"crane","seq":0,"t_start":0.0,"t_end":2.5,"command":"MOVE_TO","x":1.5,"y":5.0}"#,
+            r#"{"agent":"crane","seq":1,"t_start":2.5,"t_end":4.0,"command":"EVADE_TO","x":-1.0,"y":5.0,"after":[{"agent":"gantry","seq":0},{"agent":"crane, \"north\"","seq":3}]}"#,
+            r#"{"agent":"crane","seq":2,"t_start":4.0,"t_end":6.0,"command":"WAIT","until":6.0}"#,
+            r#"{"agent":"crane","seq":3,"t_start":6.0,"t_end":9.0,"command":"EXECUTE","action":null,"duration":2.0}"#,
+            r#"{"agent":"crane","seq":4,"t_start":9.0,"t_end":20.0,"command":"PARK","until":20.0}"#,
+        ]
+    );
+    assert!(lines.ends_with('\n'));
+    let parsed = lines
+        .lines()
+        .map(|l| serde_json::from_str::<TimedCommand>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(parsed, commands);
+}
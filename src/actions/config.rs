@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use crate::actions::data::{Action, ActionType, Agent};
+
+/// The numeric settings of the solver. Times and distances are in the units
+/// of the input, which are the units of the scenario in a scenario file.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SolverConfig {
+    /// The time step to which the solver rounds up the times agents wait
+    /// for each other, and rounds down the latest departures to parking.
+    /// Zero leaves the times continuous.
+    #[serde(default = "SolverConfig::default_quantum")]
+    pub quantum: f64,
+    /// The distance below which positions count as equal, so that agents
+    /// exactly one safety distance apart are not in conflict due to the
+    /// rounding of floating-point numbers.
+    #[serde(default = "SolverConfig::default_tolerance")]
+    pub tolerance: f64,
+    /// The distance agents keep on top of their safety distance
+    #[serde(default)]
+    pub margin: f64,
+    /// The share of the duration of a scheduled action the solver reserves
+    /// on top of it, e.g. `0.1` for 10 %
+    #[serde(default)]
+    pub slack: f64,
+    /// The number of standard deviations of the duration of a scheduled
+    /// action the solver reserves on top of it
+    #[serde(default)]
+    pub deviations: f64,
+    /// The time it takes agents to react, during which they keep moving: the
+    /// distance agents keep grows by the distance both cover along the
+    /// x-axis meanwhile at the speed they move at
+    #[serde(default)]
+    pub reaction_time: f64,
+}
+
+impl SolverConfig {
+    fn default_quantum() -> f64 {
+        1.0
+    }
+
+    fn default_tolerance() -> f64 {
+        1e-6
+    }
+
+    /// The distance along the x-axis the solver keeps between two agents at
+    /// rest.
+    pub fn safety_distance(&self, a1: &Agent, a2: &Agent) -> f64 {
+        self.safety_distance_at(a1, a2, 0.0, 0.0)
+    }
+
+    /// The distance along the x-axis the solver keeps between two agents
+    /// while they move along it at the speeds `v1` and `v2`, which grows by
+    /// the distance both cover in the reaction time.
+    pub fn safety_distance_at(&self, a1: &Agent, a2: &Agent, v1: f64, v2: f64) -> f64 {
+        a1.safety_x(a2) + self.margin + self.reaction_time * (v1.abs() + v2.abs())
+    }
+
+    /// The time the solver reserves after an action in case it takes
+    /// longer than planned. Only scheduled actions are buffered.
+    pub fn buffer(&self, action: &Action) -> f64 {
+        match action.r#type {
+            ActionType::Scheduled => self.scheduled_buffer(action.duration, action.std_dev),
+            _ => 0.0,
+        }
+    }
+
+    /// The time the solver reserves after a scheduled action of the
+    /// duration and its standard deviation.
+    pub fn scheduled_buffer(&self, duration: f64, std_dev: f64) -> f64 {
+        self.slack * duration + self.deviations * std_dev
+    }
+
+    /// The earliest time of the quantum at or after `t`.
+    pub fn round_up(&self, t: f64) -> f64 {
+        f64::max(self.round(t, f64::ceil), t)
+    }
+
+    /// The latest time of the quantum at or before `t`.
+    pub fn round_down(&self, t: f64) -> f64 {
+        f64::min(self.round(t, f64::floor), t)
+    }
+
+    /// Round `t` to a multiple of the quantum. Times within a millionth of
+    /// a quantum of a multiple count as on it, so that the noise of times
+    /// converted between units does not add a quantum. Rounding up and down
+    /// keeps such times as they are rather than move them the wrong way.
+    fn round(&self, t: f64, f: fn(f64) -> f64) -> f64 {
+        if self.quantum > 0.0 {
+            let steps = t / self.quantum;
+            if (steps - steps.round()).abs() < 1e-6 {
+                steps.round() * self.quantum
+            } else {
+                f(steps) * self.quantum
+            }
+        } else {
+            t
+        }
+    }
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            quantum: SolverConfig::default_quantum(),
+            tolerance: SolverConfig::default_tolerance(),
+            margin: 0.0,
+            slack: 0.0,
+            deviations: 0.0,
+            reaction_time: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
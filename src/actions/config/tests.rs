@@ -0,0 +1,71 @@
+use crate::actions::config::SolverConfig;
+
+#[test]
+fn test_rounding() {
+    let config: SolverConfig = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(config, SolverConfig::default());
+    assert_eq!(
+        (config.round_up(10.2), config.round_down(10.8)),
+        (11.0, 10.0)
+    );
+
+    let config = SolverConfig {
+        quantum: 0.5,
+        ..SolverConfig::default()
+    };
+    assert_eq!(
+        (config.round_up(10.2), config.round_down(10.8)),
+        (10.5, 10.5)
+    );
+
+    // Noise of converted times does not move them to the next step, nor
+    // back across the step they are on.
+    let config = SolverConfig {
+        quantum: 0.001,
+        ..SolverConfig::default()
+    };
+    assert_eq!(config.round_up(10.5 + 1e-12), 10.5 + 1e-12);
+    assert_eq!(config.round_up(10.5 - 1e-12), 10.5);
+    assert_eq!(config.round_down(10.5 - 1e-12), 10.5 - 1e-12);
+    assert_eq!(config.round_down(10.5 + 1e-12), 10.5);
+
+    let config = SolverConfig {
+        quantum: 0.0,
+        ..SolverConfig::default()
+    };
+    assert_eq!(
+        (config.round_up(10.2), config.round_down(10.8)),
+        (10.2, 10.8)
+    );
+}
+
+#[test]
+fn test_rounding_near_step() {
+    let config = SolverConfig {
+        quantum: 0.5,
+        ..SolverConfig::default()
+    };
+    // Times on a step stay there.
+    assert_eq!(
+        (config.round_up(10.5), config.round_down(10.5)),
+        (10.5, 10.5)
+    );
+    assert_eq!((config.round_up(0.0), config.round_down(0.0)), (0.0, 0.0));
+
+    // Times just past a step leave it, in either direction.
+    assert_eq!(
+        (config.round_up(10.5001), config.round_down(10.5001)),
+        (11.0, 10.5)
+    );
+    assert_eq!(
+        (config.round_up(10.4999), config.round_down(10.4999)),
+        (10.5, 10.0)
+    );
+
+    // Within a millionth of a quantum a time counts as on the step, beyond
+    // that it does not.
+    assert_eq!(config.round_up(10.5 + 1e-7), 10.5 + 1e-7);
+    assert_eq!(config.round_down(10.5 - 1e-7), 10.5 - 1e-7);
+    assert_eq!(config.round_up(10.5 + 1e-6), 11.0);
+    assert_eq!(config.round_down(10.5 - 1e-6), 10.0);
+}
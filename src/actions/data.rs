@@ -1,23 +1,97 @@
-use geo::{Coord, CoordNum, LineString, Polygon};
-use serde::{Deserialize, Serialize};
+use geo::{
+    BooleanOps, Closest, ClosestPoint, Coord, CoordNum, LineString, MultiPolygon, Point, Polygon,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::actions::site::TimeWindow;
 
 /// An agent is a named entity that can execute actions
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Agent {
     /// The name of the agent
     pub name: String,
-    /// The reach of the agent, represented as a polygon
-    #[serde(with = "PolygonSerde")]
-    pub reach: Polygon,
+    /// The reach of the agent, i.e. the area it works in, represented as
+    /// one or more polygons. Agents bound to the road network have none.
+    #[serde(with = "MultiPolygonSerde", default = "Agent::no_area")]
+    pub reach: MultiPolygon,
+    /// The corridors the agent may travel through outside its reach, e.g.
+    /// to move between disjoint parts of the reach. Targets never lie in a
+    /// corridor.
+    #[serde(with = "MultiPolygonSerde", default = "Agent::no_area")]
+    pub corridors: MultiPolygon,
     /// The position of the agent, represented as a coordinate
     #[serde(with = "CoordSerde")]
     pub position: Coord<f64>,
     /// The velocity of the agent in two dimensions
     pub velocity: ConstVel2D,
+    /// How the agent combines the motion along both axes
+    #[serde(default)]
+    pub motion: Motion,
     /// The safety distance in the x-axis direction
     pub safety_x: f64,
     /// The order of the agent
     pub order: i64,
+    /// The capability tags of the agent, e.g. the equipment it carries
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// The windows in which the agent is parked and cannot take actions,
+    /// e.g. for maintenance
+    #[serde(default)]
+    pub unavailable: Vec<Unavailability>,
+    /// The planner used to find the paths of the agent
+    #[serde(default)]
+    pub planner: Planner,
+}
+
+/// The path planner of an agent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Planner {
+    /// Search the visibility graph of the vertices of the reach. The paths
+    /// are the fastest, but the graph gets dense for detailed polygons.
+    #[default]
+    Visibility,
+    /// Search a grid of square cells rasterised from the reach, smoothed
+    /// with any-angle moves. Passages narrower than a cell are not found.
+    Grid {
+        /// The side of a cell
+        cell: f64,
+    },
+    /// Drive along the road network of the site instead of through the
+    /// reach. Targets snap to the nearest node of the network.
+    Road,
+}
+
+/// A time window in which an agent is parked and cannot take actions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Unavailability {
+    /// The time the agent is unavailable
+    pub window: TimeWindow,
+    /// Where the agent is parked during the window
+    pub parking: Parking,
+}
+
+/// The place an unavailable agent is parked.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Parking {
+    /// The agent is parked at the given position
+    #[serde(with = "CoordSerde")]
+    Position(Coord),
+    /// The agent is parked anywhere inside the given zone
+    #[serde(with = "PolygonSerde")]
+    Zone(Polygon),
+}
+
+impl Parking {
+    /// The point of the parking closest to the position.
+    pub fn closest(&self, position: Coord) -> Coord {
+        match self {
+            Parking::Position(c) => *c,
+            Parking::Zone(zone) => match zone.closest_point(&Point::from(position)) {
+                Closest::Intersection(p) | Closest::SinglePoint(p) => p.0,
+                Closest::Indeterminate => zone.exterior().0[0],
+            },
+        }
+    }
 }
 
 /// Motion of constant velocity in two dimensions.
@@ -29,8 +103,32 @@ pub struct ConstVel2D {
     pub y: f64,
 }
 
+/// How an agent moves between two points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Motion {
+    /// Both axes move at the same time, so a move takes as long as its
+    /// slowest axis
+    #[default]
+    Simultaneous,
+    /// One axis moves at a time, so a move is an L-shaped pair of
+    /// axis-aligned segments through a corner point
+    AxisAligned {
+        /// The axis that moves first
+        first: Axis,
+    },
+}
+
+/// An axis of the plane
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    /// The x-axis
+    X,
+    /// The y-axis
+    Y,
+}
+
 /// The type of an action
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ActionType {
     /// A scheduled action
     Scheduled,
@@ -38,26 +136,88 @@ pub enum ActionType {
     Evasive,
     /// An idle action
     Idle,
+    /// The agent is parked during an unavailability window
+    Parked,
 }
 
 /// An action is an event that is executed by an agent at a given location.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Action {
     /// The agent executing the action
     pub agent: Agent,
     /// The target location of the action
+    #[serde(with = "CoordSerde")]
     pub target: Coord,
     /// The duration of the action
     pub duration: f64,
     /// The type of the action
     pub r#type: ActionType,
+    /// The capabilities an agent needs to execute the action
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// The standard deviation of the duration, for which the solver plans a
+    /// buffer
+    #[serde(default)]
+    pub std_dev: f64,
+}
+
+impl Default for Agent {
+    /// An unnamed agent at the origin without reach, which does not move.
+    fn default() -> Self {
+        Agent {
+            name: String::new(),
+            reach: Agent::no_area(),
+            corridors: Agent::no_area(),
+            position: Coord { x: 0.0, y: 0.0 },
+            velocity: ConstVel2D { x: 0.0, y: 0.0 },
+            motion: Motion::default(),
+            safety_x: 0.0,
+            order: 0,
+            capabilities: vec![],
+            unavailable: vec![],
+            planner: Planner::default(),
+        }
+    }
 }
 
 impl Agent {
+    fn no_area() -> MultiPolygon {
+        MultiPolygon(vec![])
+    }
+
+    /// The area the agent may travel through: its reach and corridors.
+    pub fn region(&self) -> MultiPolygon {
+        if self.corridors.0.is_empty() {
+            self.reach.clone()
+        } else {
+            self.reach.union(&self.corridors)
+        }
+    }
+
+    /// Whether the agent is bound to the road network of the site.
+    pub fn on_roads(&self) -> bool {
+        self.planner == Planner::Road
+    }
+
     /// Calculates the safety distance in the x-axis direction between this agent and another agent.
     pub fn safety_x(&self, other: &Agent) -> f64 {
         f64::max(self.safety_x, other.safety_x)
     }
+
+    /// The required capabilities that this agent lacks.
+    pub fn missing_capabilities(&self, requires: &[String]) -> Vec<String> {
+        requires
+            .iter()
+            .filter(|c| !self.capabilities.contains(c))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether this agent has all the required capabilities, e.g. those of
+    /// an action.
+    pub fn can_execute(&self, requires: &[String]) -> bool {
+        self.missing_capabilities(requires).is_empty()
+    }
 }
 
 /// A schedule is a list of events, determining the absolute order in which
@@ -69,18 +229,20 @@ pub struct Schedule {
 }
 
 /// A segment of a path
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Segment {
     /// The starting coordinate of the segment
+    #[serde(with = "CoordSerde")]
     pub start: Coord,
     /// The ending coordinate of the segment
+    #[serde(with = "CoordSerde")]
     pub end: Coord,
     /// The duration of the segment
     pub duration: f64,
 }
 
 /// A path is a list of moves necessary to arrive at the given action.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Path {
     /// The list of moves that take the agent from the previous action to the current action
     pub moves: Vec<Segment>,
@@ -90,10 +252,15 @@ pub struct Path {
     pub t_start: f64,
     /// The end time of the path, which is after the present action is finished
     pub t_end: f64,
+    /// The index of the scheduled action the solver was executing when it
+    /// planned the path, none for the initial position and the parking
+    /// after the last action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
 }
 
 /// A point in 3D space-time
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct PointST {
     /// The x-coordinate of the point
     pub x: f64,
@@ -106,7 +273,7 @@ pub struct PointST {
 /// Serializer and deserializer for `Coord` type
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(remote = "Coord")]
-struct CoordSerde<T = f64>
+pub(crate) struct CoordSerde<T = f64>
 where
     T: CoordNum,
 {
@@ -140,7 +307,7 @@ impl<T: CoordNum> From<CoordSerdeForPolygon<T>> for Coord<T> {
 /// Serializer and deserializer for `Polygon` type
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "Polygon")]
-struct PolygonSerde<T = f64>
+pub(crate) struct PolygonSerde<T = f64>
 where
     T: CoordNum,
 {
@@ -164,7 +331,7 @@ impl<T: CoordNum> PolygonSerde<T> {
 
     fn get_interiors(pol: &Polygon<T>) -> Vec<Vec<CoordSerdeForPolygon<T>>> {
         pol.interiors()
-            .into_iter()
+            .iter()
             .map(|ls| {
                 ls.clone()
                     .0
@@ -176,6 +343,40 @@ impl<T: CoordNum> PolygonSerde<T> {
     }
 }
 
+/// A polygon in the form of `PolygonSerde`
+#[derive(Serialize, Deserialize)]
+struct PolygonForm(#[serde(with = "PolygonSerde")] Polygon);
+
+/// The forms of a multi-polygon: a single polygon, as for agents with a
+/// connected reach, or a list of polygons
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MultiPolygonForm {
+    One(#[serde(with = "PolygonSerde")] Polygon),
+    Many(Vec<PolygonForm>),
+}
+
+/// Serializer and deserializer for `MultiPolygon` type. Multi-polygons of
+/// a single part are written as a polygon.
+pub(crate) struct MultiPolygonSerde;
+
+impl MultiPolygonSerde {
+    pub(crate) fn serialize<S: Serializer>(mp: &MultiPolygon, s: S) -> Result<S::Ok, S::Error> {
+        match &mp.0[..] {
+            [p] => MultiPolygonForm::One(p.clone()),
+            ps => MultiPolygonForm::Many(ps.iter().cloned().map(PolygonForm).collect()),
+        }
+        .serialize(s)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<MultiPolygon, D::Error> {
+        Ok(match MultiPolygonForm::deserialize(d)? {
+            MultiPolygonForm::One(p) => MultiPolygon(vec![p]),
+            MultiPolygonForm::Many(ps) => MultiPolygon(ps.into_iter().map(|p| p.0).collect()),
+        })
+    }
+}
+
 impl<T: CoordNum> From<PolygonSerde<T>> for Polygon<T> {
     fn from(value: PolygonSerde<T>) -> Self {
         Polygon::new(
@@ -0,0 +1,318 @@
+use std::fmt;
+
+use geo::Coord;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::actions::{
+    data::{ActionType, CoordSerde, Path},
+    trace::{Decision, TraceEvent},
+    Routing,
+};
+
+/// Why an agent idles or evades.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum Cause {
+    /// The agent waits for another agent, to keep its distance.
+    Waiting {
+        /// The name of the agent waited for
+        agent: String,
+        /// The index of the path of the agent waited for
+        path: usize,
+        /// The type of the action of that path
+        r#type: ActionType,
+        /// The index of the scheduled action the path belongs to
+        step: Option<usize>,
+        /// The x-position of the agent waited for
+        x: f64,
+        /// The time the agent waited for is at the position
+        t: f64,
+        /// The distance the agents keep along the x-axis
+        distance: f64,
+    },
+    /// The agent waits for the closure of an obstacle to end.
+    Closure,
+    /// The agent makes way for another agent moving to its target.
+    MakingWay {
+        /// The name of the agent made way for
+        agent: String,
+        /// The index of the path of the agent made way for
+        path: usize,
+        /// The type of the action of that path
+        r#type: ActionType,
+        /// The index of the scheduled action the path belongs to
+        step: Option<usize>,
+        /// The x-position of the target of the agent made way for
+        x: f64,
+        /// The distance the agents keep along the x-axis
+        distance: f64,
+    },
+}
+
+/// An idle or evasive path with its immediate cause.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Link {
+    /// The name of the agent
+    pub agent: String,
+    /// The index of the path in the route of the agent
+    pub path: usize,
+    /// The type of the action of the path, idle or evasive
+    pub r#type: ActionType,
+    /// The start time of the path
+    pub t_start: f64,
+    /// The end time of the path
+    pub t_end: f64,
+    /// The target of the path
+    #[serde(with = "CoordSerde")]
+    pub target: Coord,
+    /// The cause of the path
+    pub cause: Cause,
+}
+
+/// The chain of causes of a path. The first link explains the path itself,
+/// every further link the idle or evasive path of the agent the previous
+/// cause names. Times and positions are those of the solver.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Explanation {
+    /// The links of the chain
+    pub links: Vec<Link>,
+}
+
+/// The reason a path cannot be explained.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExplainError {
+    /// The routing holds no trace of the solver
+    NoTrace,
+    /// The agent or the path does not exist
+    UnknownPath {
+        /// The name of the agent
+        agent: String,
+        /// The index of the path
+        path: usize,
+    },
+    /// The path neither idles nor evades
+    NotExplainable(ActionType),
+    /// The trace holds no decision for the path, e.g. for agents bound to
+    /// the road network or waiting to park
+    NotTraced,
+}
+
+impl fmt::Display for ExplainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExplainError::NoTrace => write!(f, "the routing was computed without a trace"),
+            ExplainError::UnknownPath { agent, path } => {
+                write!(f, "agent '{}' has no path #{}", agent, path)
+            }
+            ExplainError::NotExplainable(t) => {
+                write!(f, "only idle and evasive paths are explained, not {:?}", t)
+            }
+            ExplainError::NotTraced => write!(f, "the trace holds no decision for the path"),
+        }
+    }
+}
+
+impl std::error::Error for ExplainError {}
+
+impl Cause {
+    /// The other agent the cause names, with the index of its path.
+    fn other(&self) -> Option<(String, usize)> {
+        match self {
+            Cause::Waiting { agent, path, .. } | Cause::MakingWay { agent, path, .. } => {
+                Some((agent.clone(), *path))
+            }
+            Cause::Closure => None,
+        }
+    }
+}
+
+/// What an agent is doing on a path of the given type.
+fn doing(r#type: &ActionType, step: Option<usize>) -> String {
+    match (r#type, step) {
+        (ActionType::Scheduled, Some(s)) => format!("executing action #{}", s),
+        (ActionType::Scheduled, None) => String::from("executing an action"),
+        (ActionType::Evasive, _) => String::from("evading"),
+        (ActionType::Idle, _) => String::from("idling"),
+        (ActionType::Parked, _) => String::from("parking"),
+    }
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cause::Waiting {
+                agent,
+                r#type,
+                step,
+                x,
+                distance,
+                ..
+            } => write!(
+                f,
+                "{} was at x={} {}, which needed clearance of {}",
+                agent,
+                x,
+                doing(r#type, *step),
+                distance
+            ),
+            Cause::Closure => write!(f, "an obstacle was closed"),
+            Cause::MakingWay {
+                agent,
+                r#type,
+                step,
+                x,
+                distance,
+                ..
+            } => write!(
+                f,
+                "{} needed clearance of {} to reach x={}, {}",
+                agent,
+                distance,
+                x,
+                doing(r#type, *step)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.r#type {
+            ActionType::Idle => write!(
+                f,
+                "{} idled {} from {}",
+                self.agent,
+                self.t_end - self.t_start,
+                self.t_start
+            )?,
+            _ => write!(f, "{} evaded to x={}", self.agent, self.target.x)?,
+        }
+        write!(f, " because {}", self.cause)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.links.iter().join("; "))
+    }
+}
+
+impl Routing {
+    /// Explain why the agent idles or evades on the path with the given
+    /// index, following the chain of causes recorded in the trace.
+    pub fn explain(&self, agent: &str, path: usize) -> Result<Explanation, ExplainError> {
+        let mut links = vec![self.link(agent, path)?];
+        while let Some((agent, path)) = links.last().unwrap().cause.other() {
+            if links.iter().any(|l| l.agent == agent && l.path == path) {
+                break;
+            }
+            // The chain ends at a path that neither idles nor evades, or
+            // where the trace has no answer.
+            match self.link(&agent, path) {
+                Ok(link) => links.push(link),
+                Err(_) => break,
+            }
+        }
+        Ok(Explanation { links })
+    }
+
+    fn paths(&self, agent: &str) -> Option<&Vec<Path>> {
+        self.routes
+            .iter()
+            .find(|(a, _)| a.name == agent)
+            .map(|(_, ps)| ps)
+    }
+
+    /// The immediate cause of an idle or evasive path.
+    fn link(&self, agent: &str, index: usize) -> Result<Link, ExplainError> {
+        let events = &self.trace.as_ref().ok_or(ExplainError::NoTrace)?.events;
+        let path = self
+            .paths(agent)
+            .and_then(|ps| ps.get(index))
+            .ok_or_else(|| ExplainError::UnknownPath {
+                agent: agent.to_string(),
+                path: index,
+            })?;
+        let cause = match path.action.r#type {
+            ActionType::Idle => self.waiting(events, agent, path),
+            ActionType::Evasive => self.making_way(events, agent, path),
+            ref t => return Err(ExplainError::NotExplainable(t.clone())),
+        }
+        .ok_or(ExplainError::NotTraced)?;
+        Ok(Link {
+            agent: agent.to_string(),
+            path: index,
+            r#type: path.action.r#type.clone(),
+            t_start: path.t_start,
+            t_end: path.t_end,
+            target: path.action.target,
+            cause,
+        })
+    }
+
+    /// The cause of an idle path, from the wait recorded for it.
+    fn waiting(&self, events: &[TraceEvent], agent: &str, path: &Path) -> Option<Cause> {
+        let blocking = events.iter().rev().find_map(|e| match &e.decision {
+            Decision::Wait { blocking, until }
+                if e.agent == agent && e.time == path.t_start && *until == path.t_end =>
+            {
+                Some(blocking)
+            }
+            _ => None,
+        })?;
+        let Some(b) = blocking else {
+            return Some(Cause::Closure);
+        };
+        // The point waited for ends a path of the other agent, or lies
+        // within one.
+        let ps = self.paths(&b.agent)?;
+        let i = ps
+            .iter()
+            .position(|p| p.t_start < b.t && b.t <= p.t_end)
+            .or_else(|| ps.iter().position(|p| p.t_start <= b.t && b.t <= p.t_end))?;
+        Some(Cause::Waiting {
+            agent: b.agent.clone(),
+            path: i,
+            r#type: ps[i].action.r#type.clone(),
+            step: ps[i].step,
+            x: b.position.x,
+            t: b.t,
+            distance: b.distance,
+        })
+    }
+
+    /// The cause of an evasive path, from the conflict that made the solver
+    /// send the agent away.
+    fn making_way(&self, events: &[TraceEvent], agent: &str, path: &Path) -> Option<Cause> {
+        let k = events.iter().rposition(|e| match &e.decision {
+            Decision::Evasion { agent: a, target } => {
+                a == agent && *target == path.action.target && e.time <= path.t_start
+            }
+            _ => false,
+        })?;
+        let (event, distance) = match &events[k.checked_sub(1)?] {
+            e @ TraceEvent {
+                decision: Decision::Conflict { distance, .. },
+                ..
+            } => (e, *distance),
+            _ => return None,
+        };
+        let ps = self.paths(&event.agent)?;
+        let i = ps.iter().position(|p| {
+            p.action.target == event.target
+                && p.action.r#type == event.r#type
+                && p.t_start >= event.time
+        })?;
+        Some(Cause::MakingWay {
+            agent: event.agent.clone(),
+            path: i,
+            r#type: event.r#type.clone(),
+            step: ps[i].step,
+            x: event.target.x,
+            distance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,98 @@
+use crate::actions::{
+    data::{ActionType, Agent, Schedule},
+    explain::{Cause, ExplainError},
+    fixtures::{action, agent},
+    routes, Routing, Solver,
+};
+
+/// The other agent works at x = 40 until 25, then evades to 60 for the
+/// crane, which waits until 15 to arrive at 50 as the other agent leaves.
+fn schedule() -> (Vec<Agent>, Schedule) {
+    let agents = vec![agent("agent-0", 10.0, 0), agent("agent-1", 30.0, 1)];
+    let schedule = Schedule {
+        actions: vec![
+            action(&agents[1], 40.0, 20.0),
+            action(&agents[0], 50.0, 0.0),
+        ],
+    };
+    (agents, schedule)
+}
+
+fn traced() -> Routing {
+    let (agents, schedule) = schedule();
+    let solver = Solver {
+        trace: true,
+        ..Solver::default()
+    };
+    solver.routes(&agents, schedule).unwrap()
+}
+
+#[test]
+fn test_explain_idle() {
+    let routing = traced();
+    assert_eq!(routing.routes[0].1[1].action.r#type, ActionType::Idle);
+
+    let explanation = routing.explain("agent-0", 1).unwrap();
+    assert_eq!(explanation.links.len(), 1);
+    assert_eq!(
+        explanation.links[0].cause,
+        Cause::Waiting {
+            agent: String::from("agent-1"),
+            path: 1,
+            r#type: ActionType::Scheduled,
+            step: Some(0),
+            x: 40.0,
+            t: 25.0,
+            distance: 10.0,
+        }
+    );
+    assert_eq!(
+        explanation.to_string(),
+        "agent-0 idled 15 from 0 because agent-1 was at x=40 executing action #0, \
+         which needed clearance of 10"
+    );
+}
+
+#[test]
+fn test_explain_evasion() {
+    let routing = traced();
+    assert_eq!(routing.routes[1].1[2].action.r#type, ActionType::Evasive);
+
+    let explanation = routing.explain("agent-1", 2).unwrap();
+    assert_eq!(
+        explanation.links[0].cause,
+        Cause::MakingWay {
+            agent: String::from("agent-0"),
+            path: 2,
+            r#type: ActionType::Scheduled,
+            step: Some(1),
+            x: 50.0,
+            distance: 10.0,
+        }
+    );
+    assert_eq!(
+        explanation.to_string(),
+        "agent-1 evaded to x=60 because agent-0 needed clearance of 10 to reach x=50, \
+         executing action #1"
+    );
+}
+
+#[test]
+fn test_explain_errors() {
+    let routing = traced();
+    assert_eq!(
+        routing.explain("agent-0", 2),
+        Err(ExplainError::NotExplainable(ActionType::Scheduled))
+    );
+    assert_eq!(
+        routing.explain("agent-2", 0),
+        Err(ExplainError::UnknownPath {
+            agent: String::from("agent-2"),
+            path: 0,
+        })
+    );
+
+    let (agents, schedule) = schedule();
+    let untraced = routes(&agents, schedule).unwrap();
+    assert_eq!(untraced.explain("agent-0", 1), Err(ExplainError::NoTrace));
+}
@@ -0,0 +1,40 @@
+//! Agents and actions shared by the unit tests and, with the `test-utils`
+//! feature, the integration tests.
+
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+
+use crate::actions::data::{Action, ActionType, Agent, ConstVel2D, Motion, Planner};
+
+/// An agent on a rail along the x-axis: its reach is 100 long and 10 wide,
+/// and it starts at `x` in the middle of the rail.
+pub fn agent(name: &str, x: f64, order: i64) -> Agent {
+    Agent {
+        name: String::from(name),
+        reach: Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        )
+        .into(),
+        corridors: MultiPolygon(vec![]),
+        position: Coord { x, y: 5.0 },
+        velocity: ConstVel2D { x: 2.0, y: 1.0 },
+        motion: Motion::Simultaneous,
+        safety_x: 10.0,
+        order,
+        capabilities: vec![],
+        unavailable: vec![],
+        planner: Planner::Visibility,
+    }
+}
+
+/// A scheduled action of the agent at `x` in the middle of its rail.
+pub fn action(agent: &Agent, x: f64, duration: f64) -> Action {
+    Action {
+        agent: agent.clone(),
+        target: Coord { x, y: 5.0 },
+        duration,
+        r#type: ActionType::Scheduled,
+        requires: vec![],
+        std_dev: 0.0,
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+
+use geo::{Coord, Geometry, LineString, MultiPolygon, Point, Polygon};
+use geojson::{Feature, FeatureCollection, JsonObject, JsonValue};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wkt::{ToWkt, TryFromWkt};
+
+use crate::actions::{
+    data::{Agent, MultiPolygonSerde, PolygonSerde},
+    site::Obstacle,
+    trace::{Decision, TraceEvent},
+    Routing,
+};
+
+/// An error reading agents or obstacles from GIS formats.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GisError {
+    /// The input is not valid GeoJSON
+    Parse(String),
+    /// A feature has no geometry, or one of the wrong type
+    Geometry {
+        /// The index of the feature
+        feature: usize,
+        /// A description of the problem
+        message: String,
+    },
+    /// The properties of a feature do not describe an agent or obstacle
+    Properties {
+        /// The index of the feature
+        feature: usize,
+        /// A description of the problem
+        message: String,
+    },
+}
+
+impl fmt::Display for GisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GisError::Parse(m) => write!(f, "invalid GeoJSON: {}", m),
+            GisError::Geometry { feature, message } => {
+                write!(f, "invalid geometry of feature #{}: {}", feature, message)
+            }
+            GisError::Properties { feature, message } => {
+                write!(f, "invalid properties of feature #{}: {}", feature, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GisError {}
+
+/// A feature with its geometry as well-known text, e.g. a row of a database
+/// table.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WktRecord {
+    /// The geometry of the feature
+    pub wkt: String,
+    /// The properties of the feature
+    #[serde(flatten)]
+    pub properties: JsonObject,
+}
+
+/// Read agents from a GeoJSON feature collection. The geometry of a feature
+/// is the reach of the agent, a polygon or a multi-polygon. Its properties
+/// are the other fields of the agent, with the position and velocity as
+/// `[x, y]` pairs.
+pub fn agents_from_geojson(s: &str) -> Result<Vec<Agent>, GisError> {
+    from_geojson(s)
+}
+
+/// Write agents as a GeoJSON feature collection.
+pub fn agents_to_geojson(agents: &[Agent]) -> String {
+    to_geojson(agents.iter().map(|a| (a.shape(), agent_properties(a))))
+}
+
+/// Read agents from records with their reach as well-known text.
+pub fn agents_from_wkt(records: &[WktRecord]) -> Result<Vec<Agent>, GisError> {
+    from_wkt(records)
+}
+
+/// Write agents as records with their reach as well-known text.
+pub fn agents_to_wkt(agents: &[Agent]) -> Vec<WktRecord> {
+    to_wkt(agents.iter().map(|a| (a.shape(), agent_properties(a))))
+}
+
+/// Read obstacles from a GeoJSON feature collection. The geometry of a
+/// feature is the area of the obstacle, its properties are the name and the
+/// optional window.
+pub fn obstacles_from_geojson(s: &str) -> Result<Vec<Obstacle>, GisError> {
+    from_geojson(s)
+}
+
+/// Write obstacles as a GeoJSON feature collection.
+pub fn obstacles_to_geojson(obstacles: &[Obstacle]) -> String {
+    to_geojson(
+        obstacles
+            .iter()
+            .map(|o| (o.shape(), obstacle_properties(o))),
+    )
+}
+
+/// Read obstacles from records with their area as well-known text.
+pub fn obstacles_from_wkt(records: &[WktRecord]) -> Result<Vec<Obstacle>, GisError> {
+    from_wkt(records)
+}
+
+/// Write obstacles as records with their area as well-known text.
+pub fn obstacles_to_wkt(obstacles: &[Obstacle]) -> Vec<WktRecord> {
+    to_wkt(
+        obstacles
+            .iter()
+            .map(|o| (o.shape(), obstacle_properties(o))),
+    )
+}
+
+/// Write a routing as a GeoJSON feature collection: the moves of every path
+/// as a line string, with the agent, the action and the times as
+/// properties, followed by the events of the trace, if recorded, as points
+/// where the decision applies, with the event as properties.
+pub fn routing_to_geojson(routing: &Routing) -> String {
+    let paths = routing.routes.iter().flat_map(|(a, paths)| {
+        paths.iter().filter(|p| !p.moves.is_empty()).map(|p| {
+            let line = std::iter::once(p.moves[0].start)
+                .chain(p.moves.iter().map(|s| s.end))
+                .collect::<LineString>();
+            let properties = serde_json::json!({
+                "agent": a.name,
+                "type": p.action.r#type,
+                "target": [p.action.target.x, p.action.target.y],
+                "t_start": p.t_start,
+                "t_end": p.t_end,
+            });
+            (Geometry::LineString(line), object(properties))
+        })
+    });
+    let events = routing.trace.iter().flat_map(|t| t.events.iter()).map(|e| {
+        let point = Point::from(event_position(e));
+        (
+            Geometry::Point(point),
+            object(serde_json::to_value(e).unwrap()),
+        )
+    });
+    to_geojson(paths.chain(events))
+}
+
+/// Where a decision applies: at the agent in the way of a conflict, at the
+/// point of an evasion, and at the target of the agent that waits.
+fn event_position(e: &TraceEvent) -> Coord {
+    match &e.decision {
+        Decision::Conflict { position, .. } => *position,
+        Decision::Evasion { target, .. } => *target,
+        Decision::Wait { .. } => e.target,
+    }
+}
+
+fn object(value: JsonValue) -> JsonObject {
+    match value {
+        JsonValue::Object(properties) => properties,
+        _ => unreachable!("paths and events serialize to objects"),
+    }
+}
+
+/// Read a polygon from well-known text.
+pub fn polygon_from_wkt(s: &str) -> Result<Polygon, String> {
+    polygon(Geometry::try_from_wkt_str(s).map_err(|e| e.to_string())?)
+}
+
+/// Write a polygon as well-known text.
+pub fn polygon_to_wkt(p: &Polygon) -> String {
+    p.wkt_string()
+}
+
+/// The polygon of a geometry. A multi-polygon is accepted if it has a
+/// single part.
+fn polygon(g: Geometry) -> Result<Polygon, String> {
+    match g {
+        Geometry::Polygon(p) => Ok(p),
+        Geometry::MultiPolygon(mp) if mp.0.len() == 1 => Ok(mp.0.into_iter().next().unwrap()),
+        Geometry::MultiPolygon(mp) => Err(format!(
+            "multi-polygons with {} parts are not supported",
+            mp.0.len()
+        )),
+        _ => Err(String::from("not a polygon")),
+    }
+}
+
+/// Agents and obstacles, with the field that holds their geometry.
+trait Shaped: Serialize + DeserializeOwned {
+    const FIELD: &'static str;
+
+    /// The geometry of the value.
+    fn shape(&self) -> Geometry;
+
+    /// The serialized field for a geometry.
+    fn field(g: Geometry) -> Result<JsonValue, String>;
+}
+
+impl Shaped for Agent {
+    const FIELD: &'static str = "reach";
+
+    fn shape(&self) -> Geometry {
+        match &self.reach.0[..] {
+            [p] => Geometry::Polygon(p.clone()),
+            _ => Geometry::MultiPolygon(self.reach.clone()),
+        }
+    }
+
+    fn field(g: Geometry) -> Result<JsonValue, String> {
+        let reach = match g {
+            Geometry::Polygon(p) => MultiPolygon(vec![p]),
+            Geometry::MultiPolygon(mp) => mp,
+            _ => return Err(String::from("not a polygon or multi-polygon")),
+        };
+        Ok(serde_json::to_value(MultiPolygonValue(reach)).unwrap())
+    }
+}
+
+impl Shaped for Obstacle {
+    const FIELD: &'static str = "area";
+
+    fn shape(&self) -> Geometry {
+        Geometry::Polygon(self.area.clone())
+    }
+
+    fn field(g: Geometry) -> Result<JsonValue, String> {
+        Ok(serde_json::to_value(PolygonValue(polygon(g)?)).unwrap())
+    }
+}
+
+#[derive(Serialize)]
+struct PolygonValue(#[serde(with = "PolygonSerde")] Polygon);
+
+#[derive(Serialize)]
+struct MultiPolygonValue(#[serde(with = "MultiPolygonSerde")] MultiPolygon);
+
+/// Build a value from the properties of a feature and its geometry.
+fn from_parts<T: Shaped>(
+    feature: usize,
+    mut properties: JsonObject,
+    geometry: Geometry,
+) -> Result<T, GisError> {
+    let field = T::field(geometry).map_err(|message| GisError::Geometry { feature, message })?;
+    for key in ["position", "velocity"] {
+        if let Some(JsonValue::Array(xy)) = properties.get(key) {
+            if let [x, y] = &xy[..] {
+                let value = serde_json::json!({ "x": x, "y": y });
+                properties.insert(String::from(key), value);
+            }
+        }
+    }
+    properties.insert(String::from(T::FIELD), field);
+    serde_json::from_value(JsonValue::Object(properties)).map_err(|e| GisError::Properties {
+        feature,
+        message: e.to_string(),
+    })
+}
+
+/// The properties of a value, without its geometry.
+fn properties<T: Shaped>(value: &T) -> JsonObject {
+    let JsonValue::Object(mut properties) = serde_json::to_value(value).unwrap() else {
+        unreachable!("agents and obstacles serialize to objects")
+    };
+    properties.remove(T::FIELD);
+    properties
+}
+
+fn agent_properties(a: &Agent) -> JsonObject {
+    let mut properties = properties(a);
+    properties.insert(
+        String::from("position"),
+        serde_json::json!([a.position.x, a.position.y]),
+    );
+    properties.insert(
+        String::from("velocity"),
+        serde_json::json!([a.velocity.x, a.velocity.y]),
+    );
+    properties
+}
+
+fn obstacle_properties(o: &Obstacle) -> JsonObject {
+    properties(o)
+}
+
+fn from_geojson<T: Shaped>(s: &str) -> Result<Vec<T>, GisError> {
+    let collection = s
+        .parse::<FeatureCollection>()
+        .map_err(|e| GisError::Parse(e.to_string()))?;
+    collection
+        .features
+        .into_iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let geometry = f
+                .geometry
+                .as_ref()
+                .ok_or_else(|| String::from("missing"))
+                .and_then(|g| Geometry::try_from(g).map_err(|e| e.to_string()))
+                .map_err(|message| GisError::Geometry {
+                    feature: i,
+                    message,
+                })?;
+            from_parts(i, f.properties.unwrap_or_default(), geometry)
+        })
+        .collect()
+}
+
+fn to_geojson(features: impl Iterator<Item = (Geometry, JsonObject)>) -> String {
+    FeatureCollection::new(features.map(|(g, properties)| Feature {
+        geometry: Some(geojson::Geometry::from(&g)),
+        properties: Some(properties),
+        ..Feature::default()
+    }))
+    .to_string()
+}
+
+fn from_wkt<T: Shaped>(records: &[WktRecord]) -> Result<Vec<T>, GisError> {
+    records
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let geometry = Geometry::try_from_wkt_str(&r.wkt).map_err(|e| GisError::Geometry {
+                feature: i,
+                message: e.to_string(),
+            })?;
+            from_parts(i, r.properties.clone(), geometry)
+        })
+        .collect()
+}
+
+fn to_wkt(features: impl Iterator<Item = (Geometry, JsonObject)>) -> Vec<WktRecord> {
+    features
+        .map(|(g, properties)| WktRecord {
+            wkt: g.wkt_string(),
+            properties,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,180 @@
+use geo::{Coord, Geometry, LineString, MultiPolygon, Point, Polygon};
+
+use crate::actions::{
+    data::{Agent, Motion, Planner, Schedule},
+    fixtures::{action, agent},
+    gis::{
+        agents_from_geojson, agents_from_wkt, agents_to_geojson, agents_to_wkt,
+        obstacles_from_geojson, obstacles_from_wkt, obstacles_to_geojson, obstacles_to_wkt,
+        polygon_from_wkt, polygon_to_wkt, routing_to_geojson, GisError,
+    },
+    site::Obstacle,
+    Solver,
+};
+
+fn square(x0: f64, y0: f64, side: f64) -> Polygon {
+    Polygon::new(
+        LineString::from(vec![
+            (x0, y0),
+            (x0 + side, y0),
+            (x0 + side, y0 + side),
+            (x0, y0 + side),
+            (x0, y0),
+        ]),
+        vec![],
+    )
+}
+
+const AGENTS: &str = r#"{
+    "type": "FeatureCollection",
+    "features": [
+        {
+            "type": "Feature",
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": [[[[0, 0], [100, 0], [100, 10], [0, 10], [0, 0]]]]
+            },
+            "properties": {
+                "name": "crane",
+                "position": [10, 5],
+                "velocity": [2, 1],
+                "safety_x": 10,
+                "order": 0
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn test_agents_geojson() {
+    let agents = agents_from_geojson(AGENTS).unwrap();
+    assert_eq!(agents.len(), 1);
+    let a = &agents[0];
+    assert_eq!(a.name, "crane");
+    assert_eq!(a.position, Coord { x: 10.0, y: 5.0 });
+    assert_eq!((a.velocity.x, a.velocity.y), (2.0, 1.0));
+    assert_eq!((a.safety_x, a.order), (10.0, 0));
+    assert_eq!(a.motion, Motion::Simultaneous);
+    assert_eq!(a.planner, Planner::Visibility);
+    assert_eq!(a.reach.0.len(), 1);
+
+    let again = agents_from_geojson(&agents_to_geojson(&agents)).unwrap();
+    assert_eq!(again[0].name, a.name);
+    assert_eq!(again[0].position, a.position);
+    assert_eq!(again[0].reach, a.reach);
+}
+
+#[test]
+fn test_agents_wkt() {
+    let agent = Agent {
+        reach: square(0.0, 0.0, 10.0).into(),
+        safety_x: 3.0,
+        capabilities: vec![String::from("hook")],
+        planner: Planner::Grid { cell: 0.5 },
+        ..agent("crane", 5.0, 1)
+    };
+    let records = agents_to_wkt(std::slice::from_ref(&agent));
+    assert!(records[0].wkt.starts_with("POLYGON"));
+    let again = agents_from_wkt(&records).unwrap();
+    assert_eq!(again[0].reach, agent.reach);
+    assert_eq!(again[0].position, agent.position);
+    assert_eq!(again[0].capabilities, agent.capabilities);
+    assert_eq!(again[0].planner, agent.planner);
+}
+
+#[test]
+fn test_multi_polygon_reach() {
+    let mut agents = agents_from_geojson(AGENTS).unwrap();
+    agents[0].reach = MultiPolygon(vec![square(0.0, 0.0, 10.0), square(20.0, 0.0, 10.0)]);
+    agents[0].corridors = square(10.0, 4.0, 10.0).into();
+
+    let again = agents_from_geojson(&agents_to_geojson(&agents)).unwrap();
+    assert_eq!(again[0].reach, agents[0].reach);
+    assert_eq!(again[0].corridors, agents[0].corridors);
+    let records = agents_to_wkt(&agents);
+    assert!(records[0].wkt.starts_with("MULTIPOLYGON"));
+    let again = agents_from_wkt(&records).unwrap();
+    assert_eq!(again[0].reach, agents[0].reach);
+}
+
+#[test]
+fn test_obstacles() {
+    let obstacles = vec![Obstacle {
+        name: String::from("pillar"),
+        area: square(4.0, 4.0, 2.0),
+        window: None,
+    }];
+    let again = obstacles_from_geojson(&obstacles_to_geojson(&obstacles)).unwrap();
+    assert_eq!(again[0].name, "pillar");
+    assert_eq!(again[0].area, obstacles[0].area);
+    let again = obstacles_from_wkt(&obstacles_to_wkt(&obstacles)).unwrap();
+    assert_eq!(again[0].area, obstacles[0].area);
+}
+
+#[test]
+fn test_invalid_geometry() {
+    let p = polygon_from_wkt("MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)))").unwrap();
+    assert_eq!(polygon_from_wkt(&polygon_to_wkt(&p)).unwrap(), p);
+    assert!(
+        polygon_from_wkt("MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)), ((2 2, 3 2, 3 3, 2 2)))").is_err()
+    );
+    assert!(polygon_from_wkt("POINT (1 1)").is_err());
+
+    let point = AGENTS.replace(
+        r#""type": "MultiPolygon",
+                "coordinates": [[[[0, 0], [100, 0], [100, 10], [0, 10], [0, 0]]]]"#,
+        r#""type": "Point", "coordinates": [0, 0]"#,
+    );
+    assert!(matches!(
+        agents_from_geojson(&point),
+        Err(GisError::Geometry { feature: 0, .. })
+    ));
+    assert!(matches!(
+        agents_from_geojson(&AGENTS.replace(r#""order": 0"#, r#""order": "first""#)),
+        Err(GisError::Properties { feature: 0, .. })
+    ));
+    assert!(matches!(agents_from_geojson("{"), Err(GisError::Parse(_))));
+}
+
+#[test]
+fn test_routing_geojson() {
+    let crane = agent("agent-0", 10.0, 0);
+    let other = agent("agent-1", 30.0, 1);
+    let schedule = Schedule {
+        actions: vec![action(&other, 40.0, 20.0), action(&crane, 50.0, 0.0)],
+    };
+    let solver = Solver {
+        trace: true,
+        ..Solver::default()
+    };
+    let routing = solver.routes(&[crane, other], schedule).unwrap();
+
+    let collection = routing_to_geojson(&routing)
+        .parse::<geojson::FeatureCollection>()
+        .unwrap();
+
+    let kinds = collection
+        .features
+        .iter()
+        .map(|f| f.geometry.as_ref().unwrap().value.type_name())
+        .collect::<Vec<_>>();
+    let moving = routing
+        .routes
+        .iter()
+        .flat_map(|(_, paths)| paths.iter())
+        .filter(|p| !p.moves.is_empty())
+        .count();
+    let events = routing.trace.unwrap().events.len();
+    assert_eq!(kinds.len(), moving + events);
+    assert!(kinds[..moving].iter().all(|&k| k == "LineString"));
+    assert!(kinds[moving..].iter().all(|&k| k == "Point"));
+    // The other agent evades to 60.
+    let evasion = &collection.features[moving + 1];
+    assert_eq!(
+        Geometry::try_from(evasion.geometry.as_ref().unwrap()).unwrap(),
+        Geometry::Point(Point::new(60.0, 5.0))
+    );
+    assert!(evasion.properties.as_ref().unwrap()["decision"]
+        .get("Evasion")
+        .is_some());
+}
@@ -1,94 +1,338 @@
-use std::collections::HashMap;
-
-use geo::{Contains, Coord, LineString};
+use geo::{
+    line_intersection::{line_intersection, LineIntersection},
+    BooleanOps, Contains, Coord, CoordsIter, Distance, Euclidean, Intersects, Line,
+    MapCoordsInPlace, MultiPolygon, Point, Polygon,
+};
 use itertools::Itertools;
 use petgraph::{
     algo::astar,
-    graph::{NodeIndex, UnGraph},
-    Graph,
+    graph::{DiGraph, NodeIndex},
 };
 
-use crate::actions::data::{Action, ConstVel2D, Segment};
-
-pub fn find_path_2d_g(a: &Action, start: Coord) -> Option<Vec<Segment>> {
-    let g = create_graph(a, start);
-    let path = astar(
-        &g.pet_g,
-        g.node_to_nix[0],
-        |n| n == g.node_to_nix[1],
-        |e| *e.weight(),
-        |_| 0.0,
-    );
-    path.map(|(_, path)| {
-        path.into_iter()
-            .tuple_windows()
-            .map(|(s, e)| (s, e, g.pet_g.find_edge(s, e).unwrap()))
-            .map(|(s, e, edge_ix)| (s, e, *g.pet_g.edge_weight(edge_ix).unwrap()))
-            .map(|(s, e, time)| Segment {
-                start: g.nodes[g.nix_to_node[&s]],
-                end: g.nodes[g.nix_to_node[&e]],
-                duration: time,
-            })
-            .collect::<Vec<_>>()
-    })
+use crate::actions::data::{Action, Agent, Axis, ConstVel2D, Motion, Planner, Segment};
+
+pub mod grid;
+pub mod road;
+
+use grid::NavGrid;
+
+/// Tolerance on the distance of a path to the region it travels in. It
+/// absorbs the rounding of boolean operations on polygons.
+pub const EPS: f64 = 1e-6;
+
+/// Find the fastest path from `start` to the target of the action within the
+/// reach and corridors of its agent, avoiding the obstacles.
+pub fn find_path_2d_g(a: &Action, start: Coord, obstacles: &[&Polygon]) -> Option<Vec<Segment>> {
+    Navigator::new(&a.agent, free_region(&a.agent.region(), obstacles)).find_path(start, a.target)
 }
 
-/// Internal undirected graph
-pub struct G {
-    /// The nodes. The first node should be the start point, the second the target.
-    pub nodes: Vec<Coord<f64>>,
-    pub pet_g: UnGraph<Coord<f64>, f64>,
-    pub node_to_nix: Vec<NodeIndex>,
-    /// A mapping from the pet graph node indices to the element in `nodes`.
-    pub nix_to_node: HashMap<NodeIndex, usize>,
+/// Navigation structure of an agent, built by the planner of the agent.
+pub enum Navigator {
+    /// A visibility graph
+    Visibility(NavGraph),
+    /// A grid searched with Theta*
+    Grid(NavGrid),
 }
 
-fn create_graph(a: &Action, start: Coord) -> G {
-    let poly = &a.agent.reach;
-    let mut coords = vec![start, a.target];
-    coords.extend(poly.exterior().points().map(|p| p.0));
-    for interior in poly.interiors() {
-        coords.extend(interior.points().map(|p| p.0));
+impl Navigator {
+    /// Build the navigation structure of the planner of the agent travelling
+    /// in the region. Agents bound to the road network are routed on the
+    /// network by the solver, in the region they use the visibility graph.
+    pub fn new(agent: &Agent, region: MultiPolygon) -> Navigator {
+        let (velocity, motion) = (agent.velocity, agent.motion);
+        match agent.planner {
+            Planner::Visibility | Planner::Road => {
+                Navigator::Visibility(NavGraph::new(region, velocity, motion))
+            }
+            Planner::Grid { cell } => {
+                Navigator::Grid(NavGrid::new(&region, cell, velocity, motion))
+            }
+        }
     }
 
-    let edges = coords
-        .iter()
-        .map(|pi| {
-            coords
-                .iter()
-                .enumerate()
-                .filter(|(_, &pj)| poly.contains(&LineString::from(vec![*pi, pj])))
-                .map(|(j, pj)| (j, timer(*pi, *pj, &a.agent.velocity)))
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    let mut pet_g = Graph::new_undirected();
-    let mut pet_ns = Vec::new();
-    let mut nix_to_coord = HashMap::new();
-    for (i, n) in coords.iter().enumerate() {
-        let pet_n = pet_g.add_node(*n);
-        pet_ns.push(pet_n);
-        nix_to_coord.insert(pet_n, i);
+    /// Find a path from `start` to `target` within the region.
+    pub fn find_path(&mut self, start: Coord, target: Coord) -> Option<Vec<Segment>> {
+        match self {
+            Navigator::Visibility(g) => g.find_path(start, target),
+            Navigator::Grid(g) => g.find_path(start, target),
+        }
     }
-    for (i, es) in edges.iter().enumerate() {
-        for e in es.iter() {
-            pet_g.add_edge(pet_ns[i], pet_ns[e.0], e.1);
+}
+
+/// The algorithm used to search the visibility graph
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Search {
+    /// A* guided by the travel time heuristic of the motion model
+    #[default]
+    AStar,
+    /// Dijkstra, i.e., A* without heuristic
+    Dijkstra,
+}
+
+/// Navigation structure of an agent in a region. The visibility between the
+/// vertices of the region is computed once; queries only connect their start
+/// and target to it.
+pub struct NavGraph {
+    /// The region the agent travels in
+    region: MultiPolygon,
+    /// The velocity of the agent
+    velocity: ConstVel2D,
+    /// The motion mode of the agent
+    motion: Motion,
+    /// The visibility graph. The first two nodes are reserved for the start
+    /// and target of a query, the others are the vertices of the region.
+    pet_g: DiGraph<Coord<f64>, f64>,
+}
+
+impl NavGraph {
+    /// Build the navigation structure for an agent with the given velocity
+    /// and motion travelling in the region.
+    pub fn new(region: MultiPolygon, velocity: ConstVel2D, motion: Motion) -> NavGraph {
+        let mut vertices = Vec::new();
+        for poly in region.iter() {
+            for ring in std::iter::once(poly.exterior()).chain(poly.interiors()) {
+                // The closing coordinate of a ring repeats the first one.
+                vertices.extend(ring.0.iter().skip(1).copied());
+            }
         }
+        let mut pet_g = DiGraph::new();
+        pet_g.add_node(Coord::zero());
+        pet_g.add_node(Coord::zero());
+        let nixs = vertices
+            .iter()
+            .map(|v| pet_g.add_node(*v))
+            .collect::<Vec<_>>();
+        // Moving one axis at a time, the move back passes through another
+        // corner, so each direction is an edge of its own.
+        for ((i, vi), (j, vj)) in vertices.iter().enumerate().tuple_combinations() {
+            for (from, to, a, b) in [(i, j, vi, vj), (j, i, vj, vi)] {
+                if visible_move(&region, *a, *b, motion) {
+                    pet_g.add_edge(nixs[from], nixs[to], travel_time(*a, *b, &velocity, motion));
+                }
+            }
+        }
+        NavGraph {
+            region,
+            velocity,
+            motion,
+            pet_g,
+        }
+    }
+
+    /// Find the fastest path from `start` to `target` within the region.
+    pub fn find_path(&mut self, start: Coord, target: Coord) -> Option<Vec<Segment>> {
+        self.find_path_with(start, target, Search::AStar)
     }
 
-    G {
-        nodes: coords,
-        pet_g: pet_g,
-        node_to_nix: pet_ns,
-        nix_to_node: nix_to_coord,
+    /// Find the fastest path from `start` to `target` within the region with
+    /// the given search algorithm.
+    pub fn find_path_with(
+        &mut self,
+        start: Coord,
+        target: Coord,
+        search: Search,
+    ) -> Option<Vec<Segment>> {
+        if visible_move(&self.region, start, target, self.motion) {
+            return Some(segments([start, target], &self.velocity, self.motion));
+        }
+        let (s, t) = (NodeIndex::new(0), NodeIndex::new(1));
+        self.pet_g[s] = start;
+        self.pet_g[t] = target;
+        let mut added = Vec::new();
+        for nix in self.pet_g.node_indices().skip(2) {
+            let v = self.pet_g[nix];
+            for (n1, n2, from, to) in [(s, nix, start, v), (nix, t, v, target)] {
+                if visible_move(&self.region, from, to, self.motion) {
+                    let time = travel_time(from, to, &self.velocity, self.motion);
+                    added.push(self.pet_g.add_edge(n1, n2, time));
+                }
+            }
+        }
+        let (velocity, motion) = (self.velocity, self.motion);
+        let pet_g = &self.pet_g;
+        let path = astar(
+            pet_g,
+            s,
+            |n| n == t,
+            |e| *e.weight(),
+            |n| match search {
+                Search::AStar => heuristic(pet_g[n], target, &velocity, motion),
+                Search::Dijkstra => 0.0,
+            },
+        );
+        let result = path.map(|(_, path)| {
+            segments(
+                path.into_iter().map(|n| self.pet_g[n]),
+                &self.velocity,
+                self.motion,
+            )
+        });
+        // Removing the query edges last to first keeps the indices of the
+        // other edges unchanged.
+        for e in added.into_iter().rev() {
+            self.pet_g.remove_edge(e);
+        }
+        result
     }
 }
 
-fn timer(start: Coord<f64>, end: Coord<f64>, vel: &ConstVel2D) -> f64 {
+/// The part of the region that is not covered by any obstacle.
+pub fn free_region(region: &MultiPolygon, obstacles: &[&Polygon]) -> MultiPolygon {
+    if obstacles.is_empty() {
+        return region.clone();
+    }
+    let obstacles = MultiPolygon(obstacles.iter().map(|&o| o.clone()).collect());
+    let mut free = region.difference(&obstacles);
+    // The boolean operations round the coordinates off, which would move
+    // positions on the edges of the region, e.g. of agents, out of it. The
+    // coordinates are snapped back to those of the input.
+    let (mut xs, mut ys): (Vec<f64>, Vec<f64>) = region
+        .coords_iter()
+        .chain(obstacles.coords_iter())
+        .map(|c| (c.x, c.y))
+        .unzip();
+    xs.sort_by(f64::total_cmp);
+    ys.sort_by(f64::total_cmp);
+    let snap = |v: f64, vs: &[f64]| {
+        let k = vs.partition_point(|&w| w < v);
+        vs[k.saturating_sub(1)..(k + 1).min(vs.len())]
+            .iter()
+            .copied()
+            .find(|&w| (v - w).abs() <= 1e-6 * (1.0 + w.abs()))
+            .unwrap_or(v)
+    };
+    free.map_coords_in_place(|c| Coord {
+        x: snap(c.x, &xs),
+        y: snap(c.y, &ys),
+    });
+    free
+}
+
+/// Whether the move from `a` to `b` stays within the region.
+pub(crate) fn visible_move(region: &MultiPolygon, a: Coord, b: Coord, motion: Motion) -> bool {
+    legs(a, b, motion)
+        .into_iter()
+        .all(|(l1, l2)| visible(region, l1, l2))
+}
+
+/// Whether the straight line from `a` to `b` stays within the region.
+fn visible(region: &MultiPolygon, a: Coord, b: Coord) -> bool {
+    piece_midpoints(region.iter(), a, b).into_iter().all(|m| {
+        let p = Point::from(m);
+        region.intersects(&p) || Euclidean.distance(region, &p) <= EPS
+    })
+}
+
+/// Whether the straight line from `a` to `b` passes through the interior of
+/// the polygon, not merely along its boundary.
+pub fn crosses_interior(poly: &Polygon, a: Coord, b: Coord) -> bool {
+    piece_midpoints(std::iter::once(poly), a, b)
+        .into_iter()
+        .map(Point::from)
+        .any(|p| {
+            poly.contains(&p)
+                && std::iter::once(poly.exterior())
+                    .chain(poly.interiors())
+                    .all(|r| Euclidean.distance(r, &p) > EPS)
+        })
+}
+
+/// Split the line from `a` to `b` where it meets the boundaries of the
+/// polygons and return the midpoints of the pieces. Each piece lies either
+/// inside or outside of every polygon.
+fn piece_midpoints<'a>(polys: impl Iterator<Item = &'a Polygon>, a: Coord, b: Coord) -> Vec<Coord> {
+    let d = b - a;
+    let len2 = d.x * d.x + d.y * d.y;
+    if len2 == 0.0 {
+        return vec![a];
+    }
+    let line = Line::new(a, b);
+    let param = |c: Coord| ((c.x - a.x) * d.x + (c.y - a.y) * d.y) / len2;
+    let mut ts = vec![0.0, 1.0];
+    for poly in polys {
+        for l in std::iter::once(poly.exterior())
+            .chain(poly.interiors())
+            .flat_map(|r| r.lines())
+        {
+            match line_intersection(line, l) {
+                Some(LineIntersection::SinglePoint { intersection, .. }) => {
+                    ts.push(param(intersection))
+                }
+                Some(LineIntersection::Collinear { intersection }) => {
+                    ts.push(param(intersection.start));
+                    ts.push(param(intersection.end));
+                }
+                None => {}
+            }
+        }
+    }
+    ts.sort_by(f64::total_cmp);
+    ts.into_iter()
+        .tuple_windows()
+        .filter(|(t1, t2)| t2 - t1 > 1e-12)
+        .map(|(t1, t2)| a + d * ((t1 + t2) / 2.0))
+        .collect()
+}
+
+/// The time to travel along a straight segment. An agent moving one axis at
+/// a time only travels axis-aligned segments, for which this time is the
+/// same.
+pub fn timer(start: Coord<f64>, end: Coord<f64>, vel: &ConstVel2D) -> f64 {
     let t_x = (end.x - start.x).abs() / vel.x;
     let t_y = (end.y - start.y).abs() / vel.y;
     t_x.max(t_y)
 }
 
+/// The time to move from `start` to `end` with the given motion.
+pub fn travel_time(start: Coord, end: Coord, vel: &ConstVel2D, motion: Motion) -> f64 {
+    legs(start, end, motion)
+        .into_iter()
+        .map(|(l1, l2)| timer(l1, l2, vel))
+        .sum()
+}
+
+/// A lower bound on the time to travel from `start` to `end` along any path,
+/// which makes it an admissible heuristic for A*. No path is faster than the
+/// direct move: with both axes moving at the same time it is the straight
+/// line, with one axis at a time every path covers at least the distances
+/// along both axes.
+pub fn heuristic(start: Coord<f64>, end: Coord<f64>, vel: &ConstVel2D, motion: Motion) -> f64 {
+    travel_time(start, end, vel, motion)
+}
+
+/// The straight pieces of the move from `a` to `b`. Moving one axis at a
+/// time, the move passes through a corner point, unless it is axis-aligned
+/// already.
+pub fn legs(a: Coord, b: Coord, motion: Motion) -> Vec<(Coord, Coord)> {
+    let corner = match motion {
+        Motion::Simultaneous => None,
+        Motion::AxisAligned { first: Axis::X } => Some(Coord { x: b.x, y: a.y }),
+        Motion::AxisAligned { first: Axis::Y } => Some(Coord { x: a.x, y: b.y }),
+    };
+    match corner {
+        Some(c) if c != a && c != b => vec![(a, c), (c, b)],
+        _ => vec![(a, b)],
+    }
+}
+
+/// The segments of a path through the points, with the corner points of
+/// the motion.
+pub fn segments(
+    points: impl IntoIterator<Item = Coord>,
+    vel: &ConstVel2D,
+    motion: Motion,
+) -> Vec<Segment> {
+    points
+        .into_iter()
+        .tuple_windows()
+        .flat_map(|(a, b)| legs(a, b, motion))
+        .map(|(start, end)| Segment {
+            start,
+            end,
+            duration: timer(start, end, vel),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests;
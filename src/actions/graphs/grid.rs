@@ -0,0 +1,335 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use geo::{BoundingRect, Coord, MultiPolygon};
+use itertools::Itertools;
+
+use crate::actions::{
+    data::{ConstVel2D, Motion, Segment},
+    graphs::{heuristic, legs, segments, travel_time, visible_move},
+};
+
+/// Navigation structure of an agent in a region rasterised into square
+/// cells. A cell is free if it lies entirely within the region, so paths
+/// only pass through free cells, apart from the cells of their start and
+/// target, where they have to stay within the region itself. Paths are
+/// searched with Theta*, which shortcuts the grid moves wherever the line of
+/// sight allows.
+pub struct NavGrid {
+    /// The region the agent travels in
+    region: MultiPolygon,
+    /// The lower left corner of the grid
+    origin: Coord,
+    /// The side of a cell
+    cell: f64,
+    /// The number of columns
+    nx: usize,
+    /// The number of rows
+    ny: usize,
+    /// Whether a cell is free, row by row
+    free: Vec<bool>,
+    /// The velocity of the agent
+    velocity: ConstVel2D,
+    /// The motion mode of the agent
+    motion: Motion,
+}
+
+/// A node on the open list of the search, ordered by lowest cost first.
+struct Open {
+    f: f64,
+    node: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.total_cmp(&self.f)
+    }
+}
+
+/// The start and target of a query and their cells.
+struct Query {
+    start: Coord,
+    target: Coord,
+    s: usize,
+    t: usize,
+}
+
+impl NavGrid {
+    /// Rasterise the region into cells of the given side for an agent with
+    /// the given velocity and motion.
+    pub fn new(region: &MultiPolygon, cell: f64, velocity: ConstVel2D, motion: Motion) -> NavGrid {
+        let (origin, nx, ny) = match region.bounding_rect() {
+            Some(r) => (
+                r.min(),
+                ((r.width() / cell).ceil() as usize).max(1),
+                ((r.height() / cell).ceil() as usize).max(1),
+            ),
+            None => (Coord::zero(), 0, 0),
+        };
+        let mut grid = NavGrid {
+            region: region.clone(),
+            origin,
+            cell,
+            nx,
+            ny,
+            free: vec![false; nx * ny],
+            velocity,
+            motion,
+        };
+        let lines = region
+            .iter()
+            .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors()))
+            .flat_map(|r| r.lines())
+            .collect::<Vec<_>>();
+        // A cell lies within the region if its center does and no boundary
+        // passes through its interior. The centers are tested row by row
+        // with the crossings of the boundary.
+        for j in 0..ny {
+            let y = origin.y + (j as f64 + 0.5) * cell;
+            let mut xs = lines
+                .iter()
+                .filter(|l| (l.start.y > y) != (l.end.y > y))
+                .map(|l| l.start.x + (y - l.start.y) * l.dx() / l.dy())
+                .collect::<Vec<_>>();
+            xs.sort_by(f64::total_cmp);
+            for i in 0..nx {
+                let x = origin.x + (i as f64 + 0.5) * cell;
+                grid.free[j * nx + i] = xs.iter().filter(|&&c| c < x).count() % 2 == 1;
+            }
+        }
+        let mut blocked = Vec::new();
+        for l in lines.iter() {
+            let (a, d) = (
+                grid.to_grid(l.start),
+                grid.to_grid(l.end) - grid.to_grid(l.start),
+            );
+            grid.traverse(l.start, l.end, |i, j, t0, t1| {
+                let m = a + d * ((t0 + t1) / 2.0);
+                let (fx, fy) = (m.x - i as f64, m.y - j as f64);
+                if fx > 1e-9 && fx < 1.0 - 1e-9 && fy > 1e-9 && fy < 1.0 - 1e-9 {
+                    blocked.push((i, j));
+                }
+                true
+            });
+        }
+        for (i, j) in blocked {
+            if let Some(idx) = grid.index(i, j) {
+                grid.free[idx] = false;
+            }
+        }
+        grid
+    }
+
+    /// Find a fast path from `start` to `target` through the free cells.
+    /// Unlike the visibility graph, the path is not guaranteed to be the
+    /// fastest, and narrow passages of less than a cell are not found.
+    pub fn find_path(&self, start: Coord, target: Coord) -> Option<Vec<Segment>> {
+        let q = Query {
+            start,
+            target,
+            s: self.cell_of(start)?,
+            t: self.cell_of(target)?,
+        };
+        let mut parent = vec![q.s; self.free.len()];
+        // The search never returns to the cell it starts in.
+        if q.s == q.t {
+            return self
+                .in_sight(&q, start, target)
+                .then(|| self.segments(&q, &parent));
+        }
+        let mut g = vec![f64::INFINITY; self.free.len()];
+        let mut closed = vec![false; self.free.len()];
+        let mut open = BinaryHeap::new();
+        g[q.s] = 0.0;
+        open.push(Open {
+            f: heuristic(start, target, &self.velocity, self.motion),
+            node: q.s,
+        });
+        while let Some(Open { node, .. }) = open.pop() {
+            if closed[node] {
+                continue;
+            }
+            closed[node] = true;
+            if node == q.t {
+                return Some(self.segments(&q, &parent));
+            }
+            for n in self.neighbours(&q, node) {
+                if closed[n] {
+                    continue;
+                }
+                // Theta*: connect to the parent of the expanded node
+                // directly if it is in sight.
+                let p = parent[node];
+                let from = if self.in_sight(&q, self.coord(&q, p), self.coord(&q, n)) {
+                    p
+                } else if self.in_sight(&q, self.coord(&q, node), self.coord(&q, n)) {
+                    node
+                } else {
+                    continue;
+                };
+                let (a, b) = (self.coord(&q, from), self.coord(&q, n));
+                let cost = g[from] + travel_time(a, b, &self.velocity, self.motion);
+                if cost < g[n] {
+                    g[n] = cost;
+                    parent[n] = from;
+                    open.push(Open {
+                        f: cost + heuristic(self.coord(&q, n), target, &self.velocity, self.motion),
+                        node: n,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// The path to the target of the query by following the parents.
+    fn segments(&self, q: &Query, parent: &[usize]) -> Vec<Segment> {
+        let mut nodes = vec![q.t];
+        while *nodes.last().unwrap() != q.s {
+            nodes.push(parent[*nodes.last().unwrap()]);
+        }
+        if nodes.len() == 1 {
+            nodes.push(q.s);
+        }
+        // The start and target share the node of their cell if they are in
+        // the same one, so the path ends at the target explicitly.
+        let mut coords = nodes
+            .into_iter()
+            .rev()
+            .map(|n| self.coord(q, n))
+            .collect::<Vec<_>>();
+        *coords.last_mut().unwrap() = q.target;
+        segments(coords, &self.velocity, self.motion)
+    }
+
+    /// The passable cells around a cell. Diagonal moves may not cut the
+    /// corner of an impassable cell.
+    fn neighbours(&self, q: &Query, node: usize) -> Vec<usize> {
+        let (i, j) = ((node % self.nx) as i64, (node / self.nx) as i64);
+        let passable =
+            |di: i64, dj: i64| self.index(i + di, j + dj).filter(|&n| self.passable(q, n));
+        (-1..=1)
+            .cartesian_product(-1..=1)
+            .filter(|&(di, dj)| (di, dj) != (0, 0))
+            .filter(|&(di, dj)| {
+                di == 0 || dj == 0 || (passable(di, 0).is_some() && passable(0, dj).is_some())
+            })
+            .filter_map(|(di, dj)| passable(di, dj))
+            .collect()
+    }
+
+    /// Whether the move from `a` to `b` only passes through passable cells.
+    /// A move through the cell of the start or target that is not free has
+    /// to stay within the region.
+    fn in_sight(&self, q: &Query, a: Coord, b: Coord) -> bool {
+        let mut partial = false;
+        legs(a, b, self.motion).into_iter().all(|(l1, l2)| {
+            self.traverse(l1, l2, |i, j, _, _| {
+                self.index(i, j).is_some_and(|n| {
+                    partial |= !self.free[n];
+                    self.passable(q, n)
+                })
+            })
+        }) && (!partial || visible_move(&self.region, a, b, self.motion))
+    }
+
+    fn passable(&self, q: &Query, n: usize) -> bool {
+        self.free[n] || n == q.s || n == q.t
+    }
+
+    /// The coordinate a node of the query stands for: the start and target
+    /// themselves for their cells, the center for the other cells.
+    fn coord(&self, q: &Query, n: usize) -> Coord {
+        if n == q.s {
+            q.start
+        } else if n == q.t {
+            q.target
+        } else {
+            self.origin
+                + Coord {
+                    x: ((n % self.nx) as f64 + 0.5) * self.cell,
+                    y: ((n / self.nx) as f64 + 0.5) * self.cell,
+                }
+        }
+    }
+
+    fn index(&self, i: i64, j: i64) -> Option<usize> {
+        if (0..self.nx as i64).contains(&i) && (0..self.ny as i64).contains(&j) {
+            Some(j as usize * self.nx + i as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The cell containing the coordinate. Coordinates on the upper or right
+    /// boundary of the grid belong to the last cell.
+    fn cell_of(&self, c: Coord) -> Option<usize> {
+        let p = self.to_grid(c);
+        let clamp = |v: f64, n: usize| (v.floor() as i64).min(n as i64 - 1);
+        self.index(clamp(p.x, self.nx), clamp(p.y, self.ny))
+    }
+
+    /// The coordinate in units of cells relative to the origin.
+    fn to_grid(&self, c: Coord) -> Coord {
+        (c - self.origin) / self.cell
+    }
+
+    /// Visit the cells the line from `a` to `b` passes through, with the
+    /// interval of the line parameter inside the cell, until `visit` returns
+    /// false. Returns whether all cells were visited.
+    fn traverse(
+        &self,
+        a: Coord,
+        b: Coord,
+        mut visit: impl FnMut(i64, i64, f64, f64) -> bool,
+    ) -> bool {
+        let (pa, pb) = (self.to_grid(a), self.to_grid(b));
+        let d = pb - pa;
+        // The step, the parameter of the first cell boundary and the
+        // parameter between boundaries along an axis
+        let axis = |p: f64, i: i64, d: f64| {
+            if d > 0.0 {
+                (1, ((i + 1) as f64 - p) / d, 1.0 / d)
+            } else if d < 0.0 {
+                (-1, (i as f64 - p) / d, -1.0 / d)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (mut i, mut j) = (pa.x.floor() as i64, pa.y.floor() as i64);
+        let (si, mut tx, dtx) = axis(pa.x, i, d.x);
+        let (sj, mut ty, dty) = axis(pa.y, j, d.y);
+        let mut t0 = 0.0;
+        loop {
+            let t1 = tx.min(ty).min(1.0);
+            // Lines starting on a cell boundary only touch the cell behind.
+            if (t1 - t0 > 1e-12 || d == Coord::zero()) && !visit(i, j, t0, t1) {
+                return false;
+            }
+            if t1 >= 1.0 - 1e-12 {
+                return true;
+            }
+            if (tx - ty).abs() < 1e-12 {
+                (i, j, tx, ty) = (i + si, j + sj, tx + dtx, ty + dty);
+            } else if tx < ty {
+                (i, tx) = (i + si, tx + dtx);
+            } else {
+                (j, ty) = (j + sj, ty + dty);
+            }
+            t0 = t1;
+        }
+    }
+}
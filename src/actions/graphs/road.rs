@@ -0,0 +1,161 @@
+use geo::{Coord, Distance, Euclidean, Point};
+use petgraph::{
+    algo::{astar, dijkstra},
+    graph::{DiGraph, EdgeIndex, NodeIndex},
+    visit::{EdgeRef, NodeFiltered},
+};
+
+use crate::actions::{
+    data::{ConstVel2D, Segment},
+    graphs::timer,
+    site::RoadNetwork,
+};
+
+/// A road of the network graph.
+#[derive(Clone, Copy, Debug)]
+pub struct Road {
+    /// The speed limit on the road
+    pub speed: f64,
+    /// The number of agents that may drive on the road at the same time
+    pub capacity: usize,
+}
+
+/// Navigation structure of the road network of a site. Agents bound to the
+/// network only travel along its directed roads, from node to node.
+pub struct RoadGraph {
+    /// The network, with the node positions as weights
+    pet_g: DiGraph<Coord, Road>,
+}
+
+impl RoadGraph {
+    /// Build the graph of the network. Roads between unknown nodes are
+    /// left out.
+    pub fn new(network: &RoadNetwork) -> RoadGraph {
+        let mut pet_g = DiGraph::new();
+        let nixs = network
+            .nodes
+            .iter()
+            .map(|n| (n.name.as_str(), pet_g.add_node(n.position)))
+            .collect::<Vec<_>>();
+        let find = |name: &str| nixs.iter().find(|(n, _)| *n == name).map(|(_, i)| *i);
+        for e in network.edges.iter() {
+            if let (Some(from), Some(to)) = (find(&e.from), find(&e.to)) {
+                pet_g.add_edge(
+                    from,
+                    to,
+                    Road {
+                        speed: e.speed,
+                        capacity: e.capacity,
+                    },
+                );
+            }
+        }
+        RoadGraph { pet_g }
+    }
+
+    /// The node closest to the coordinate, to which positions and targets
+    /// of agents snap.
+    pub fn nearest(&self, c: Coord) -> Option<NodeIndex> {
+        self.pet_g.node_indices().min_by(|&n1, &n2| {
+            let d = |n: NodeIndex| Euclidean.distance(&Point::from(self.pet_g[n]), &Point::from(c));
+            d(n1).total_cmp(&d(n2))
+        })
+    }
+
+    /// The position of a node.
+    pub fn position(&self, n: NodeIndex) -> Coord {
+        self.pet_g[n]
+    }
+
+    /// The road an edge stands for.
+    pub fn road(&self, e: EdgeIndex) -> Road {
+        self.pet_g[e]
+    }
+
+    /// The nodes an edge connects.
+    pub fn ends(&self, e: EdgeIndex) -> (NodeIndex, NodeIndex) {
+        self.pet_g.edge_endpoints(e).unwrap()
+    }
+
+    /// The time an agent with the given velocity takes to drive the road.
+    pub fn travel_time(&self, e: EdgeIndex, vel: &ConstVel2D) -> f64 {
+        let (from, to) = self.ends(e);
+        let (a, b) = (self.pet_g[from], self.pet_g[to]);
+        let length = Euclidean.distance(&Point::from(a), &Point::from(b));
+        f64::max(length / self.pet_g[e].speed, timer(a, b, vel))
+    }
+
+    /// Find the fastest route from `start` to `target` for an agent with the
+    /// given velocity, avoiding the blocked nodes. Returns the roads driven.
+    pub fn find_route(
+        &self,
+        start: NodeIndex,
+        target: NodeIndex,
+        vel: &ConstVel2D,
+        blocked: &[NodeIndex],
+    ) -> Option<Vec<EdgeIndex>> {
+        let g = NodeFiltered::from_fn(&self.pet_g, |n| {
+            n == start || n == target || !blocked.contains(&n)
+        });
+        let goal = self.pet_g[target];
+        let (_, nodes) = astar(
+            &g,
+            start,
+            |n| n == target,
+            |e| self.travel_time(e.id(), vel),
+            // No road is faster than the straight line at full velocity.
+            |n| timer(self.pet_g[n], goal, vel),
+        )?;
+        nodes
+            .windows(2)
+            .map(|w| self.road_between(w[0], w[1], vel))
+            .collect()
+    }
+
+    /// The fastest road from one node to another, if any.
+    pub fn road_between(
+        &self,
+        from: NodeIndex,
+        to: NodeIndex,
+        vel: &ConstVel2D,
+    ) -> Option<EdgeIndex> {
+        self.pet_g
+            .edges_connecting(from, to)
+            .map(|e| e.id())
+            .min_by(|&e1, &e2| {
+                self.travel_time(e1, vel)
+                    .total_cmp(&self.travel_time(e2, vel))
+            })
+    }
+
+    /// The nodes reachable from `start` avoiding the blocked nodes, fastest
+    /// first.
+    pub fn reachable(
+        &self,
+        start: NodeIndex,
+        vel: &ConstVel2D,
+        blocked: &[NodeIndex],
+    ) -> Vec<NodeIndex> {
+        let g = NodeFiltered::from_fn(&self.pet_g, |n| n == start || !blocked.contains(&n));
+        let mut times = dijkstra(&g, start, None, |e| self.travel_time(e.id(), vel))
+            .into_iter()
+            .collect::<Vec<_>>();
+        times.sort_by(|(_, t1), (_, t2)| t1.total_cmp(t2));
+        times.into_iter().map(|(n, _)| n).collect()
+    }
+
+    /// The segments of the route.
+    pub fn segments(&self, route: &[EdgeIndex], vel: &ConstVel2D) -> Vec<Segment> {
+        route
+            .iter()
+            .map(|&e| {
+                let (from, to) = self.ends(e);
+                Segment {
+                    start: self.pet_g[from],
+                    end: self.pet_g[to],
+                    duration: self.travel_time(e, vel),
+                }
+            })
+            .collect()
+    }
+}
@@ -1,8 +1,13 @@
-use geo::{Coord, LineString, Polygon};
+use geo::{Coord, CoordsIter, LineString, MultiPolygon, Polygon};
+use itertools::Itertools;
 
 use crate::actions::{
-    data::{Action, ActionType, Agent, ConstVel2D, Segment},
-    graphs::{find_path_2d_g, timer},
+    data::{Action, ActionType, Agent, Axis, ConstVel2D, Motion, Planner, Segment},
+    fixtures::{action, agent},
+    graphs::{
+        crosses_interior, find_path_2d_g, free_region, grid::NavGrid, heuristic, timer, NavGraph,
+        Search,
+    },
 };
 
 #[test]
@@ -13,27 +18,337 @@ fn test_find_path_2d_g() {
         reach: Polygon::new(
             LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]),
             vec![],
-        ),
+        )
+        .into(),
+        corridors: MultiPolygon(vec![]),
         position: start,
         velocity: ConstVel2D { x: 2.0, y: 1.0 },
+        motion: Motion::Simultaneous,
         safety_x: 10.0,
         order: 0,
+        capabilities: vec![],
+        unavailable: vec![],
+        planner: Planner::Visibility,
     };
     let target = Coord { x: 90.0, y: 90.0 };
     let action = Action {
         agent: agent.clone(),
-        target: target,
+        target,
         duration: 10.0,
         r#type: ActionType::Scheduled,
+        requires: vec![],
+        std_dev: 0.0,
     };
 
     let expected = Some(vec![Segment {
-        start: start,
+        start,
         end: target,
         duration: timer(start, target, &agent.velocity),
     }]);
 
-    let actual = find_path_2d_g(&action, start);
+    let actual = find_path_2d_g(&action, start, &[]);
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn test_find_path_2d_g_obstacle() {
+    let start = Coord { x: 10.0, y: 10.0 };
+    let agent = Agent {
+        reach: Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]),
+            vec![],
+        )
+        .into(),
+        position: start,
+        ..agent("agent", 10.0, 0)
+    };
+    let obstacle = Polygon::new(
+        LineString::from(vec![(40.0, 0.0), (60.0, 0.0), (60.0, 80.0), (40.0, 80.0)]),
+        vec![],
+    );
+    let target = Coord { x: 90.0, y: 10.0 };
+    let action = Action {
+        target,
+        ..action(&agent, 90.0, 10.0)
+    };
+
+    let actual = find_path_2d_g(&action, start, &[&obstacle]).unwrap();
+
+    assert_eq!(actual.first().unwrap().start, start);
+    assert_eq!(actual.last().unwrap().end, target);
+    assert!(actual
+        .iter()
+        .all(|s| !crosses_interior(&obstacle, s.start, s.end)));
+    assert!(actual.iter().any(|s| s.end.y >= 80.0));
+}
+
+#[test]
+fn test_free_region_keeps_edges() {
+    let reach = MultiPolygon(vec![Polygon::new(
+        LineString::from(vec![
+            (0.0, 0.0),
+            (163.187006321396, 0.0),
+            (163.187006321396, 50.0),
+            (0.0, 50.0),
+        ]),
+        vec![],
+    )]);
+    let obstacle = Polygon::new(
+        LineString::from(vec![
+            (75.4264015678714, 20.0),
+            (78.0025680286629, 20.0),
+            (78.0025680286629, 30.0),
+            (75.4264015678714, 30.0),
+        ]),
+        vec![],
+    );
+
+    let actual = free_region(&reach, &[&obstacle]);
+
+    // The edges stay where they are, so that positions on them, e.g. of
+    // agents, stay within the region.
+    let expected = reach
+        .coords_iter()
+        .chain(obstacle.coords_iter())
+        .collect::<Vec<_>>();
+    assert!(actual.coords_iter().all(|c| expected.contains(&c)));
+    let grid = NavGrid::new(
+        &actual,
+        1.9486199465924103,
+        ConstVel2D { x: 2.0, y: 1.0 },
+        Motion::Simultaneous,
+    );
+    assert!(grid
+        .find_path(Coord { x: 0.0, y: 10.0 }, Coord { x: 0.0, y: 5.0 })
+        .is_some());
+}
+
+/// A square of 100 by 100 with a 5 by 5 grid of square holes.
+fn perforated_reach() -> Polygon {
+    let holes = (0..5)
+        .cartesian_product(0..5)
+        .map(|(i, j)| {
+            let (x, y) = (10.0 + 18.0 * i as f64, 10.0 + 18.0 * j as f64);
+            LineString::from(vec![(x, y), (x + 8.0, y), (x + 8.0, y + 8.0), (x, y + 8.0)])
+        })
+        .collect::<Vec<_>>();
+    Polygon::new(
+        LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]),
+        holes,
+    )
+}
+
+#[test]
+fn test_nav_graph_reuse() {
+    let reach = perforated_reach();
+    let agent = Agent {
+        reach: reach.clone().into(),
+        position: Coord { x: 5.0, y: 5.0 },
+        ..agent("agent", 5.0, 0)
+    };
+    let mut nav = NavGraph::new(MultiPolygon(vec![reach]), agent.velocity, agent.motion);
+    let points = [
+        (5.0, 5.0),
+        (95.0, 95.0),
+        (22.0, 14.0),
+        (80.0, 50.0),
+        (50.0, 3.0),
+    ]
+    .map(|(x, y)| Coord { x, y });
+
+    for (&start, &target) in points.iter().tuple_combinations() {
+        let action = Action {
+            target,
+            ..action(&agent, 0.0, 0.0)
+        };
+        let expected = find_path_2d_g(&action, start, &[]).unwrap();
+
+        let first = nav.find_path(start, target).unwrap();
+        let second = nav.find_path(start, target).unwrap();
+
+        let duration = |p: &[Segment]| p.iter().map(|s| s.duration).sum::<f64>();
+        assert_eq!(first, second);
+        assert!((duration(&first) - duration(&expected)).abs() < 1e-9);
+        assert_eq!(first.first().unwrap().start, start);
+        assert_eq!(first.last().unwrap().end, target);
+    }
+}
+
+#[test]
+fn test_astar_matches_dijkstra() {
+    let velocity = ConstVel2D { x: 2.0, y: 1.0 };
+    let mut nav = NavGraph::new(
+        MultiPolygon(vec![perforated_reach()]),
+        velocity,
+        Motion::Simultaneous,
+    );
+    let points = [5.0, 41.0, 77.0, 95.0]
+        .into_iter()
+        .cartesian_product([5.0, 23.0, 59.0, 95.0])
+        .map(|(x, y)| Coord { x, y })
+        .collect::<Vec<_>>();
+
+    for (&start, &target) in points.iter().tuple_combinations() {
+        let astar = nav.find_path_with(start, target, Search::AStar).unwrap();
+        let dijkstra = nav.find_path_with(start, target, Search::Dijkstra).unwrap();
+
+        let duration = |p: &[Segment]| p.iter().map(|s| s.duration).sum::<f64>();
+        assert!((duration(&astar) - duration(&dijkstra)).abs() < 1e-9);
+        assert!(
+            duration(&astar) >= heuristic(start, target, &velocity, Motion::Simultaneous) - 1e-9
+        );
+    }
+}
+
+#[test]
+fn test_grid_planner() {
+    let reach = perforated_reach();
+    let velocity = ConstVel2D { x: 2.0, y: 1.0 };
+    let region = MultiPolygon(vec![reach.clone()]);
+    let mut nav = NavGraph::new(region.clone(), velocity, Motion::Simultaneous);
+    let grid = NavGrid::new(&region, 1.0, velocity, Motion::Simultaneous);
+    let holes = reach
+        .interiors()
+        .iter()
+        .map(|r| Polygon::new(r.clone(), vec![]))
+        .collect::<Vec<_>>();
+    let points = [
+        (5.0, 5.0),
+        (95.0, 95.0),
+        (22.0, 14.0),
+        (80.0, 50.0),
+        (0.0, 100.0),
+    ]
+    .map(|(x, y)| Coord { x, y });
+
+    for (&start, &target) in points.iter().tuple_combinations() {
+        let path = grid.find_path(start, target).unwrap();
+        let optimal = nav.find_path(start, target).unwrap();
+
+        let duration = |p: &[Segment]| p.iter().map(|s| s.duration).sum::<f64>();
+        assert_eq!(path.first().unwrap().start, start);
+        assert_eq!(path.last().unwrap().end, target);
+        assert!(path
+            .iter()
+            .tuple_windows()
+            .all(|(s1, s2)| s1.end == s2.start));
+        assert!(path
+            .iter()
+            .all(|s| holes.iter().all(|h| !crosses_interior(h, s.start, s.end))));
+        assert!(duration(&path) >= duration(&optimal) - 1e-9);
+        assert!(duration(&path) <= 1.2 * duration(&optimal));
+    }
+}
+
+#[test]
+fn test_axis_aligned_paths() {
+    let reach = perforated_reach();
+    let velocity = ConstVel2D { x: 2.0, y: 1.0 };
+    let region = MultiPolygon(vec![reach.clone()]);
+    let holes = reach
+        .interiors()
+        .iter()
+        .map(|r| Polygon::new(r.clone(), vec![]))
+        .collect::<Vec<_>>();
+    let points =
+        [(5.0, 5.0), (95.0, 95.0), (22.0, 14.0), (80.0, 50.0)].map(|(x, y)| Coord { x, y });
+
+    for first in [Axis::X, Axis::Y] {
+        let motion = Motion::AxisAligned { first };
+        let mut nav = NavGraph::new(region.clone(), velocity, motion);
+        let grid = NavGrid::new(&region, 1.0, velocity, motion);
+        for (&start, &target) in points.iter().tuple_combinations() {
+            for path in [
+                nav.find_path(start, target).unwrap(),
+                grid.find_path(start, target).unwrap(),
+            ] {
+                let duration = path.iter().map(|s| s.duration).sum::<f64>();
+                assert_eq!(path.first().unwrap().start, start);
+                assert_eq!(path.last().unwrap().end, target);
+                assert!(path
+                    .iter()
+                    .all(|s| s.start.x == s.end.x || s.start.y == s.end.y));
+                assert!(path
+                    .iter()
+                    .all(|s| holes.iter().all(|h| !crosses_interior(h, s.start, s.end))));
+                assert!(duration >= heuristic(start, target, &velocity, motion) - 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_grid_endpoints_next_to_obstacle() {
+    let reach = Polygon::new(
+        LineString::from(vec![(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)]),
+        vec![LineString::from(vec![
+            (6.2, 8.5),
+            (6.6, 8.5),
+            (6.6, 11.5),
+            (6.2, 11.5),
+        ])],
+    );
+    let hole = Polygon::new(reach.interiors()[0].clone(), vec![]);
+    let velocity = ConstVel2D { x: 2.0, y: 1.0 };
+    let grid = NavGrid::new(
+        &MultiPolygon(vec![reach]),
+        2.0,
+        velocity,
+        Motion::Simultaneous,
+    );
+    // The target shares its cell with the obstacle, which the straight line
+    // from the start passes through.
+    let (start, target) = (Coord { x: 1.0, y: 9.0 }, Coord { x: 7.0, y: 9.0 });
+
+    for (a, b) in [(start, target), (target, start)] {
+        let path = grid.find_path(a, b).unwrap();
+        assert_eq!(path.first().unwrap().start, a);
+        assert_eq!(path.last().unwrap().end, b);
+        assert!(path
+            .iter()
+            .all(|s| !crosses_interior(&hole, s.start, s.end)));
+    }
+    // Within a cell, the obstacle cannot be bypassed.
+    let (left, right) = (Coord { x: 6.1, y: 9.0 }, Coord { x: 6.7, y: 9.0 });
+    assert_eq!(grid.find_path(left, right), None);
+    // Within a cell clear of it, the path leads straight to the target.
+    let (a, b) = (Coord { x: 0.5, y: 0.5 }, Coord { x: 1.5, y: 1.0 });
+    let path = grid.find_path(a, b).unwrap();
+    assert_eq!(path.len(), 1);
+    assert_eq!((path[0].start, path[0].end), (a, b));
+    assert_eq!(path[0].duration, timer(a, b, &velocity));
+}
+
+#[test]
+fn test_one_way_moves() {
+    let reach = Polygon::new(
+        LineString::from(vec![
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 2.0),
+            (2.0, 2.0),
+            (2.0, 10.0),
+            (0.0, 10.0),
+        ]),
+        vec![],
+    );
+    let motion = Motion::AxisAligned { first: Axis::X };
+    let nav = NavGraph::new(
+        MultiPolygon(vec![reach]),
+        ConstVel2D { x: 2.0, y: 1.0 },
+        motion,
+    );
+    let node = |x: f64, y: f64| {
+        nav.pet_g
+            .node_indices()
+            .skip(2)
+            .find(|&n| nav.pet_g[n] == Coord { x, y })
+            .unwrap()
+    };
+    // Moving x first along the arms of the L stays within it, while the
+    // move back passes the corner outside.
+    let (lower, upper) = (node(10.0, 2.0), node(0.0, 10.0));
+    assert!(nav.pet_g.find_edge(lower, upper).is_some());
+    assert!(nav.pet_g.find_edge(upper, lower).is_none());
+}
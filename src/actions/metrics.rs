@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use geo::{Distance, Euclidean, Point};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    data::{ActionType, Path},
+    Routing,
+};
+
+/// Key figures of a set of paths, broken down by the type of their action.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TypeMetrics {
+    /// The number of paths of this type
+    pub count: usize,
+    /// The distance travelled to reach the targets
+    pub travel_distance: f64,
+    /// The time spent travelling to the targets
+    pub travel_time: f64,
+    /// The time spent executing the actions at the targets
+    pub action_time: f64,
+}
+
+/// Key figures of the paths of a single agent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentMetrics {
+    /// The name of the agent
+    pub agent: String,
+    /// The end time of the last path of the agent
+    pub t_end: f64,
+    /// The total distance travelled
+    pub travel_distance: f64,
+    /// The total time spent travelling
+    pub travel_time: f64,
+    /// The total time spent waiting in idle paths
+    pub idle_time: f64,
+    /// The number of evasive moves
+    pub evasive_moves: usize,
+    /// The total duration of the evasive moves
+    pub evasive_time: f64,
+    /// The fraction of the makespan the agent is travelling or executing an
+    /// action, excluding the time it is parked
+    pub utilisation: f64,
+    /// The figures per action type
+    pub by_type: BTreeMap<ActionType, TypeMetrics>,
+}
+
+/// Solution quality report of a routing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// The end time of the last path of all agents
+    pub makespan: f64,
+    /// The total distance travelled by all agents
+    pub travel_distance: f64,
+    /// The total time spent travelling by all agents
+    pub travel_time: f64,
+    /// The total time spent waiting by all agents
+    pub idle_time: f64,
+    /// The total number of evasive moves
+    pub evasive_moves: usize,
+    /// The total duration of the evasive moves
+    pub evasive_time: f64,
+    /// The figures per action type over all agents
+    pub by_type: BTreeMap<ActionType, TypeMetrics>,
+    /// The figures per agent
+    pub agents: Vec<AgentMetrics>,
+}
+
+/// Differences between the key figures of two routings, computed as
+/// `other - self`. Negative values mean `other` is better.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetricsDelta {
+    /// The difference in makespan
+    pub makespan: f64,
+    /// The difference in total travel distance
+    pub travel_distance: f64,
+    /// The difference in total travel time
+    pub travel_time: f64,
+    /// The difference in total idle time
+    pub idle_time: f64,
+    /// The difference in the number of evasive moves
+    pub evasive_moves: i64,
+    /// The difference in total evasive time
+    pub evasive_time: f64,
+    /// The differences per agent present in both routings
+    pub agents: BTreeMap<String, AgentMetricsDelta>,
+}
+
+/// Differences between the key figures of an agent in two routings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentMetricsDelta {
+    /// The difference in the end time of the last path
+    pub t_end: f64,
+    /// The difference in travel distance
+    pub travel_distance: f64,
+    /// The difference in travel time
+    pub travel_time: f64,
+    /// The difference in idle time
+    pub idle_time: f64,
+    /// The difference in the number of evasive moves
+    pub evasive_moves: i64,
+    /// The difference in evasive time
+    pub evasive_time: f64,
+    /// The difference in utilisation
+    pub utilisation: f64,
+}
+
+impl TypeMetrics {
+    fn add_path(&mut self, p: &Path) {
+        self.count += 1;
+        self.travel_distance += p
+            .moves
+            .iter()
+            .map(|s| Euclidean.distance(Point::from(s.start), Point::from(s.end)))
+            .sum::<f64>();
+        self.travel_time += p.moves.iter().map(|s| s.duration).sum::<f64>();
+        self.action_time += p.action.duration;
+    }
+
+    fn add(&mut self, other: &TypeMetrics) {
+        self.count += other.count;
+        self.travel_distance += other.travel_distance;
+        self.travel_time += other.travel_time;
+        self.action_time += other.action_time;
+    }
+}
+
+impl AgentMetrics {
+    fn new(agent: &str, paths: &[Path], makespan: f64) -> AgentMetrics {
+        let mut by_type: BTreeMap<ActionType, TypeMetrics> = BTreeMap::new();
+        // The initial path only places the agent at its position.
+        for p in paths
+            .iter()
+            .filter(|p| p.t_end > p.t_start || !p.moves.is_empty())
+        {
+            by_type
+                .entry(p.action.r#type.clone())
+                .or_default()
+                .add_path(p);
+        }
+        let total = |f: fn(&TypeMetrics) -> f64| by_type.values().map(f).sum::<f64>();
+        let travel_distance = total(|m| m.travel_distance);
+        let travel_time = total(|m| m.travel_time);
+        let idle = by_type.get(&ActionType::Idle).cloned().unwrap_or_default();
+        let evasive = by_type
+            .get(&ActionType::Evasive)
+            .cloned()
+            .unwrap_or_default();
+        let parked = by_type
+            .get(&ActionType::Parked)
+            .map_or(0.0, |m| m.action_time);
+        let busy = travel_time + total(|m| m.action_time) - idle.action_time - parked;
+        AgentMetrics {
+            agent: agent.to_string(),
+            t_end: paths.iter().map(|p| p.t_end).fold(0.0, f64::max),
+            travel_distance,
+            travel_time,
+            idle_time: idle.action_time,
+            evasive_moves: evasive.count,
+            evasive_time: evasive.travel_time + evasive.action_time,
+            utilisation: if makespan > 0.0 { busy / makespan } else { 0.0 },
+            by_type,
+        }
+    }
+
+    fn compare(&self, other: &AgentMetrics) -> AgentMetricsDelta {
+        AgentMetricsDelta {
+            t_end: other.t_end - self.t_end,
+            travel_distance: other.travel_distance - self.travel_distance,
+            travel_time: other.travel_time - self.travel_time,
+            idle_time: other.idle_time - self.idle_time,
+            evasive_moves: other.evasive_moves as i64 - self.evasive_moves as i64,
+            evasive_time: other.evasive_time - self.evasive_time,
+            utilisation: other.utilisation - self.utilisation,
+        }
+    }
+}
+
+impl Metrics {
+    /// Compare these metrics with those of another routing of the same
+    /// agents.
+    pub fn compare(&self, other: &Metrics) -> MetricsDelta {
+        MetricsDelta {
+            makespan: other.makespan - self.makespan,
+            travel_distance: other.travel_distance - self.travel_distance,
+            travel_time: other.travel_time - self.travel_time,
+            idle_time: other.idle_time - self.idle_time,
+            evasive_moves: other.evasive_moves as i64 - self.evasive_moves as i64,
+            evasive_time: other.evasive_time - self.evasive_time,
+            agents: self
+                .agents
+                .iter()
+                .filter_map(|a| {
+                    other
+                        .agents
+                        .iter()
+                        .find(|o| o.agent == a.agent)
+                        .map(|o| (a.agent.clone(), a.compare(o)))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Routing {
+    /// Compute the solution quality metrics of the routing.
+    pub fn metrics(&self) -> Metrics {
+        let makespan = self
+            .routes
+            .iter()
+            .flat_map(|(_, ps)| ps.iter().map(|p| p.t_end))
+            .fold(0.0, f64::max);
+        let agents = self
+            .routes
+            .iter()
+            .map(|(a, ps)| AgentMetrics::new(&a.name, ps, makespan))
+            .collect::<Vec<_>>();
+        let mut by_type: BTreeMap<ActionType, TypeMetrics> = BTreeMap::new();
+        for (t, m) in agents.iter().flat_map(|a| a.by_type.iter()) {
+            by_type.entry(t.clone()).or_default().add(m);
+        }
+        Metrics {
+            makespan,
+            travel_distance: agents.iter().map(|a| a.travel_distance).sum(),
+            travel_time: agents.iter().map(|a| a.travel_time).sum(),
+            idle_time: agents.iter().map(|a| a.idle_time).sum(),
+            evasive_moves: agents.iter().map(|a| a.evasive_moves).sum(),
+            evasive_time: agents.iter().map(|a| a.evasive_time).sum(),
+            by_type,
+            agents,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+use geo::Coord;
+
+use crate::actions::{
+    data::{ActionType, Parking, Schedule, Unavailability},
+    fixtures::{action, agent},
+    routes,
+    site::TimeWindow,
+};
+
+#[test]
+fn test_metrics_evasion() {
+    let agents = vec![agent("agent-0", 10.0, 0), agent("agent-1", 80.0, 1)];
+    let schedule = Schedule {
+        actions: vec![action(&agents[1], 15.0, 5.0)],
+    };
+
+    let routing = routes(&agents, schedule).unwrap();
+    let metrics = routing.metrics();
+
+    let t_end = routing
+        .routes
+        .iter()
+        .flat_map(|(_, ps)| ps.iter().map(|p| p.t_end))
+        .fold(0.0, f64::max);
+    assert_eq!(metrics.makespan, t_end);
+    assert_eq!(metrics.evasive_moves, 1);
+    assert_eq!(metrics.agents[0].evasive_moves, 1);
+    assert_eq!(metrics.agents[0].travel_distance, 5.0);
+    assert_eq!(metrics.agents[1].travel_distance, 65.0);
+    assert_eq!(metrics.by_type[&ActionType::Scheduled].count, 1);
+    assert_eq!(metrics.by_type[&ActionType::Scheduled].action_time, 5.0);
+    assert!(metrics.agents[1].utilisation > 0.0 && metrics.agents[1].utilisation <= 1.0);
+}
+
+#[test]
+fn test_metrics_compare() {
+    let agents = vec![agent("agent-0", 10.0, 0), agent("agent-1", 80.0, 1)];
+    let near = routes(
+        &agents,
+        Schedule {
+            actions: vec![action(&agents[1], 15.0, 5.0)],
+        },
+    )
+    .unwrap()
+    .metrics();
+    let far = routes(
+        &agents,
+        Schedule {
+            actions: vec![action(&agents[1], 60.0, 5.0)],
+        },
+    )
+    .unwrap()
+    .metrics();
+
+    let same = near.compare(&near);
+    assert_eq!(same.makespan, 0.0);
+    assert_eq!(same.evasive_moves, 0);
+
+    let delta = near.compare(&far);
+    assert_eq!(delta.evasive_moves, -1);
+    assert!(delta.makespan < 0.0);
+    assert_eq!(delta.agents["agent-1"].travel_distance, -45.0);
+}
+
+#[test]
+fn test_metrics_idle_and_parked() {
+    let mut crane = agent("agent-2", 80.0, 2);
+    crane.unavailable = vec![Unavailability {
+        window: TimeWindow {
+            start: 10.0,
+            end: 60.0,
+        },
+        parking: Parking::Position(Coord { x: 90.0, y: 5.0 }),
+    }];
+    let agents = vec![agent("agent-0", 10.0, 0), agent("agent-1", 30.0, 1), crane];
+    let schedule = Schedule {
+        actions: vec![action(&agents[2], 85.0, 2.0), action(&agents[0], 15.0, 5.0)],
+    };
+    let metrics = routes(&agents, schedule).unwrap().metrics();
+
+    // The crane works until 4.5, waits until 7 to leave for its parking
+    // just in time and stays there until 60, which ends the routing.
+    assert_eq!(metrics.makespan, 60.0);
+    assert_eq!(metrics.idle_time, 2.5);
+    let crane = &metrics.agents[2];
+    assert_eq!(crane.t_end, 60.0);
+    assert_eq!(crane.idle_time, 2.5);
+    assert_eq!(crane.travel_time, 5.0);
+    assert_eq!(crane.by_type[&ActionType::Idle].count, 1);
+    assert_eq!(crane.by_type[&ActionType::Parked].action_time, 50.5);
+    // Neither the wait nor the time parked count as busy, but driving to
+    // the parking does.
+    assert_eq!(crane.utilisation, 7.0 / 60.0);
+
+    // An agent without actions that never has to move is not busy at all.
+    let idle = &metrics.agents[1];
+    assert_eq!(idle.t_end, 0.0);
+    assert_eq!(idle.travel_distance, 0.0);
+    assert_eq!(idle.idle_time, 0.0);
+    assert_eq!(idle.utilisation, 0.0);
+    assert!(idle.by_type.is_empty());
+    assert_eq!(metrics.agents[0].utilisation, 7.5 / 60.0);
+}
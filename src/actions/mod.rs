@@ -1,10 +1,41 @@
-use data::{Action, ActionType, Agent, Path, PointST, Schedule, Segment};
+use config::SolverConfig;
+use data::{
+    Action, ActionType, Agent, ConstVel2D, Path, PointST, Schedule, Segment, Unavailability,
+};
 use geo::Coord;
+use graphs::{road::RoadGraph, Navigator};
 use itertools::Itertools;
-use std::cmp::Ordering;
+use reservations::{Reservations, Trip};
+use serde::{Deserialize, Serialize};
+use site::{Site, TimeWindow};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+};
+use trace::{Blocking, ConflictResolution, Decision, Trace, TraceEvent};
+use validation::{validate, validate_config, validate_site, ValidationIssue};
 
+pub mod assignment;
+pub mod commands;
+pub mod config;
 pub mod data;
-mod graphs;
+pub mod explain;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fixtures;
+pub mod gis;
+pub mod graphs;
+pub mod metrics;
+pub mod precedence;
+pub mod reservations;
+pub mod sampling;
+pub mod scenario;
+pub mod simulation;
+pub mod site;
+pub mod trace;
+pub mod units;
+pub mod validation;
 
 impl Path {
     pub fn to_points_st(&self) -> Vec<PointST> {
@@ -18,7 +49,7 @@ impl Path {
         };
         let mut clock = self.t_start;
         for s in self.moves.iter() {
-            clock = clock + s.duration;
+            clock += s.duration;
             result.push(PointST {
                 x: s.end.x,
                 y: s.end.y,
@@ -28,21 +59,12 @@ impl Path {
         result.push(PointST {
             x: self.action.target.x,
             y: self.action.target.y,
-            t: clock + self.action.duration,
+            t: self.t_end,
         });
         result
     }
 }
 
-/// Indicator on how a conflict is resolved
-enum ConflictResolution {
-    /// The conflict is resolved by an x-pos lower than the value
-    LowerThanX(f64),
-
-    /// The conflict is resolved by an x-pos higher than the value
-    HigherThanX(f64),
-}
-
 /// A conflict is caused by an agent, being at the position of its latest
 /// action, and hindering the action now to be done by some other agent.
 struct Conflict<'a> {
@@ -52,129 +74,837 @@ struct Conflict<'a> {
 
     /// The resolution for the conflict.
     resolution: ConflictResolution,
+
+    /// The distance the agents keep along the x-axis.
+    distance: f64,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Routing {
     /// The list of paths for each agent.
     pub routes: Vec<(Agent, Vec<Path>)>,
+    /// The decisions of the solver, if it was asked to record them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Trace>,
+}
+
+/// The reason a schedule cannot be routed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoutingError {
+    /// The agents or schedule failed validation. Contains all issues found,
+    /// at least one of which is an error.
+    Invalid(Vec<ValidationIssue>),
+    /// No path leads from the start to the target within the reach and
+    /// corridors of the agent, not even after temporary obstacles are gone.
+    NoPath {
+        /// The name of the agent
+        agent: String,
+        /// The start of the path
+        start: Coord,
+        /// The target of the path
+        target: Coord,
+    },
+    /// The agent cannot be parked before its unavailability starts.
+    Unavailable {
+        /// The name of the agent
+        agent: String,
+        /// The window the agent is unavailable
+        window: TimeWindow,
+    },
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingError::Invalid(issues) => {
+                write!(f, "invalid input")?;
+                for i in issues.iter() {
+                    write!(f, "\n  {}", i)?;
+                }
+                Ok(())
+            }
+            RoutingError::NoPath {
+                agent,
+                start,
+                target,
+            } => write!(
+                f,
+                "no path for agent '{}' from ({}, {}) to ({}, {})",
+                agent, start.x, start.y, target.x, target.y
+            ),
+            RoutingError::Unavailable { agent, window } => write!(
+                f,
+                "agent '{}' cannot be parked before it is unavailable from {} to {}",
+                agent, window.start, window.end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+/// The solver computes routes for agents operating on a site.
+#[derive(Clone, Debug, Default)]
+pub struct Solver {
+    /// The site the agents operate on
+    pub site: Site,
+    /// The numeric settings of the solver
+    pub config: SolverConfig,
+    /// Whether to record the decisions of the solver in the trace of the
+    /// routing
+    pub trace: bool,
 }
 
 /// Compute routes for each agent, given a schedule of actions
-pub fn routes(agents: &Vec<Agent>, sched: Schedule) -> Routing {
-    let init: Vec<(Agent, Vec<Path>)> = agents
-        .iter()
-        .map(|a| {
-            (
-                a.clone(),
-                vec![Path {
+pub fn routes(agents: &[Agent], sched: Schedule) -> Result<Routing, RoutingError> {
+    Solver::default().routes(agents, sched)
+}
+
+impl Solver {
+    /// Compute routes for each agent, given a schedule of actions
+    pub fn routes(&self, agents: &[Agent], sched: Schedule) -> Result<Routing, RoutingError> {
+        let init: Vec<(Agent, Vec<Path>)> = agents
+            .iter()
+            .map(|a| {
+                (
+                    a.clone(),
+                    vec![Path {
+                        moves: vec![],
+                        action: Action {
+                            agent: a.clone(),
+                            target: a.position,
+                            duration: 0.0,
+                            r#type: ActionType::Idle,
+                            requires: vec![],
+                            std_dev: 0.0,
+                        },
+                        t_start: 0.0,
+                        t_end: 0.0,
+                        step: None,
+                    }],
+                )
+            })
+            .collect();
+        self.extend(agents, init, None, 0, sched)
+    }
+
+    /// Replan incrementally: execute further scheduled actions after the
+    /// paths of a routing, which stay as they are. The actions are numbered
+    /// after those already routed, and the decisions are recorded after
+    /// those of the trace of the routing.
+    pub fn replan(&self, routing: &Routing, sched: Schedule) -> Result<Routing, RoutingError> {
+        let agents = routing
+            .routes
+            .iter()
+            .map(|(a, _)| a.clone())
+            .collect::<Vec<_>>();
+        let first = routing
+            .routes
+            .iter()
+            .flat_map(|(_, ps)| ps.iter().filter_map(|p| p.step))
+            .max()
+            .map_or(0, |s| s + 1);
+        self.extend(
+            &agents,
+            routing.routes.clone(),
+            routing.trace.clone(),
+            first,
+            sched,
+        )
+    }
+
+    /// Execute the schedule after the given paths, numbering the actions
+    /// from `first`.
+    fn extend(
+        &self,
+        agents: &[Agent],
+        init: Vec<(Agent, Vec<Path>)>,
+        trace: Option<Trace>,
+        first: usize,
+        sched: Schedule,
+    ) -> Result<Routing, RoutingError> {
+        let mut issues = validate_config(&self.config, None);
+        issues.extend(validate(agents, &sched, &self.config));
+        issues.extend(validate_site(&self.site, agents, &self.config));
+        if issues.iter().any(|i| i.is_error()) {
+            return Err(RoutingError::Invalid(issues));
+        }
+        let run = Run {
+            solver: self,
+            nav: RefCell::new(HashMap::new()),
+            roads: RoadGraph::new(&self.site.roads),
+            trace: self.trace.then(|| RefCell::new(trace.unwrap_or_default())),
+            step: Cell::new(None),
+        };
+        let r = sched
+            .actions
+            .iter()
+            .enumerate()
+            .try_fold(init, |acc, (i, a)| {
+                run.step.set(Some(first + i));
+                let frontier = acc
+                    .iter()
+                    .map(|(_, ps)| ps.last().unwrap().t_end)
+                    .fold(0.0, f64::max);
+                let acc = run.park_due(acc, frontier)?;
+                run.execute_action(a, acc)
+            })?;
+        run.step.set(None);
+        let r = run.park_due(r, f64::INFINITY)?;
+
+        Ok(Routing {
+            routes: r,
+            trace: run.trace.map(RefCell::into_inner),
+        })
+    }
+}
+
+/// The state of the solver while computing routes.
+struct Run<'a> {
+    /// The solver settings
+    solver: &'a Solver,
+    /// The navigation structures per agent and set of obstacles present,
+    /// given by their index in the site.
+    nav: RefCell<HashMap<(String, Vec<usize>), Navigator>>,
+    /// The road network of the site
+    roads: RoadGraph,
+    /// The decisions taken so far, if they are recorded
+    trace: Option<RefCell<Trace>>,
+    /// The index of the scheduled action being executed
+    step: Cell<Option<usize>>,
+}
+
+impl Run<'_> {
+    /// Record a decision taken while executing the action.
+    fn record(&self, time: f64, action: &Action, decision: Decision) {
+        if let Some(trace) = &self.trace {
+            let event = TraceEvent::new(self.step.get(), time, action, decision);
+            trace.borrow_mut().events.push(event);
+        }
+    }
+
+    /// The number of decisions recorded, to rewind to when an attempt is
+    /// discarded.
+    fn recorded(&self) -> usize {
+        self.trace.as_ref().map_or(0, |t| t.borrow().events.len())
+    }
+
+    fn rewind(&self, recorded: usize) {
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().events.truncate(recorded);
+        }
+    }
+
+    /// Execute an action, i.e., find a path for the agent to arrive at the
+    /// action target and resolve any existing conflicts. If the agent would
+    /// become unavailable before the action is finished, it is parked first.
+    fn execute_action(
+        &self,
+        action: &Action,
+        r: Vec<(Agent, Vec<Path>)>,
+    ) -> Result<Vec<(Agent, Vec<Path>)>, RoutingError> {
+        if action.agent.unavailable.is_empty() || action.r#type == ActionType::Parked {
+            return self.execute_available(action, r);
+        }
+        let t0 = agent_paths(&action.agent, &r).last().unwrap().t_end;
+        let recorded = self.recorded();
+        let result = self.execute_available(action, r.clone())?;
+        let t1 = agent_paths(&action.agent, &result).last().unwrap().t_end;
+        match action
+            .agent
+            .unavailable
+            .iter()
+            .find(|u| u.window.end > t0 && u.window.start < t1)
+        {
+            Some(u) => {
+                self.rewind(recorded);
+                let r = self.park(&action.agent, u, r)?;
+                self.execute_action(action, r)
+            }
+            None => Ok(result),
+        }
+    }
+
+    /// Park every agent whose next unavailability starts no later than the
+    /// horizon, in the order the windows start.
+    fn park_due(
+        &self,
+        r: Vec<(Agent, Vec<Path>)>,
+        horizon: f64,
+    ) -> Result<Vec<(Agent, Vec<Path>)>, RoutingError> {
+        let mut result = r;
+        loop {
+            let due = result
+                .iter()
+                .flat_map(|(a, ps)| {
+                    let t = ps.last().unwrap().t_end;
+                    a.unavailable
+                        .iter()
+                        .filter(move |u| u.window.end > t && u.window.start <= horizon)
+                        .map(move |u| (a, u))
+                })
+                .min_by(|(_, u1), (_, u2)| u1.window.start.total_cmp(&u2.window.start))
+                .map(|(a, u)| (a.clone(), u.clone()));
+            match due {
+                Some((a, u)) => result = self.park(&a, &u, result)?,
+                None => return Ok(result),
+            }
+        }
+    }
+
+    /// Move the agent to its parking position before the unavailability
+    /// starts and keep it there until the window ends. The agent departs as
+    /// late as possible, or right away if that is too late.
+    fn park(
+        &self,
+        agent: &Agent,
+        unavailability: &Unavailability,
+        r: Vec<(Agent, Vec<Path>)>,
+    ) -> Result<Vec<(Agent, Vec<Path>)>, RoutingError> {
+        let last_path = agent_paths(agent, &r).last().unwrap();
+        let (t0, position) = (last_path.t_end, last_path.action.target);
+        let window = unavailability.window;
+        let target = unavailability.parking.closest(position);
+        let action = Action {
+            agent: agent.clone(),
+            target,
+            duration: 0.0,
+            r#type: ActionType::Parked,
+            requires: vec![],
+            std_dev: 0.0,
+        };
+        let attempt = |departure: f64, r: Vec<(Agent, Vec<Path>)>| {
+            let mut r = r;
+            let i = r.iter().position(|(a, _)| a.name == agent.name).unwrap();
+            if departure > t0 {
+                r[i].1.push(Path {
                     moves: vec![],
                     action: Action {
-                        agent: a.clone(),
-                        target: a.position,
-                        duration: 0.0,
+                        agent: agent.clone(),
+                        target: position,
+                        duration: departure - t0,
                         r#type: ActionType::Idle,
+                        requires: vec![],
+                        std_dev: 0.0,
                     },
-                    t_start: 0.0,
-                    t_end: 0.0,
-                }],
-            )
+                    t_start: t0,
+                    t_end: departure,
+                    step: self.step.get(),
+                });
+            }
+            let mut r = self.execute_action(&action, r)?;
+            let parked = r[i].1.last_mut().unwrap();
+            if parked.t_end > window.start {
+                return Ok(None);
+            }
+            parked.action.duration = window.end - parked.t_end;
+            parked.t_end = window.end;
+            Ok(Some(r))
+        };
+        let (_, path_2d) = self.find_path_2d(&action, position, t0)?;
+        let latest = self
+            .solver
+            .config
+            .round_down(window.start - path_2d.iter().map(|s| s.duration).sum::<f64>());
+        if latest > t0 {
+            let recorded = self.recorded();
+            if let Some(result) = attempt(latest, r.clone())? {
+                return Ok(result);
+            }
+            self.rewind(recorded);
+        }
+        attempt(t0, r)?.ok_or_else(|| RoutingError::Unavailable {
+            agent: agent.name.clone(),
+            window,
         })
-        .collect();
-    let r = sched
-        .actions
-        .iter()
-        .fold(init, |acc, a| execute_action(a, acc));
+    }
 
-    Routing { routes: r }
-}
+    /// Execute an action regardless of the availability of the agent.
+    fn execute_available(
+        &self,
+        action: &Action,
+        r: Vec<(Agent, Vec<Path>)>,
+    ) -> Result<Vec<(Agent, Vec<Path>)>, RoutingError> {
+        if action.agent.on_roads() {
+            return self.execute_road(action, r, true);
+        }
+        let last_path = agent_paths(&action.agent, &r).last().unwrap();
+        let start = last_path.action.target;
+        let mut t = last_path.t_end;
 
-/// Execute an action, i.e., find a path for the agent to arrive at the action
-/// target and resolve any existing conflicts.
-fn execute_action(action: &Action, r: Vec<(Agent, Vec<Path>)>) -> Vec<(Agent, Vec<Path>)> {
-    let paths = agent_paths(&action.agent, &r);
-    let path_2d = find_path_2d(action, paths.iter().last().unwrap().action.target.clone());
+        let mut result = r;
+        let (path_2d, idle, blocking) = loop {
+            let (departure, path_2d) = self.find_path_2d(action, start, t)?;
+            while let Some(conflict) =
+                first_conflict(&action.agent, &path_2d, &result, &self.solver.config)
+            {
+                let ev_action = evasion_target(&conflict);
+                if self.trace.is_some() {
+                    let blocking = &conflict.cause.agent;
+                    self.record(
+                        t,
+                        action,
+                        Decision::Conflict {
+                            blocking: blocking.name.clone(),
+                            position: conflict.cause.target,
+                            distance: conflict.distance,
+                            resolution: conflict.resolution,
+                        },
+                    );
+                    self.record(
+                        agent_paths(blocking, &result).last().unwrap().t_end,
+                        action,
+                        Decision::Evasion {
+                            agent: blocking.name.clone(),
+                            target: ev_action.target,
+                        },
+                    );
+                }
+                result = self.execute_action(&ev_action, result)?;
+            }
+            let (idle, blocking) =
+                idle_path(action, &path_2d, &result, departure, &self.solver.config);
+            // Waiting for other agents may move the departure into the
+            // closure of an obstacle, so the path is planned again.
+            if idle.t_end == departure
+                || self
+                    .solver
+                    .site
+                    .obstacles
+                    .iter()
+                    .all(|o| o.window.is_none())
+                || self.find_path_2d(action, start, idle.t_end)? == (idle.t_end, path_2d.clone())
+            {
+                break (path_2d, idle, blocking);
+            }
+            t = idle.t_end;
+        };
+        if idle.t_end != idle.t_start {
+            self.record(
+                idle.t_start,
+                action,
+                Decision::Wait {
+                    blocking,
+                    until: idle.t_end,
+                },
+            );
+        }
+        let path = Path {
+            moves: path_2d.clone(),
+            action: action.clone(),
+            t_start: idle.t_end,
+            t_end: idle.t_end
+                + path_2d.iter().map(|s| s.duration).sum::<f64>()
+                + action.duration
+                + self.solver.config.buffer(action),
+            step: self.step.get(),
+        };
+        let mut v = agent_paths(&action.agent, &result).clone();
+        if idle.t_end != idle.t_start {
+            v.push(Path {
+                step: self.step.get(),
+                ..idle
+            });
+        }
+        v.push(path);
+        let i = result
+            .iter()
+            .position(|(a, _)| a.name == action.agent.name)
+            .unwrap();
+        result[i] = (action.agent.clone(), v);
+        Ok(result)
+    }
 
-    let mut result = r;
-    while let Some(conflict) = first_conflict(&action.agent, &path_2d, &result) {
-        let ev_action = evasion_target(&conflict);
-        result = execute_action(&ev_action, result);
+    /// Execute an action of an agent bound to the road network. Agents
+    /// parked on the route are sent to a free node first, if `evade` is
+    /// set, and the departure is delayed until the reservations of the
+    /// other agents leave room for the trip.
+    fn execute_road(
+        &self,
+        action: &Action,
+        r: Vec<(Agent, Vec<Path>)>,
+        evade: bool,
+    ) -> Result<Vec<(Agent, Vec<Path>)>, RoutingError> {
+        let (roads, clearance) = (&self.roads, self.solver.site.roads.clearance);
+        let agent = &action.agent;
+        let last_path = agent_paths(agent, &r).last().unwrap();
+        let (t0, start) = (last_path.t_end, last_path.action.target);
+        let no_path = || RoutingError::NoPath {
+            agent: agent.name.clone(),
+            start,
+            target: action.target,
+        };
+        let (Some(from), Some(to)) = (roads.nearest(start), roads.nearest(action.target)) else {
+            return Err(no_path());
+        };
+        let mut result = r;
+        let (route, reserved) = loop {
+            let reserved = Reservations::of_others(agent, &result, roads, clearance);
+            let parked = reserved.parked();
+            let blocked = parked.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+            let route = match roads.find_route(from, to, &agent.velocity, &blocked) {
+                Some(route) => route,
+                None if evade => roads
+                    .find_route(from, to, &agent.velocity, &[])
+                    .ok_or_else(no_path)?,
+                None => return Err(no_path()),
+            };
+            let nodes = route.iter().map(|&e| roads.ends(e).1).collect::<Vec<_>>();
+            let Some(&(node, name)) = parked.iter().find(|(n, _)| nodes.contains(n)) else {
+                break (route, reserved);
+            };
+            if !evade {
+                return Err(no_path());
+            }
+            // The other agent may drive through the route to a refuge off
+            // it, as the agent waits for it to clear.
+            let other = &result.iter().find(|(a, _)| a.name == name).unwrap().0;
+            let refuge = roads
+                .reachable(
+                    node,
+                    &other.velocity,
+                    &[blocked.as_slice(), &[from]].concat(),
+                )
+                .into_iter()
+                .find(|n| *n != node && !nodes.contains(n))
+                .ok_or_else(no_path)?;
+            let ev_action = Action {
+                agent: other.clone(),
+                target: roads.position(refuge),
+                duration: 0.0,
+                r#type: ActionType::Evasive,
+                requires: vec![],
+                std_dev: 0.0,
+            };
+            result = self.execute_road(&ev_action, result, false)?;
+        };
+        let durations = route
+            .iter()
+            .map(|&e| roads.travel_time(e, &agent.velocity))
+            .collect::<Vec<_>>();
+        let mut departure = t0;
+        while let Some(d) = reserved.delay(
+            &Trip {
+                route: &route,
+                durations: &durations,
+                departure,
+            },
+            roads,
+            clearance,
+        ) {
+            if !d.is_finite() {
+                return Err(no_path());
+            }
+            departure = self.solver.config.round_up(d);
+        }
+        let mut v = agent_paths(agent, &result).clone();
+        if departure > t0 {
+            v.push(Path {
+                moves: vec![],
+                action: Action {
+                    agent: agent.clone(),
+                    target: start,
+                    duration: departure - t0,
+                    r#type: ActionType::Idle,
+                    requires: vec![],
+                    std_dev: 0.0,
+                },
+                t_start: t0,
+                t_end: departure,
+                step: self.step.get(),
+            });
+        }
+        v.push(Path {
+            moves: roads.segments(&route, &agent.velocity),
+            action: Action {
+                target: roads.position(to),
+                ..action.clone()
+            },
+            t_start: departure,
+            t_end: departure
+                + durations.iter().sum::<f64>()
+                + action.duration
+                + self.solver.config.buffer(action),
+            step: self.step.get(),
+        });
+        let i = result
+            .iter()
+            .position(|(a, _)| a.name == agent.name)
+            .unwrap();
+        result[i] = (agent.clone(), v);
+        Ok(result)
     }
-    let idle = idle_path(action, &path_2d, &result);
-    let path = Path {
-        moves: path_2d.clone(),
-        action: action.clone(),
-        t_start: idle.t_end,
-        t_end: idle.t_end + path_2d[0].duration + action.duration,
-    };
-    let mut v = agent_paths(&action.agent, &result).clone();
-    if idle.t_end != idle.t_start {
-        v.push(idle);
+
+    /// Find a path from `start` to the action target avoiding the obstacles
+    /// of the site, departing no earlier than `t`. When closures block every
+    /// path, the departure is postponed until one of them ends. Returns the
+    /// departure time and the path.
+    fn find_path_2d(
+        &self,
+        action: &Action,
+        start: Coord,
+        t: f64,
+    ) -> Result<(f64, Vec<Segment>), RoutingError> {
+        if action.agent.on_roads() {
+            let (roads, vel) = (&self.roads, &action.agent.velocity);
+            return roads
+                .nearest(start)
+                .zip(roads.nearest(action.target))
+                .and_then(|(from, to)| roads.find_route(from, to, vel, &[]))
+                .map(|route| (t, roads.segments(&route, vel)))
+                .ok_or_else(|| RoutingError::NoPath {
+                    agent: action.agent.name.clone(),
+                    start,
+                    target: action.target,
+                });
+        }
+        let obstacles = &self.solver.site.obstacles;
+        let mut departure = t;
+        loop {
+            let mut blocking = (0..obstacles.len())
+                .filter(|&i| obstacles[i].present(departure, departure))
+                .collect::<Vec<_>>();
+            while let Some(path) = self.nav_path(&action.agent, start, action.target, &blocking) {
+                let arrival = departure + path.iter().map(|s| s.duration).sum::<f64>();
+                let closing = (0..obstacles.len())
+                    .filter(|i| !blocking.contains(i))
+                    .filter(|&i| obstacles[i].present(departure, arrival))
+                    .filter(|&i| {
+                        path.iter()
+                            .any(|s| graphs::crosses_interior(&obstacles[i].area, s.start, s.end))
+                    })
+                    .collect::<Vec<_>>();
+                if closing.is_empty() {
+                    return Ok((departure, path));
+                }
+                blocking.extend(closing);
+                blocking.sort();
+            }
+            match blocking
+                .iter()
+                .filter_map(|&i| obstacles[i].window)
+                .map(|w| w.end)
+                .filter(|&e| e > departure)
+                .reduce(f64::min)
+            {
+                Some(e) => departure = e,
+                None => {
+                    return Err(RoutingError::NoPath {
+                        agent: action.agent.name.clone(),
+                        start,
+                        target: action.target,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Find a path for the agent amid the given obstacles of the site, using
+    /// the cached navigation structure.
+    fn nav_path(
+        &self,
+        agent: &Agent,
+        start: Coord,
+        target: Coord,
+        obstacles: &[usize],
+    ) -> Option<Vec<Segment>> {
+        self.nav
+            .borrow_mut()
+            .entry((agent.name.clone(), obstacles.to_vec()))
+            .or_insert_with(|| {
+                let areas = obstacles
+                    .iter()
+                    .map(|&i| &self.solver.site.obstacles[i].area)
+                    .collect::<Vec<_>>();
+                Navigator::new(agent, graphs::free_region(&agent.region(), &areas))
+            })
+            .find_path(start, target)
     }
-    v.push(path);
-    let i = result
-        .iter()
-        .position(|(a, _)| a.name == action.agent.name)
-        .unwrap();
-    result[i] = (action.agent.clone(), v);
-    result
 }
 
-fn agent_paths<'a, 'b>(agent: &'a Agent, r: &'b Vec<(Agent, Vec<Path>)>) -> &'b Vec<Path> {
+fn agent_paths<'b>(agent: &Agent, r: &'b [(Agent, Vec<Path>)]) -> &'b Vec<Path> {
     let (_, agent_paths) = r.iter().find(|(a, _)| a.name == agent.name).unwrap();
     agent_paths
 }
 
-fn idle_path(action: &Action, path_2d: &Vec<Segment>, r: &Vec<(Agent, Vec<Path>)>) -> Path {
-    let last_path = agent_paths(&action.agent, r).last().unwrap();
+/// The breakpoints of the position along the x-axis of an agent at `x` at
+/// time `t` that goes on to move along the segments, between which it moves
+/// linearly. Each segment arrives at its end along the x-axis first.
+fn x_track(moves: &[Segment], vel: &ConstVel2D, t: f64, x: f64) -> Vec<(f64, f64)> {
+    let mut track = vec![(t, x)];
+    let mut clock = t;
+    for s in moves.iter() {
+        track.push((clock + s.x_arrival(vel), s.end.x));
+        clock += s.duration;
+        track.push((clock, s.end.x));
+    }
+    track
+}
+
+/// The departures of an agent for which a piece of its track, relative to
+/// the departure, comes closer than `sd` to a piece of the track of another
+/// agent, which is on the `side` of it along the x-axis. Returns the bounds
+/// of the departures that come closer by more than the tolerance, the time
+/// from which on the departure keeps the full distance, and the time the
+/// other agent is where it decides on the latter. The last piece of the
+/// agent may last forever, as it stays at its target.
+fn forbidden_departures(
+    mine: [(f64, f64); 2],
+    theirs: [(f64, f64); 2],
+    side: f64,
+    sd: f64,
+    tol: f64,
+) -> Option<(f64, f64, f64, f64)> {
+    let [(u0, x0), (u1, x1)] = mine;
+    let [(t0, y0), (t1, y1)] = theirs;
+    let (u1, vm) = if u1.is_finite() {
+        (u1, (x1 - x0) / (u1 - u0))
+    } else {
+        (u0, 0.0)
+    };
+    let vo = (y1 - y0) / (t1 - t0);
+    let gap = |(u, t): (f64, f64)| side * (x0 + vm * (u - u0) - y0 - vo * (t - t0)) - sd;
+    // The gap is linear in both times, so the pairs of times at which it
+    // is below a level make up a convex polygon.
+    let corners = [(u0, t0), (u1, t0), (u1, t1), (u0, t1)];
+    if corners.iter().all(|&c| gap(c) >= -tol) {
+        return None;
+    }
+    let below = |level: f64| {
+        let mut region = vec![];
+        for (p, q) in corners.into_iter().circular_tuple_windows() {
+            let (gp, gq) = (gap(p) - level, gap(q) - level);
+            if gp <= 0.0 {
+                region.push(p);
+            }
+            if gp * gq < 0.0 {
+                let k = gp / (gp - gq);
+                region.push((p.0 + k * (q.0 - p.0), p.1 + k * (q.1 - p.1)));
+            }
+        }
+        region
+    };
+    let latest = |region: &[(f64, f64)]| {
+        *region
+            .iter()
+            .min_by(|p, q| {
+                (q.1 - q.0)
+                    .total_cmp(&(p.1 - p.0))
+                    .then(p.1.total_cmp(&q.1))
+            })
+            .unwrap()
+    };
+    let close = below(-tol);
+    let earliest = if mine[1].0.is_finite() {
+        close.iter().map(|&(u, t)| t - u).reduce(f64::min).unwrap()
+    } else {
+        f64::NEG_INFINITY
+    };
+    let (u, t) = latest(&close);
+    let (u_sd, t_sd) = latest(&below(0.0));
+    Some((earliest, t - u, t_sd - u_sd, t_sd))
+}
+
+/// The wait of the agent before it departs on the path, such that it keeps
+/// its distance to the other agents, with the agent it waits for. Each
+/// piece of the path and of the stay at its end is checked against each
+/// piece of the paths of the other agents that overlaps it in time.
+fn idle_path(
+    action: &Action,
+    path_2d: &[Segment],
+    r: &[(Agent, Vec<Path>)],
+    departure: f64,
+    config: &SolverConfig,
+) -> (Path, Option<Blocking>) {
+    let agent = &action.agent;
+    let last_path = agent_paths(agent, r).last().unwrap();
     let t0 = last_path.t_end;
-    let duration = path_2d[0].duration;
-    let xi = path_2d[0].start.x;
-    let xf = path_2d[0].end.x;
-    let s = r
+    let mut mine = x_track(path_2d, &agent.velocity, 0.0, path_2d[0].start.x);
+    let x_end = mine.last().unwrap().1;
+    mine.push((f64::INFINITY, x_end));
+    let mut forbidden = vec![];
+    for (a, ps) in r
         .iter()
-        .filter(|(a, _)| a.name != action.agent.name)
-        .filter(|(_, ps)| ps.iter().any(|p| p.t_end >= t0))
-        .map(|(a, ps)| (a, ps.iter().skip_while(|p| p.t_end < t0)))
-        .map(|(a, ps)| {
-            let sd = a.safety_x(&action.agent);
-            (
-                a,
-                ps.flat_map(|p| p.to_points_st())
-                    .tuple_windows()
-                    .filter(|(p1, _)| {
-                        if a.order < action.agent.order {
-                            xf - p1.x < sd
-                        } else {
-                            p1.x - xf < sd
-                        }
-                    })
-                    .last(),
-            )
-        })
-        .filter(|(_, t)| t.is_some())
-        .map(|(a, t)| (a, t.unwrap()))
-        .map(|(a, (p1, _))| {
-            let sd = a.safety_x(&action.agent);
-            let t1 = p1.t - (f64::abs(p1.x - xi) - sd) / action.agent.velocity.x;
-            let t2 = p1.t + (sd - f64::abs(p1.x - xf)) / a.velocity.x - duration;
-            t1.max(t2)
-        })
-        .reduce(f64::max)
-        .unwrap_or(t0)
-        .ceil();
-    let ss = f64::max(s, t0);
+        .filter(|(a, _)| a.name != agent.name && !a.on_roads())
+    {
+        let ps = ps.iter().skip_while(|p| p.t_end < t0).collect::<Vec<_>>();
+        let theirs = ps
+            .iter()
+            .flat_map(|p| {
+                let x = p.moves.first().map_or(p.action.target.x, |s| s.start.x);
+                let mut track = x_track(&p.moves, &a.velocity, p.t_start, x);
+                track.push((p.t_end, p.action.target.x));
+                track
+            })
+            .collect::<Vec<_>>();
+        let side = if a.order < agent.order { 1.0 } else { -1.0 };
+        for m in mine.windows(2).filter(|w| w[1].0 > w[0].0) {
+            for o in theirs.windows(2).filter(|w| w[1].0 > w[0].0) {
+                let pieces = ([m[0], m[1]], [o[0], o[1]]);
+                // The agents keep the distance of the speeds they move at
+                // during the pieces.
+                let speed = |[(t0, x0), (t1, x1)]: [(f64, f64); 2]| {
+                    if t1.is_finite() {
+                        (x1 - x0) / (t1 - t0)
+                    } else {
+                        0.0
+                    }
+                };
+                let sd = config.safety_distance_at(a, agent, speed(pieces.1), speed(pieces.0));
+                if let Some((lo, hi, keeping, t)) =
+                    forbidden_departures(pieces.0, pieces.1, side, sd, config.tolerance)
+                {
+                    let p = ps
+                        .iter()
+                        .find(|p| t <= p.t_end)
+                        .unwrap_or(ps.last().unwrap());
+                    let blocking = Blocking {
+                        agent: a.name.clone(),
+                        position: p.position_at(t),
+                        t,
+                        distance: sd,
+                    };
+                    forbidden.push((lo, hi, keeping, blocking));
+                }
+            }
+        }
+    }
+    // The agent departs at the earliest time no other agent forbids, which
+    // is either its first chance or one from which on it keeps the full
+    // distance to some other agent.
+    let earliest = |t: f64| f64::max(config.round_up(t), departure);
+    let start = earliest(t0);
+    let ss = forbidden
+        .iter()
+        .map(|(_, _, keeping, _)| earliest(*keeping))
+        .filter(|&s| s > start)
+        .chain([start])
+        .sorted_by(f64::total_cmp)
+        .find(|&s| forbidden.iter().all(|(lo, hi, _, _)| s <= *lo || s >= *hi))
+        .unwrap();
+    // The wait is due to the agent that keeps it from departing earlier, if
+    // it is not due to the departure.
+    let blocking = forbidden
+        .into_iter()
+        .filter(|(_, hi, _, _)| ss > start && *hi <= ss)
+        .reduce(|f1, f2| if f2.2 > f1.2 { f2 } else { f1 })
+        .map(|(_, _, _, b)| b);
 
-    Path {
+    let path = Path {
         moves: Vec::new(),
         action: Action {
-            agent: action.agent.clone(),
+            agent: agent.clone(),
             target: last_path.action.target,
             duration: ss - t0,
             r#type: ActionType::Idle,
+            requires: vec![],
+            std_dev: 0.0,
         },
         t_start: t0,
         t_end: ss,
-    }
+        step: None,
+    };
+    (path, blocking)
 }
 
 fn evasion_target(conflict: &Conflict) -> Action {
@@ -190,39 +920,56 @@ fn evasion_target(conflict: &Conflict) -> Action {
         },
         duration: 0.0,
         r#type: ActionType::Evasive,
+        requires: vec![],
+        std_dev: 0.0,
     }
 }
 
 /// Return the first conflict to be resolved, if any.
 fn first_conflict<'a>(
     agent: &'a Agent,
-    path: &'a Vec<Segment>,
-    r: &'a Vec<(Agent, Vec<Path>)>,
+    path: &'a [Segment],
+    r: &'a [(Agent, Vec<Path>)],
+    config: &SolverConfig,
 ) -> Option<Conflict<'a>> {
-    let xs = path.iter().map(|s| s.end.x).collect::<Vec<_>>();
-    let min_x = xs.clone().into_iter().reduce(f64::min).unwrap();
-    let max_x = xs.into_iter().reduce(f64::max).unwrap();
+    // The ends of the segments, with the speed along the x-axis of the
+    // faster of the segments that meet there.
+    let speeds = path
+        .iter()
+        .map(|s| s.x_speed(&agent.velocity))
+        .chain([0.0])
+        .collect::<Vec<_>>();
+    let ends = path
+        .iter()
+        .zip(speeds.windows(2))
+        .map(|(s, v)| (s.end.x, f64::max(v[0], v[1])))
+        .collect::<Vec<_>>();
     let result = r
         .iter()
-        .filter(|(a, _)| a.name != agent.name)
+        .filter(|(a, _)| a.name != agent.name && !a.on_roads())
         .map(|(_, paths)| &paths.iter().last().unwrap().action)
-        .map(|a| {
-            let sd = a.agent.safety_x(&agent);
-            (
-                a,
-                if a.agent.order < agent.order && a.target.x > min_x - sd {
-                    Some(ConflictResolution::LowerThanX(min_x - sd))
-                } else if a.agent.order > agent.order && a.target.x < max_x + sd {
-                    Some(ConflictResolution::HigherThanX(max_x + sd))
-                } else {
-                    None
-                },
-            )
+        .filter_map(|a| {
+            // The other agent is at rest at the position of its latest
+            // action, while the agent passes by.
+            let sd = |v: f64| config.safety_distance_at(&a.agent, agent, 0.0, v);
+            let bounds = ends.iter().map(|&(x, v)| (x - sd(v), x + sd(v), sd(v)));
+            // Agents closer than the safety distance by no more than the
+            // tolerance are not in conflict.
+            let tol = config.tolerance;
+            if a.agent.order < agent.order {
+                let (l, _, d) = bounds.min_by(|b1, b2| b1.0.total_cmp(&b2.0)).unwrap();
+                (a.target.x > l + tol).then_some((a, ConflictResolution::LowerThanX(l), d))
+            } else if a.agent.order > agent.order {
+                let (_, h, d) = bounds.max_by(|b1, b2| b1.1.total_cmp(&b2.1)).unwrap();
+                (a.target.x < h - tol).then_some((a, ConflictResolution::HigherThanX(h), d))
+            } else {
+                None
+            }
         })
-        .filter(|(_, c)| c.is_some())
-        .map(|(a, c)| Conflict {
+        .map(|(a, resolution, distance)| Conflict {
             cause: a,
-            resolution: c.unwrap(),
+            resolution,
+            distance,
         })
         .min_by(|c1, c2| match c1.resolution {
             ConflictResolution::LowerThanX(l1) => match c2.resolution {
@@ -248,15 +995,3 @@ fn first_conflict<'a>(
         });
     result
 }
-
-fn find_path_2d(a: &Action, p: Coord) -> Vec<Segment> {
-    let v = a.agent.velocity;
-    let t_x = (a.target.x - p.x).abs() / v.x;
-    let t_y = (a.target.y - p.y).abs() / v.y;
-    let t = t_x.max(t_y);
-    vec![Segment {
-        start: p,
-        end: a.target.clone(),
-        duration: t,
-    }]
-}
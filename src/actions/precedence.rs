@@ -0,0 +1,228 @@
+use std::{collections::HashMap, fmt};
+
+use petgraph::{
+    algo::toposort,
+    graph::{DiGraph, NodeIndex},
+    Direction,
+};
+
+use crate::actions::{
+    commands::{SyncPoint, TimedCommand},
+    config::SolverConfig,
+    Routing,
+};
+
+/// The reason a plan cannot be built or executed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlanError {
+    /// A command waits for a command that does not exist
+    UnknownCommand(SyncPoint),
+    /// The commands wait for each other, starting with the given one
+    Cycle(SyncPoint),
+    /// A command is reported complete before it was released
+    NotReleased(SyncPoint),
+    /// A command is reported complete twice
+    AlreadyCompleted(SyncPoint),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::UnknownCommand(c) => {
+                write!(f, "agent '{}' has no command #{}", c.agent, c.seq)
+            }
+            PlanError::Cycle(c) => write!(
+                f,
+                "command #{} of agent '{}' waits for itself",
+                c.seq, c.agent
+            ),
+            PlanError::NotReleased(c) => write!(
+                f,
+                "command #{} of agent '{}' has not been released",
+                c.seq, c.agent
+            ),
+            PlanError::AlreadyCompleted(c) => write!(
+                f,
+                "command #{} of agent '{}' has already been completed",
+                c.seq, c.agent
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// A temporal plan graph: the commands of the agents, each of which may
+/// only start once the commands it depends on are completed. A command
+/// depends on the previous command of its agent and on the commands of
+/// other agents it has to wait for, so that the plan stays safe whatever
+/// the commands take.
+pub struct PlanGraph {
+    graph: DiGraph<TimedCommand, ()>,
+    /// The node of each command by agent and sequence number
+    index: HashMap<(String, usize), NodeIndex>,
+}
+
+fn id(c: &TimedCommand) -> SyncPoint {
+    SyncPoint {
+        agent: c.agent.clone(),
+        seq: c.seq,
+    }
+}
+
+impl PlanGraph {
+    /// The graph of the commands of all agents, e.g. as read back from a
+    /// command stream. The commands of an agent are in order.
+    pub fn new(commands: Vec<TimedCommand>) -> Result<PlanGraph, PlanError> {
+        let mut graph = DiGraph::new();
+        let nodes = commands
+            .into_iter()
+            .map(|c| graph.add_node(c))
+            .collect::<Vec<_>>();
+        let index = nodes
+            .iter()
+            .map(|&n| ((graph[n].agent.clone(), graph[n].seq), n))
+            .collect::<HashMap<_, _>>();
+        for (&n1, &n2) in nodes.iter().zip(nodes.iter().skip(1)) {
+            if graph[n1].agent == graph[n2].agent {
+                graph.update_edge(n1, n2, ());
+            }
+        }
+        for &n in nodes.iter() {
+            for s in graph[n].after.clone() {
+                let m = *index
+                    .get(&(s.agent.clone(), s.seq))
+                    .ok_or(PlanError::UnknownCommand(s))?;
+                graph.update_edge(m, n, ());
+            }
+        }
+        toposort(&graph, None).map_err(|c| PlanError::Cycle(id(&graph[c.node_id()])))?;
+        Ok(PlanGraph { graph, index })
+    }
+
+    /// The commands in the order of the streams.
+    pub fn commands(&self) -> impl Iterator<Item = &TimedCommand> {
+        self.graph.node_weights()
+    }
+
+    /// The commands in an order in which each comes after those it depends
+    /// on.
+    pub fn sorted(&self) -> Vec<&TimedCommand> {
+        toposort(&self.graph, None)
+            .unwrap()
+            .into_iter()
+            .map(|n| &self.graph[n])
+            .collect()
+    }
+
+    fn node(&self, agent: &str, seq: usize) -> Option<NodeIndex> {
+        self.index.get(&(agent.to_string(), seq)).copied()
+    }
+
+    /// The commands the command depends on, if it exists.
+    pub fn dependencies(&self, agent: &str, seq: usize) -> Option<Vec<&TimedCommand>> {
+        let n = self.node(agent, seq)?;
+        Some(
+            self.graph
+                .neighbors_directed(n, Direction::Incoming)
+                .map(|m| &self.graph[m])
+                .collect(),
+        )
+    }
+}
+
+impl Routing {
+    /// The temporal plan graph of the command streams of the agents. The
+    /// routes have to be in solver coordinates, as for the commands.
+    pub fn plan_graph(&self, config: &SolverConfig) -> Result<PlanGraph, PlanError> {
+        PlanGraph::new(self.commands(config))
+    }
+}
+
+/// The state of a command during execution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Pending,
+    Released,
+    Completed,
+}
+
+/// Executes a plan by events rather than by time: a command is released
+/// once every command it depends on is reported complete, however long the
+/// commands take. Waiting and parking commands keep their planned duration,
+/// and the controllers report them complete once it has passed.
+pub struct Executor {
+    plan: PlanGraph,
+    states: Vec<State>,
+}
+
+impl Executor {
+    /// Start to execute the plan, releasing the commands that depend on
+    /// none.
+    pub fn new(plan: PlanGraph) -> Executor {
+        let states = plan
+            .graph
+            .node_indices()
+            .map(
+                |n| match plan.graph.neighbors_directed(n, Direction::Incoming).next() {
+                    Some(_) => State::Pending,
+                    None => State::Released,
+                },
+            )
+            .collect();
+        Executor { plan, states }
+    }
+
+    /// The plan being executed.
+    pub fn plan(&self) -> &PlanGraph {
+        &self.plan
+    }
+
+    /// The commands released and not yet completed.
+    pub fn released(&self) -> Vec<&TimedCommand> {
+        self.plan
+            .graph
+            .node_indices()
+            .filter(|n| self.states[n.index()] == State::Released)
+            .map(|n| &self.plan.graph[n])
+            .collect()
+    }
+
+    /// Whether all commands are completed.
+    pub fn is_finished(&self) -> bool {
+        self.states.iter().all(|s| *s == State::Completed)
+    }
+
+    /// Report a command complete. Returns the commands released by it.
+    pub fn complete(&mut self, agent: &str, seq: usize) -> Result<Vec<&TimedCommand>, PlanError> {
+        let command = SyncPoint {
+            agent: agent.to_string(),
+            seq,
+        };
+        let Some(n) = self.plan.node(agent, seq) else {
+            return Err(PlanError::UnknownCommand(command));
+        };
+        match self.states[n.index()] {
+            State::Pending => return Err(PlanError::NotReleased(command)),
+            State::Completed => return Err(PlanError::AlreadyCompleted(command)),
+            State::Released => self.states[n.index()] = State::Completed,
+        }
+        let graph = &self.plan.graph;
+        let released = graph
+            .neighbors_directed(n, Direction::Outgoing)
+            .filter(|&m| {
+                self.states[m.index()] == State::Pending
+                    && graph
+                        .neighbors_directed(m, Direction::Incoming)
+                        .all(|d| self.states[d.index()] == State::Completed)
+            })
+            .collect::<Vec<_>>();
+        for m in released.iter() {
+            self.states[m.index()] = State::Released;
+        }
+        Ok(released.into_iter().map(|m| &graph[m]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,152 @@
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+
+use crate::actions::{
+    commands::{Command, SyncPoint, TimedCommand},
+    config::SolverConfig,
+    data::{Action, ActionType, Agent, ConstVel2D, Motion, Planner, Schedule},
+    precedence::{Executor, PlanError, PlanGraph},
+    routes,
+};
+
+fn agent(name: &str, x: f64, order: i64) -> Agent {
+    Agent {
+        name: String::from(name),
+        reach: Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        )
+        .into(),
+        corridors: MultiPolygon(vec![]),
+        position: Coord { x, y: 5.0 },
+        velocity: ConstVel2D { x: 1.0, y: 1.0 },
+        motion: Motion::Simultaneous,
+        safety_x: 10.0,
+        order,
+        capabilities: vec![],
+        unavailable: vec![],
+        planner: Planner::Visibility,
+    }
+}
+
+fn action(agent: &Agent, x: f64, duration: f64) -> Action {
+    Action {
+        agent: agent.clone(),
+        target: Coord { x, y: 5.0 },
+        duration,
+        r#type: ActionType::Scheduled,
+        requires: vec![],
+        std_dev: 0.0,
+    }
+}
+
+fn id(agent: &str, seq: usize) -> SyncPoint {
+    SyncPoint {
+        agent: String::from(agent),
+        seq,
+    }
+}
+
+fn ids<'a>(commands: impl IntoIterator<Item = &'a TimedCommand>) -> Vec<(String, usize)> {
+    let mut ids = commands
+        .into_iter()
+        .map(|c| (c.agent.clone(), c.seq))
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+fn pair(agent: &str, seq: usize) -> (String, usize) {
+    (String::from(agent), seq)
+}
+
+#[test]
+fn test_executor() {
+    let west = agent("west", 10.0, 0);
+    let east = agent("east", 30.0, 1);
+    let schedule = Schedule {
+        actions: vec![
+            action(&east, 30.0, 10.0),
+            action(&east, 80.0, 10.0),
+            action(&west, 60.0, 10.0),
+        ],
+    };
+    let routing = routes(&[west, east], schedule).unwrap();
+    let plan = routing.plan_graph(&SolverConfig::default()).unwrap();
+
+    // West follows east, each piece of its move once east has got a safety
+    // distance beyond where the piece ends.
+    assert_eq!(
+        ids(plan.dependencies("west", 1).unwrap()),
+        vec![pair("east", 1), pair("west", 0)]
+    );
+    assert_eq!(plan.dependencies("west", 9), None);
+
+    let mut executor = Executor::new(plan);
+    assert_eq!(
+        ids(executor.released()),
+        vec![pair("east", 0), pair("west", 0)]
+    );
+    assert_eq!(ids(executor.complete("west", 0).unwrap()), vec![]);
+    assert_eq!(
+        executor.complete("west", 1),
+        Err(PlanError::NotReleased(id("west", 1)))
+    );
+    assert_eq!(
+        ids(executor.complete("east", 0).unwrap()),
+        vec![pair("east", 1)]
+    );
+    assert_eq!(
+        executor.complete("east", 0),
+        Err(PlanError::AlreadyCompleted(id("east", 0)))
+    );
+    assert_eq!(
+        executor.complete("north", 0),
+        Err(PlanError::UnknownCommand(id("north", 0)))
+    );
+    // However long east takes, west is released with it.
+    assert_eq!(
+        ids(executor.complete("east", 1).unwrap()),
+        vec![pair("east", 2), pair("west", 1)]
+    );
+    for (agent, seq) in [
+        ("east", 2),
+        ("west", 1),
+        ("east", 3),
+        ("west", 2),
+        ("east", 4),
+        ("west", 3),
+        ("east", 5),
+        ("west", 4),
+        ("west", 5),
+        ("east", 6),
+    ] {
+        assert!(!executor.is_finished());
+        executor.complete(agent, seq).unwrap();
+    }
+    assert!(executor.is_finished());
+    assert!(executor.released().is_empty());
+}
+
+#[test]
+fn test_plan_graph_errors() {
+    let command = |agent: &str, after: SyncPoint| TimedCommand {
+        agent: String::from(agent),
+        seq: 0,
+        t_start: 0.0,
+        t_end: 10.0,
+        command: Command::Wait { until: 10.0 },
+        after: vec![after],
+    };
+    assert_eq!(
+        PlanGraph::new(vec![command("west", id("east", 1))]).err(),
+        Some(PlanError::UnknownCommand(id("east", 1)))
+    );
+    assert!(matches!(
+        PlanGraph::new(vec![
+            command("west", id("east", 0)),
+            command("east", id("west", 0))
+        ])
+        .err(),
+        Some(PlanError::Cycle(_))
+    ));
+}
@@ -0,0 +1,160 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+
+use crate::actions::{
+    data::{Agent, Path},
+    graphs::road::RoadGraph,
+};
+
+/// The time an agent holds a node or a road of the road network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reservation<T> {
+    /// The name of the agent holding the item
+    pub agent: String,
+    /// The node or road held
+    pub item: T,
+    /// The time the agent takes hold of the item
+    pub start: f64,
+    /// The time the agent releases the item, infinite if the agent stays
+    pub end: f64,
+}
+
+impl<T> Reservation<T> {
+    fn new(agent: &str, item: T, start: f64, end: f64) -> Reservation<T> {
+        Reservation {
+            agent: agent.to_string(),
+            item,
+            start,
+            end,
+        }
+    }
+
+    fn overlaps(&self, start: f64, end: f64) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// The nodes and roads of the network held by the agents bound to it.
+#[derive(Clone, Debug, Default)]
+pub struct Reservations {
+    /// The nodes held, while agents pass or stay
+    pub nodes: Vec<Reservation<NodeIndex>>,
+    /// The roads held, while agents drive them
+    pub roads: Vec<Reservation<EdgeIndex>>,
+}
+
+/// A trip along roads of the network, departing at a given time.
+pub struct Trip<'a> {
+    /// The roads driven, in order
+    pub route: &'a [EdgeIndex],
+    /// The time to drive each road
+    pub durations: &'a [f64],
+    /// The departure time
+    pub departure: f64,
+}
+
+impl Reservations {
+    /// The reservations of the agents on the network other than the given
+    /// one, following their paths. A node stays reserved for the clearance
+    /// after an agent has left it.
+    pub fn of_others(
+        agent: &Agent,
+        r: &[(Agent, Vec<Path>)],
+        roads: &RoadGraph,
+        clearance: f64,
+    ) -> Reservations {
+        let mut result = Reservations::default();
+        for (a, paths) in r
+            .iter()
+            .filter(|(a, _)| a.on_roads() && a.name != agent.name)
+        {
+            let Some(mut node) = roads.nearest(a.position) else {
+                continue;
+            };
+            let mut arrival = f64::NEG_INFINITY;
+            for p in paths.iter().filter(|p| !p.moves.is_empty()) {
+                result.nodes.push(Reservation::new(
+                    &a.name,
+                    node,
+                    arrival,
+                    p.t_start + clearance,
+                ));
+                let mut clock = p.t_start;
+                for (i, s) in p.moves.iter().enumerate() {
+                    let to = roads.nearest(s.end).unwrap();
+                    if let Some(e) = roads.road_between(node, to, &a.velocity) {
+                        result
+                            .roads
+                            .push(Reservation::new(&a.name, e, clock, clock + s.duration));
+                    }
+                    clock += s.duration;
+                    if i + 1 < p.moves.len() {
+                        result
+                            .nodes
+                            .push(Reservation::new(&a.name, to, clock, clock + clearance));
+                    }
+                    node = to;
+                }
+                arrival = clock;
+            }
+            result
+                .nodes
+                .push(Reservation::new(&a.name, node, arrival, f64::INFINITY));
+        }
+        result
+    }
+
+    /// The nodes where agents stay until further notice, with the agents.
+    pub fn parked(&self) -> Vec<(NodeIndex, &str)> {
+        self.nodes
+            .iter()
+            .filter(|n| n.end == f64::INFINITY)
+            .map(|n| (n.item, n.agent.as_str()))
+            .collect()
+    }
+
+    /// The earliest departure that may resolve the first conflict of the
+    /// trip with the reservations, if any. The trip holds the nodes it
+    /// passes for the clearance and stays at its last node. Roads are held
+    /// up to their capacity, but never by agents driving the opposite way.
+    pub fn delay(&self, trip: &Trip, roads: &RoadGraph, clearance: f64) -> Option<f64> {
+        let mut clock = trip.departure;
+        for (i, (&e, &d)) in trip.route.iter().zip(trip.durations).enumerate() {
+            let (from, to) = roads.ends(e);
+            let (t0, t1) = (clock, clock + d);
+            let ahead = self
+                .roads
+                .iter()
+                .filter(|h| h.overlaps(t0, t1))
+                .filter(|h| h.item == e || roads.ends(h.item) == (to, from))
+                .collect::<Vec<_>>();
+            let oncoming = ahead.iter().filter(|h| h.item != e).map(|h| h.end);
+            let full = (ahead.iter().filter(|h| h.item == e).count() >= roads.road(e).capacity)
+                .then(|| {
+                    ahead
+                        .iter()
+                        .filter(|h| h.item == e)
+                        .map(|h| h.end)
+                        .fold(f64::INFINITY, f64::min)
+                });
+            if let Some(end) = oncoming.chain(full).reduce(f64::max) {
+                return Some(trip.departure + end - t0);
+            }
+            let stay = if i + 1 < trip.route.len() {
+                t1 + clearance
+            } else {
+                f64::INFINITY
+            };
+            if let Some(end) = self
+                .nodes
+                .iter()
+                .filter(|h| h.item == to && h.overlaps(t1, stay))
+                .map(|h| h.end)
+                .reduce(f64::max)
+            {
+                return Some(trip.departure + end - t1);
+            }
+            clock = t1;
+        }
+        None
+    }
+}
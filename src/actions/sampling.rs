@@ -0,0 +1,125 @@
+use geo::Coord;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    data::{ConstVel2D, Path, PointST, Segment},
+    graphs::timer,
+    Routing,
+};
+
+/// The positions of an agent over time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trajectory {
+    /// The name of the agent
+    pub agent: String,
+    /// The points of the agent in space and time, in order
+    pub points: Vec<PointST>,
+}
+
+impl Segment {
+    /// The position some time after the start of the segment. Both axes
+    /// start at the velocity of the agent and each stops as it arrives, so
+    /// the slowest axis arrives at the end of the segment. Segments slower
+    /// than the agent, e.g. on roads with a speed limit, slow both axes
+    /// down alike.
+    pub fn position_at(&self, vel: &ConstVel2D, elapsed: f64) -> Coord {
+        if elapsed >= self.duration {
+            return self.end;
+        }
+        let scale = timer(self.start, self.end, vel) / self.duration;
+        let axis = |from: f64, to: f64, v: f64| {
+            from + (to - from).signum() * f64::min(v * scale * elapsed.max(0.0), (to - from).abs())
+        };
+        Coord {
+            x: axis(self.start.x, self.end.x, vel.x),
+            y: axis(self.start.y, self.end.y, vel.y),
+        }
+    }
+
+    /// The time after the start of the segment at which the agent arrives
+    /// at the end along the x-axis, from when on it only moves along the
+    /// y-axis.
+    pub(crate) fn x_arrival(&self, vel: &ConstVel2D) -> f64 {
+        let timer = timer(self.start, self.end, vel);
+        if timer > 0.0 {
+            (self.end.x - self.start.x).abs() / vel.x * self.duration / timer
+        } else {
+            0.0
+        }
+    }
+
+    /// The speed along the x-axis until the agent arrives at the end along
+    /// it.
+    pub(crate) fn x_speed(&self, vel: &ConstVel2D) -> f64 {
+        let arrival = self.x_arrival(vel);
+        if arrival > 0.0 {
+            (self.end.x - self.start.x).abs() / arrival
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Path {
+    /// The position of the agent at time `t`, which is clamped to the
+    /// duration of the path. The agent stays at the target while it executes
+    /// the action.
+    pub fn position_at(&self, t: f64) -> Coord {
+        let vel = &self.action.agent.velocity;
+        let mut clock = self.t_start;
+        for s in self.moves.iter() {
+            if t < clock + s.duration {
+                return s.position_at(vel, t - clock);
+            }
+            clock += s.duration;
+        }
+        self.action.target
+    }
+}
+
+impl Routing {
+    /// The position of the agent at time `t`, if the agent exists. Before
+    /// its first path the agent is at its position, after its last one at
+    /// the last target.
+    pub fn position_at(&self, agent: &str, t: f64) -> Option<Coord> {
+        let (_, paths) = self.routes.iter().find(|(a, _)| a.name == agent)?;
+        let path = paths.iter().find(|p| t < p.t_end).or(paths.last())?;
+        Some(path.position_at(t))
+    }
+
+    /// The trajectories of all agents, sampled every `dt` from time 0 up to
+    /// the end of the last path of any agent, which is sampled as well.
+    ///
+    /// # Panics
+    ///
+    /// If `dt` is not positive.
+    pub fn sample(&self, dt: f64) -> Vec<Trajectory> {
+        assert!(dt > 0.0, "the sampling interval must be positive");
+        let makespan = self
+            .routes
+            .iter()
+            .flat_map(|(_, ps)| ps.iter().map(|p| p.t_end))
+            .fold(0.0, f64::max);
+        let n = (makespan / dt).ceil() as usize;
+        let mut times = (0..=n)
+            .map(|i| f64::min(i as f64 * dt, makespan))
+            .collect::<Vec<_>>();
+        times.dedup();
+        self.routes
+            .iter()
+            .map(|(a, _)| Trajectory {
+                agent: a.name.clone(),
+                points: times
+                    .iter()
+                    .map(|&t| {
+                        let c = self.position_at(&a.name, t).unwrap();
+                        PointST { x: c.x, y: c.y, t }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,127 @@
+use geo::Coord;
+
+use crate::actions::{
+    data::{Action, ActionType, ConstVel2D, Schedule, Segment},
+    fixtures::{action, agent},
+    routes,
+};
+
+#[test]
+fn test_segment_position() {
+    let vel = ConstVel2D { x: 2.0, y: 1.0 };
+    let segment = Segment {
+        start: Coord { x: 10.0, y: 5.0 },
+        end: Coord { x: 50.0, y: 9.0 },
+        duration: 20.0,
+    };
+    // The y-axis arrives after 4, the x-axis moves on until 20.
+    assert_eq!(segment.position_at(&vel, 2.0), Coord { x: 14.0, y: 7.0 });
+    assert_eq!(segment.position_at(&vel, 10.0), Coord { x: 30.0, y: 9.0 });
+    assert_eq!(segment.position_at(&vel, 25.0), segment.end);
+    assert_eq!(segment.position_at(&vel, -1.0), segment.start);
+
+    // A road twice as slow as the agent slows both axes down.
+    let road = Segment {
+        duration: 40.0,
+        ..segment
+    };
+    assert_eq!(road.position_at(&vel, 4.0), Coord { x: 14.0, y: 7.0 });
+    assert_eq!(road.position_at(&vel, 20.0), Coord { x: 30.0, y: 9.0 });
+}
+
+#[test]
+fn test_sample() {
+    let crane = agent("crane", 10.0, 0);
+    let schedule = Schedule {
+        actions: vec![Action {
+            agent: crane.clone(),
+            target: Coord { x: 50.0, y: 9.0 },
+            duration: 5.0,
+            r#type: ActionType::Scheduled,
+            requires: vec![],
+            std_dev: 0.0,
+        }],
+    };
+    let routing = routes(&[crane], schedule).unwrap();
+
+    assert_eq!(
+        routing.position_at("crane", -1.0),
+        Some(Coord { x: 10.0, y: 5.0 })
+    );
+    assert_eq!(
+        routing.position_at("crane", 10.0),
+        Some(Coord { x: 30.0, y: 9.0 })
+    );
+    assert_eq!(
+        routing.position_at("crane", 100.0),
+        Some(Coord { x: 50.0, y: 9.0 })
+    );
+    assert_eq!(routing.position_at("gantry", 0.0), None);
+
+    let trajectories = routing.sample(10.0);
+    assert_eq!(trajectories[0].agent, "crane");
+    let points = &trajectories[0].points;
+    assert_eq!(
+        points.iter().map(|p| (p.x, p.y, p.t)).collect::<Vec<_>>(),
+        vec![
+            (10.0, 5.0, 0.0),
+            (30.0, 9.0, 10.0),
+            (50.0, 9.0, 20.0),
+            (50.0, 9.0, 25.0)
+        ]
+    );
+}
+
+#[test]
+fn test_sample_path_boundaries() {
+    let crane = agent("crane", 10.0, 0);
+    let gantry = agent("gantry", 80.0, 1);
+    let schedule = Schedule {
+        actions: vec![action(&crane, 30.0, 5.0), action(&gantry, 90.0, 1.0)],
+    };
+    let routing = routes(&[crane, gantry], schedule).unwrap();
+    let xs = |dt: f64| {
+        routing
+            .sample(dt)
+            .iter()
+            .map(|tr| tr.points.iter().map(|p| (p.x, p.t)).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+
+    // The crane arrives at 10 and acts until 15, the gantry is done at 6
+    // and stays where it acted until the crane is done.
+    assert_eq!(
+        xs(5.0),
+        vec![
+            vec![(10.0, 0.0), (20.0, 5.0), (30.0, 10.0), (30.0, 15.0)],
+            vec![(80.0, 0.0), (90.0, 5.0), (90.0, 10.0), (90.0, 15.0)],
+        ]
+    );
+}
+
+#[test]
+fn test_sample_uneven_interval() {
+    let crane = agent("crane", 10.0, 0);
+    let schedule = Schedule {
+        actions: vec![action(&crane, 30.0, 5.0)],
+    };
+    let routing = routes(&[crane], schedule).unwrap();
+    let times = |dt: f64| {
+        routing.sample(dt)[0]
+            .points
+            .iter()
+            .map(|p| p.t)
+            .collect::<Vec<_>>()
+    };
+
+    // The last sample is at the makespan, however far the interval
+    // overshoots it, and only once.
+    assert_eq!(times(4.0), vec![0.0, 4.0, 8.0, 12.0, 15.0]);
+    assert_eq!(times(20.0), vec![0.0, 15.0]);
+    assert_eq!(times(7.5), vec![0.0, 7.5, 15.0]);
+    let points = &routing.sample(4.0)[0].points;
+    assert_eq!(
+        points.iter().map(|p| p.x).collect::<Vec<_>>(),
+        vec![10.0, 18.0, 26.0, 30.0, 30.0]
+    );
+}
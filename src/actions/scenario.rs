@@ -0,0 +1,282 @@
+use geo::Coord;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    assignment::Assigner,
+    commands::TimedCommand,
+    config::SolverConfig,
+    data::{Action, ActionType, Agent, CoordSerde, Schedule},
+    metrics::Metrics,
+    site::Site,
+    units::{Transform, Units},
+    validation::{validate, validate_config, validate_site, IssueKind, Severity, ValidationIssue},
+    Routing, RoutingError, Solver,
+};
+
+/// A scenario is the serializable input of the solver: the agents and the
+/// schedule of actions, which refer to their agent by name or leave it to
+/// be assigned.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    /// The site the agents operate on
+    #[serde(default)]
+    pub site: Site,
+    /// The agents executing the actions
+    pub agents: Vec<Agent>,
+    /// The scheduled actions, in the order they have to be executed
+    pub actions: Vec<ScenarioAction>,
+    /// The units the numbers of the scenario are expressed in
+    #[serde(default)]
+    pub units: Units,
+    /// The numeric settings of the solver, in the units of the scenario
+    #[serde(default)]
+    pub config: SolverConfig,
+    /// The transform from the coordinates of the scenario to those of the
+    /// solver, if they differ
+    #[serde(default)]
+    pub transform: Option<Transform>,
+}
+
+/// A scheduled action of a scenario.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScenarioAction {
+    /// The name of the agent executing the action, if not to be assigned
+    /// automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// The target location of the action
+    #[serde(with = "CoordSerde")]
+    pub target: Coord,
+    /// The duration of the action
+    pub duration: f64,
+    /// The capabilities an agent needs to execute the action
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// The standard deviation of the duration
+    #[serde(default)]
+    pub std_dev: f64,
+}
+
+/// The outcome of solving a scenario, in its coordinates and units.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Solution {
+    /// The routing, unless none was found
+    pub routing: Option<Routing>,
+    /// The metrics of the routing
+    pub metrics: Option<Metrics>,
+    /// The validation issues of the scenario
+    pub issues: Vec<ValidationIssue>,
+    /// The reason no routing was found, unless the issues tell
+    pub error: Option<String>,
+}
+
+impl Solution {
+    /// The solution without a routing, given the issues and the error of the
+    /// solver.
+    pub fn failed(issues: Vec<ValidationIssue>, error: Option<RoutingError>) -> Solution {
+        Solution {
+            routing: None,
+            metrics: None,
+            issues,
+            error: error.map(|e| e.to_string()),
+        }
+    }
+}
+
+impl Scenario {
+    /// The scenario in the coordinates and units of the solver: transformed
+    /// onto its axes, in metres and seconds.
+    pub fn in_solver_coordinates(&self) -> Scenario {
+        let (site, agents, targets) = match self.transform {
+            Some(t) => (
+                t.site(&self.site),
+                self.agents.iter().map(|a| t.agent(a)).collect(),
+                self.actions.iter().map(|a| t.apply(a.target)).collect(),
+            ),
+            None => (
+                self.site.clone(),
+                self.agents.clone(),
+                self.actions.iter().map(|a| a.target).collect::<Vec<_>>(),
+            ),
+        };
+        let scale = self.units.to_si();
+        Scenario {
+            site: scale.site(&site),
+            agents: agents.iter().map(|a| scale.agent(a)).collect(),
+            actions: self
+                .actions
+                .iter()
+                .zip(targets)
+                .map(|(a, target)| ScenarioAction {
+                    target: target * scale.length,
+                    duration: a.duration * scale.time,
+                    std_dev: a.std_dev * scale.time,
+                    ..a.clone()
+                })
+                .collect(),
+            units: Units::default(),
+            config: scale.config(&self.config),
+            transform: None,
+        }
+    }
+
+    /// The routing of the scenario in solver coordinates converted back to
+    /// the coordinates and units of the scenario.
+    pub fn in_scenario_coordinates(&self, routing: &Routing) -> Routing {
+        let routing = self.units.to_si().inverse().routing(routing);
+        match self.transform.and_then(|t| t.inverse()) {
+            Some(inverse) => inverse.routing(&routing),
+            None => routing,
+        }
+    }
+
+    /// The commands of a routing in solver coordinates converted back to the
+    /// coordinates and units of the scenario.
+    pub fn commands_in_scenario_coordinates(&self, commands: &[TimedCommand]) -> Vec<TimedCommand> {
+        let scale = self.units.to_si().inverse();
+        let inverse = self.transform.and_then(|t| t.inverse());
+        commands
+            .iter()
+            .map(|c| scale.command(c))
+            .map(|c| match inverse {
+                Some(t) => t.command(&c),
+                None => c,
+            })
+            .collect()
+    }
+
+    /// Build the schedule of the scenario. An action without an agent is
+    /// assigned to the capable agent that is estimated to complete it first,
+    /// after the actions queued for it before. Fails with an issue per action
+    /// that refers to an unknown agent or that no agent can execute.
+    pub fn schedule(&self) -> Result<Schedule, Vec<ValidationIssue>> {
+        self.schedule_after(None)
+    }
+
+    /// Build the schedule of the scenario to be executed after the routing,
+    /// if any. Actions without an agent are assigned as if the agents are
+    /// busy with the paths of the routing first.
+    pub fn schedule_after(
+        &self,
+        routing: Option<&Routing>,
+    ) -> Result<Schedule, Vec<ValidationIssue>> {
+        let mut issues = Vec::new();
+        let mut actions = Vec::new();
+        let mut assigner = Assigner::new(&self.agents, &self.site, &self.config);
+        if let Some(r) = routing {
+            assigner = assigner.after(r);
+        }
+        for (i, a) in self.actions.iter().enumerate() {
+            let agent = match &a.agent {
+                Some(name) => self.agents.iter().position(|ag| &ag.name == name),
+                None => assigner.assign(a),
+            };
+            match agent {
+                Some(j) => {
+                    assigner.queue(j, a);
+                    actions.push(Action {
+                        agent: self.agents[j].clone(),
+                        target: a.target,
+                        duration: a.duration,
+                        r#type: ActionType::Scheduled,
+                        requires: a.requires.clone(),
+                        std_dev: a.std_dev,
+                    })
+                }
+                None => issues.push(match &a.agent {
+                    Some(name) => ValidationIssue {
+                        severity: Severity::Error,
+                        kind: IssueKind::UnknownAgent,
+                        agent: Some(name.clone()),
+                        action: Some(i),
+                        message: format!("action #{} refers to unknown agent '{}'", i, name),
+                    },
+                    None => ValidationIssue {
+                        severity: Severity::Error,
+                        kind: IssueKind::NoCapableAgent,
+                        agent: None,
+                        action: Some(i),
+                        message: format!(
+                            "no agent with capabilities [{}] can reach the target of action #{}",
+                            a.requires.join(", "),
+                            i
+                        ),
+                    },
+                }),
+            }
+        }
+        if issues.is_empty() {
+            Ok(Schedule { actions })
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Validate the scenario and build its schedule, both in the coordinates
+    /// of the solver. Returns the issues found, with the scenario and its
+    /// schedule unless any of them is an error.
+    pub fn prepare(&self) -> (Option<(Scenario, Schedule)>, Vec<ValidationIssue>) {
+        self.prepare_after(None)
+    }
+
+    /// Validate the scenario and build its schedule to be executed after the
+    /// routing in solver coordinates, if any, as in `prepare`.
+    pub fn prepare_after(
+        &self,
+        routing: Option<&Routing>,
+    ) -> (Option<(Scenario, Schedule)>, Vec<ValidationIssue>) {
+        let mut issues = validate_config(&self.config, self.transform.as_ref());
+        if issues.iter().any(|i| i.is_error()) {
+            return (None, issues);
+        }
+        let scenario = self.in_solver_coordinates();
+        let schedule = match scenario.schedule_after(routing) {
+            Ok(s) => s,
+            Err(unknown) => {
+                issues.extend(unknown);
+                return (None, issues);
+            }
+        };
+        issues.extend(validate(&scenario.agents, &schedule, &scenario.config));
+        issues.extend(validate_site(
+            &scenario.site,
+            &scenario.agents,
+            &scenario.config,
+        ));
+        if issues.iter().any(|i| i.is_error()) {
+            (None, issues)
+        } else {
+            (Some((scenario, schedule)), issues)
+        }
+    }
+
+    /// Validate and solve the scenario. Returns the solution, and the routing
+    /// in solver coordinates if one is found.
+    pub fn solve(&self) -> (Solution, Option<Routing>) {
+        let (prepared, issues) = self.prepare();
+        let Some((s, schedule)) = prepared else {
+            return (Solution::failed(issues, None), None);
+        };
+        let solver = Solver {
+            site: s.site,
+            config: s.config,
+            trace: false,
+        };
+        match solver.routes(&s.agents, schedule) {
+            Ok(routing) => (self.solution(&routing, issues), Some(routing)),
+            Err(e) => (Solution::failed(issues, Some(e)), None),
+        }
+    }
+
+    /// The solution of a routing in solver coordinates, converted back to
+    /// the coordinates and units of the scenario.
+    pub fn solution(&self, routing: &Routing, issues: Vec<ValidationIssue>) -> Solution {
+        let routing = self.in_scenario_coordinates(routing);
+        Solution {
+            metrics: Some(routing.metrics()),
+            routing: Some(routing),
+            issues,
+            error: None,
+        }
+    }
+}
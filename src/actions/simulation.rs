@@ -0,0 +1,577 @@
+use std::{collections::HashMap, fmt};
+
+use geo::Coord;
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, LogNormal, Normal, Triangular, Uniform};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    commands::{Command, TimedCommand},
+    config::SolverConfig,
+    data::{Agent, Segment},
+    precedence::PlanError,
+    Routing,
+};
+
+/// The smallest factor on the velocity of an agent, so that every move
+/// comes to an end.
+pub const MIN_VELOCITY_FACTOR: f64 = 0.01;
+
+/// The distribution of a factor on planned durations or velocities.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Spread {
+    /// Always one, as planned
+    #[default]
+    Exact,
+    /// Uniform between `min` and `max`
+    Uniform { min: f64, max: f64 },
+    /// Normal with the given mean and standard deviation
+    Normal { mean: f64, std_dev: f64 },
+    /// Log-normal, i.e. `exp` of a normal with the given `mu` and `sigma`
+    LogNormal { mu: f64, sigma: f64 },
+    /// Triangular between `min` and `max`, with its peak at `mode`
+    Triangular { min: f64, mode: f64, max: f64 },
+}
+
+/// A spread ready to draw factors from.
+enum Sampler {
+    Exact,
+    Uniform(Uniform<f64>),
+    Normal(Normal<f64>),
+    LogNormal(LogNormal<f64>),
+    Triangular(Triangular<f64>),
+}
+
+impl Spread {
+    fn sampler(&self) -> Result<Sampler, SimulationError> {
+        let invalid = |e: &dyn fmt::Display| SimulationError::Spread(*self, e.to_string());
+        Ok(match *self {
+            Spread::Exact => Sampler::Exact,
+            Spread::Uniform { min, max } => {
+                Sampler::Uniform(Uniform::new_inclusive(min, max).map_err(|e| invalid(&e))?)
+            }
+            Spread::Normal { mean, std_dev } => {
+                Sampler::Normal(Normal::new(mean, std_dev).map_err(|e| invalid(&e))?)
+            }
+            Spread::LogNormal { mu, sigma } => {
+                Sampler::LogNormal(LogNormal::new(mu, sigma).map_err(|e| invalid(&e))?)
+            }
+            Spread::Triangular { min, mode, max } => {
+                Sampler::Triangular(Triangular::new(min, max, mode).map_err(|e| invalid(&e))?)
+            }
+        })
+    }
+}
+
+impl Sampler {
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        match self {
+            Sampler::Exact => 1.0,
+            Sampler::Uniform(d) => d.sample(rng),
+            Sampler::Normal(d) => d.sample(rng),
+            Sampler::LogNormal(d) => d.sample(rng),
+            Sampler::Triangular(d) => d.sample(rng),
+        }
+    }
+}
+
+/// How the agents start their commands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Execution {
+    /// Each command starts at its planned time, or as soon as the agent is
+    /// done with the previous one if that is later. Agents do not wait for
+    /// each other.
+    #[default]
+    Timed,
+    /// Each command starts as soon as the commands it depends on in the
+    /// plan graph are completed. Waits keep their planned duration.
+    Events,
+}
+
+/// The settings of a simulation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// The factor on the planned durations of the actions, drawn for every
+    /// action
+    #[serde(default)]
+    pub durations: Spread,
+    /// The factor on the velocities of the agents, drawn for every move
+    #[serde(default)]
+    pub velocities: Spread,
+    /// How the agents start their commands
+    #[serde(default)]
+    pub execution: Execution,
+    /// The number of runs
+    #[serde(default = "SimulationConfig::default_runs")]
+    pub runs: usize,
+    /// The seed of the random numbers, so that a simulation can be repeated
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl SimulationConfig {
+    fn default_runs() -> usize {
+        100
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            durations: Spread::default(),
+            velocities: Spread::default(),
+            execution: Execution::default(),
+            runs: SimulationConfig::default_runs(),
+            seed: 0,
+        }
+    }
+}
+
+/// The reason a routing cannot be simulated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimulationError {
+    /// The plan graph of the routing cannot be built
+    Plan(PlanError),
+    /// The parameters of a spread are invalid
+    Spread(Spread, String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Plan(e) => write!(f, "{}", e),
+            SimulationError::Spread(s, e) => write!(f, "invalid spread {:?}: {}", s, e),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// How late an agent finishes in a run, and why.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Delay {
+    /// The name of the agent
+    pub agent: String,
+    /// How much later than planned the agent completes its last command
+    pub lateness: f64,
+    /// How much longer than planned the moves and actions of the agent take
+    pub own: f64,
+    /// How long the agent waits for other agents on top of its plan, i.e.
+    /// the delay propagated to it
+    pub blocked: f64,
+}
+
+/// Two agents closer to each other than their safety distance.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// The names of the agents
+    pub agents: [String; 2],
+    /// The first time the agents are too close
+    pub t: f64,
+    /// The smallest distance between the agents along the x-axis
+    pub distance: f64,
+    /// The safety distance of the agents along the x-axis, without the
+    /// margins the solver adds on top
+    pub required: f64,
+}
+
+/// The outcome of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    /// The time the last command is completed
+    pub makespan: f64,
+    /// The delays of the agents
+    pub delays: Vec<Delay>,
+    /// The pairs of agents that come too close
+    pub violations: Vec<Violation>,
+}
+
+/// A summary of the values of many runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// The mean
+    pub mean: f64,
+    /// The standard deviation
+    pub std_dev: f64,
+    /// The smallest value
+    pub min: f64,
+    /// The median
+    pub p50: f64,
+    /// The 90th percentile
+    pub p90: f64,
+    /// The 95th percentile
+    pub p95: f64,
+    /// The 99th percentile
+    pub p99: f64,
+    /// The largest value
+    pub max: f64,
+}
+
+/// The value below which the given share of the sorted values lie.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let i = (q * sorted.len() as f64).ceil() as usize;
+    sorted[i.clamp(1, sorted.len()) - 1]
+}
+
+impl Summary {
+    fn new(values: &[f64]) -> Summary {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let var = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Summary {
+            mean,
+            std_dev: var.sqrt(),
+            min: sorted[0],
+            p50: quantile(&sorted, 0.5),
+            p90: quantile(&sorted, 0.9),
+            p95: quantile(&sorted, 0.95),
+            p99: quantile(&sorted, 0.99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// The outcome of a simulation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    /// The makespan of the plan
+    pub planned_makespan: f64,
+    /// The makespans of the runs
+    pub makespan: Summary,
+    /// The share of the runs in which agents come too close
+    pub violation_rate: f64,
+    /// The runs
+    pub runs: Vec<Run>,
+}
+
+impl SimulationReport {
+    /// The buffer to add to the planned makespan so that the given share of
+    /// the runs, e.g. `0.95`, completes in time.
+    pub fn buffer(&self, share: f64) -> f64 {
+        let mut makespans = self.runs.iter().map(|r| r.makespan).collect::<Vec<_>>();
+        if makespans.is_empty() {
+            return 0.0;
+        }
+        makespans.sort_by(f64::total_cmp);
+        f64::max(quantile(&makespans, share) - self.planned_makespan, 0.0)
+    }
+}
+
+/// A command as executed in a run.
+#[derive(Clone, Copy)]
+struct Executed {
+    t_start: f64,
+    t_end: f64,
+    segment: Segment,
+}
+
+/// The commands of a plan in the order they are executed, with their
+/// dependencies by index.
+struct Prepared<'a> {
+    commands: Vec<&'a TimedCommand>,
+    previous: Vec<Option<usize>>,
+    dependencies: Vec<Vec<usize>>,
+    /// The agent of each command, by its index in the routing
+    agents: Vec<usize>,
+    /// The commands of each agent of the routing, in order
+    timelines: Vec<Vec<usize>>,
+}
+
+impl<'a> Prepared<'a> {
+    fn new(routing: &Routing, sorted: Vec<&'a TimedCommand>) -> Prepared<'a> {
+        let index = sorted
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ((c.agent.as_str(), c.seq), i))
+            .collect::<HashMap<_, _>>();
+        let names = routing
+            .routes
+            .iter()
+            .enumerate()
+            .map(|(j, (a, _))| (a.name.as_str(), j))
+            .collect::<HashMap<_, _>>();
+        let agents = sorted
+            .iter()
+            .map(|c| names[c.agent.as_str()])
+            .collect::<Vec<_>>();
+        // The commands of an agent depend on each other, so they are sorted
+        // in order.
+        let mut timelines = vec![vec![]; routing.routes.len()];
+        for (i, &j) in agents.iter().enumerate() {
+            timelines[j].push(i);
+        }
+        Prepared {
+            agents,
+            timelines,
+            previous: sorted
+                .iter()
+                .map(|c| {
+                    let seq = c.seq.checked_sub(1)?;
+                    index.get(&(c.agent.as_str(), seq)).copied()
+                })
+                .collect(),
+            dependencies: sorted
+                .iter()
+                .map(|c| {
+                    c.after
+                        .iter()
+                        .map(|s| index[&(s.agent.as_str(), s.seq)])
+                        .collect()
+                })
+                .collect(),
+            commands: sorted,
+        }
+    }
+}
+
+impl Routing {
+    /// Execute the command streams of the routing many times, with the
+    /// durations of the actions and the velocities of the agents drawn from
+    /// the spreads of the simulation. The routes have to be in solver
+    /// coordinates, as for the commands.
+    pub fn simulate(
+        &self,
+        config: &SolverConfig,
+        simulation: &SimulationConfig,
+    ) -> Result<SimulationReport, SimulationError> {
+        let durations = simulation.durations.sampler()?;
+        let velocities = simulation.velocities.sampler()?;
+        let plan = self.plan_graph(config).map_err(SimulationError::Plan)?;
+        let prepared = Prepared::new(self, plan.sorted());
+        let mut rng = StdRng::seed_from_u64(simulation.seed);
+        let runs = (0..simulation.runs)
+            .map(|_| {
+                let executed = self.execute(&prepared, simulation.execution, &mut |c| match c {
+                    Command::MoveTo { .. } | Command::EvadeTo { .. } => {
+                        1.0 / f64::max(velocities.sample(&mut rng), MIN_VELOCITY_FACTOR)
+                    }
+                    Command::Execute { .. } => f64::max(durations.sample(&mut rng), 0.0),
+                    _ => 1.0,
+                });
+                self.run(&prepared, &executed, config, simulation)
+            })
+            .collect::<Vec<_>>();
+        let planned_makespan = prepared
+            .commands
+            .iter()
+            .map(|c| c.t_end)
+            .fold(0.0, f64::max);
+        let mut makespans = runs.iter().map(|r| r.makespan).collect::<Vec<_>>();
+        if makespans.is_empty() {
+            makespans.push(planned_makespan);
+        }
+        Ok(SimulationReport {
+            planned_makespan,
+            makespan: Summary::new(&makespans),
+            violation_rate: runs.iter().filter(|r| !r.violations.is_empty()).count() as f64
+                / simulation.runs.max(1) as f64,
+            runs,
+        })
+    }
+
+    /// The times and moves of the commands in a run, with the factor on the
+    /// planned duration of each command drawn by `factor`.
+    fn execute(
+        &self,
+        prepared: &Prepared,
+        execution: Execution,
+        factor: &mut dyn FnMut(&Command) -> f64,
+    ) -> Vec<Executed> {
+        let mut executed: Vec<Option<Executed>> = vec![None; prepared.commands.len()];
+        for (i, c) in prepared.commands.iter().enumerate() {
+            let previous = prepared.previous[i].map(|p| executed[p].unwrap());
+            let ready = previous.map_or(0.0, |p| p.t_end);
+            let start = previous.map_or_else(
+                || self.routes[prepared.agents[i]].0.position,
+                |p| p.segment.end,
+            );
+            let t_start = match execution {
+                Execution::Timed => f64::max(c.t_start, ready),
+                Execution::Events => prepared.dependencies[i]
+                    .iter()
+                    .map(|&d| executed[d].unwrap().t_end)
+                    .fold(ready, f64::max),
+            };
+            let planned = c.t_end - c.t_start;
+            let (duration, end) = match c.command {
+                Command::MoveTo { x, y } | Command::EvadeTo { x, y } => {
+                    (planned * factor(&c.command), Coord { x, y })
+                }
+                Command::Execute { duration, .. } => (duration * factor(&c.command), start),
+                Command::Wait { until } if execution == Execution::Timed => {
+                    (f64::max(until - t_start, 0.0), start)
+                }
+                Command::Wait { .. } => (planned, start),
+                Command::Park { until } => (f64::max(until - t_start, 0.0), start),
+            };
+            executed[i] = Some(Executed {
+                t_start,
+                t_end: t_start + duration,
+                segment: Segment {
+                    start,
+                    end,
+                    duration: planned,
+                },
+            });
+        }
+        executed.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// The delays and violations of a run.
+    fn run(
+        &self,
+        prepared: &Prepared,
+        executed: &[Executed],
+        config: &SolverConfig,
+        simulation: &SimulationConfig,
+    ) -> Run {
+        let makespan = executed.iter().map(|e| e.t_end).fold(0.0, f64::max);
+        let delays = self
+            .routes
+            .iter()
+            .zip(prepared.timelines.iter())
+            .map(|((a, _), timeline)| {
+                let mut delay = Delay {
+                    agent: a.name.clone(),
+                    lateness: 0.0,
+                    own: 0.0,
+                    blocked: 0.0,
+                };
+                for &i in timeline.iter() {
+                    let c = prepared.commands[i];
+                    let e = &executed[i];
+                    match c.command {
+                        Command::MoveTo { .. } | Command::EvadeTo { .. } => {
+                            delay.own += (e.t_end - e.t_start) - (c.t_end - c.t_start)
+                        }
+                        Command::Execute { duration, .. } => {
+                            delay.own += (e.t_end - e.t_start) - duration
+                        }
+                        _ => {}
+                    }
+                    let ready = prepared.previous[i].map_or(0.0, |p| executed[p].t_end);
+                    if simulation.execution == Execution::Events {
+                        delay.blocked += e.t_start - ready;
+                    }
+                }
+                if let Some(&i) = timeline.last() {
+                    delay.lateness = executed[i].t_end - prepared.commands[i].t_end;
+                }
+                delay
+            })
+            .collect();
+        Run {
+            makespan,
+            delays,
+            violations: self.violations(prepared, executed, config),
+        }
+    }
+
+    /// The pairs of agents on rails that come closer than their safety
+    /// distance. The agents move linearly along the x-axis between the
+    /// breakpoints of their tracks, so their closest approach is exact.
+    fn violations(
+        &self,
+        prepared: &Prepared,
+        executed: &[Executed],
+        config: &SolverConfig,
+    ) -> Vec<Violation> {
+        let (agents, tracks): (Vec<_>, Vec<_>) = self
+            .routes
+            .iter()
+            .zip(prepared.timelines.iter())
+            .filter(|((a, _), _)| !a.on_roads())
+            .map(|((a, _), timeline)| (a, track(a, timeline.iter().map(|&i| &executed[i]))))
+            .unzip();
+        let mut violations = vec![];
+        for i in 0..agents.len() {
+            for j in i + 1..agents.len() {
+                let required = agents[i].safety_x(agents[j]);
+                let threshold = required - config.tolerance;
+                let mut times = tracks[i]
+                    .iter()
+                    .chain(tracks[j].iter())
+                    .map(|&(t, _)| t)
+                    .collect::<Vec<_>>();
+                times.sort_by(f64::total_cmp);
+                times.dedup();
+                // The gap between the agents at every breakpoint, the first
+                // one twice so that it is checked on its own as well.
+                let gaps = times[..1]
+                    .iter()
+                    .chain(times.iter())
+                    .map(|&t| (t, x_at(&tracks[i], t) - x_at(&tracks[j], t)))
+                    .collect::<Vec<_>>();
+                let mut violation: Option<Violation> = None;
+                for w in gaps.windows(2) {
+                    let ((t0, g0), (t1, g1)) = (w[0], w[1]);
+                    let distance = if g0 * g1 <= 0.0 {
+                        0.0
+                    } else {
+                        f64::min(g0.abs(), g1.abs())
+                    };
+                    if distance >= threshold {
+                        continue;
+                    }
+                    match violation.as_mut() {
+                        Some(v) => v.distance = f64::min(v.distance, distance),
+                        None => {
+                            // The time the gap shrinks below the safety
+                            // distance.
+                            let t = if g0.abs() <= required {
+                                t0
+                            } else if g0 > 0.0 {
+                                t0 + (g0 - required) / (g0 - g1) * (t1 - t0)
+                            } else {
+                                t0 + (-required - g0) / (g1 - g0) * (t1 - t0)
+                            };
+                            violation = Some(Violation {
+                                agents: [agents[i].name.clone(), agents[j].name.clone()],
+                                t,
+                                distance,
+                                required,
+                            })
+                        }
+                    }
+                }
+                violations.extend(violation);
+            }
+        }
+        violations
+    }
+}
+
+/// The breakpoints of the position of the agent along the x-axis during a
+/// run, between which it moves linearly. The commands of an agent run one
+/// after the other.
+fn track<'a>(a: &Agent, timeline: impl Iterator<Item = &'a Executed>) -> Vec<(f64, f64)> {
+    let mut points = vec![(0.0, a.position.x)];
+    for e in timeline {
+        let s = &e.segment;
+        points.push((e.t_start, s.start.x));
+        if s.duration > 0.0 {
+            let arrival = s.x_arrival(&a.velocity) * (e.t_end - e.t_start) / s.duration;
+            points.push((e.t_start + arrival, s.end.x));
+        }
+        points.push((e.t_end, s.end.x));
+    }
+    points
+}
+
+/// The position along the x-axis at time `t` on the track.
+fn x_at(track: &[(f64, f64)], t: f64) -> f64 {
+    let k = track.partition_point(|&(tk, _)| tk <= t);
+    match (k.checked_sub(1), track.get(k)) {
+        (Some(p), Some(&(t1, x1))) => {
+            let (t0, x0) = track[p];
+            x0 + (x1 - x0) * (t - t0) / (t1 - t0)
+        }
+        (Some(p), None) => track[p].1,
+        (None, _) => track[0].1,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,257 @@
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{Action, ActionType, Agent, ConstVel2D, Motion, Path, Planner, Schedule, Segment},
+    simulation::{Execution, SimulationConfig, SimulationError, Spread},
+    Routing, Solver,
+};
+
+fn agent(name: &str, x: f64, order: i64) -> Agent {
+    Agent {
+        name: String::from(name),
+        reach: Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        )
+        .into(),
+        corridors: MultiPolygon(vec![]),
+        position: Coord { x, y: 5.0 },
+        velocity: ConstVel2D { x: 1.0, y: 1.0 },
+        motion: Motion::Simultaneous,
+        safety_x: 10.0,
+        order,
+        capabilities: vec![],
+        unavailable: vec![],
+        planner: Planner::Visibility,
+    }
+}
+
+fn action(agent: &Agent, x: f64, duration: f64) -> Action {
+    Action {
+        agent: agent.clone(),
+        target: Coord { x, y: 5.0 },
+        duration,
+        r#type: ActionType::Scheduled,
+        requires: vec![],
+        std_dev: 0.0,
+    }
+}
+
+/// West follows east once east has finished its first action.
+fn following() -> Routing {
+    following_with(&Solver::default())
+}
+
+fn following_with(solver: &Solver) -> Routing {
+    let west = agent("west", 10.0, 0);
+    let east = agent("east", 30.0, 1);
+    let schedule = Schedule {
+        actions: vec![
+            action(&east, 30.0, 10.0),
+            action(&east, 80.0, 10.0),
+            action(&west, 60.0, 10.0),
+        ],
+    };
+    solver.routes(&[west, east], schedule).unwrap()
+}
+
+#[test]
+fn test_simulate_exact() {
+    let routing = following();
+    let config = SolverConfig::default();
+    for execution in [Execution::Timed, Execution::Events] {
+        let simulation = SimulationConfig {
+            execution,
+            runs: 3,
+            ..Default::default()
+        };
+        let report = routing.simulate(&config, &simulation).unwrap();
+        assert_eq!(report.planned_makespan, 70.0);
+        assert_eq!(report.makespan.max, 70.0);
+        assert_eq!(report.violation_rate, 0.0);
+        assert_eq!(report.buffer(0.95), 0.0);
+        let delays = report.runs[0]
+            .delays
+            .iter()
+            .map(|d| (d.lateness, d.own, d.blocked))
+            .collect::<Vec<_>>();
+        match execution {
+            Execution::Timed => assert_eq!(delays, vec![(0.0, 0.0, 0.0); 2]),
+            // By events, west keeps a safety distance more behind east than
+            // planned, which it can afford as east finishes last.
+            Execution::Events => assert_eq!(delays, vec![(10.0, 0.0, 10.0), (0.0, 0.0, 0.0)]),
+        }
+    }
+}
+
+#[test]
+fn test_simulate_delay() {
+    let routing = following();
+    let config = SolverConfig::default();
+    // Every action takes twice as long as planned.
+    let simulation = SimulationConfig {
+        durations: Spread::Uniform { min: 2.0, max: 2.0 },
+        runs: 1,
+        ..Default::default()
+    };
+
+    // On time, west sets off while east is still at work and runs into it.
+    let report = routing.simulate(&config, &simulation).unwrap();
+    assert_eq!(report.violation_rate, 1.0);
+    let violation = &report.runs[0].violations[0];
+    assert_eq!(
+        violation.agents,
+        [String::from("west"), String::from("east")]
+    );
+    assert_eq!(violation.t, 10.0);
+    assert_eq!(violation.required, 10.0);
+
+    // By events, west waits for east and inherits its delay.
+    let simulation = SimulationConfig {
+        execution: Execution::Events,
+        ..simulation
+    };
+    let report = routing.simulate(&config, &simulation).unwrap();
+    assert_eq!(report.violation_rate, 0.0);
+    assert_eq!(report.makespan.max, 90.0);
+    assert_eq!(report.buffer(0.95), 20.0);
+    let west = &report.runs[0].delays[0];
+    assert_eq!((west.lateness, west.own, west.blocked), (30.0, 10.0, 20.0));
+}
+
+#[test]
+fn test_simulate_spread() {
+    let routing = following();
+    let config = SolverConfig::default();
+    let simulation = SimulationConfig {
+        durations: Spread::Triangular {
+            min: 0.8,
+            mode: 1.0,
+            max: 2.0,
+        },
+        velocities: Spread::Normal {
+            mean: 1.0,
+            std_dev: 0.1,
+        },
+        execution: Execution::Events,
+        runs: 200,
+        seed: 7,
+    };
+    let report = routing.simulate(&config, &simulation).unwrap();
+    assert_eq!(report.runs.len(), 200);
+    // By events, no agent comes too close, even if it follows another one
+    // at a different velocity, while on time they do.
+    assert_eq!(report.violation_rate, 0.0);
+    let timed = SimulationConfig {
+        execution: Execution::Timed,
+        ..simulation.clone()
+    };
+    assert!(routing.simulate(&config, &timed).unwrap().violation_rate > 0.0);
+    let m = &report.makespan;
+    assert!(m.min <= m.p50 && m.p50 <= m.p90 && m.p90 <= m.p95 && m.p95 <= m.max);
+    assert!(report.buffer(0.5) <= report.buffer(0.95));
+    assert_eq!(report.buffer(0.95), (m.p95 - 70.0).max(0.0));
+    // The same seed repeats the simulation.
+    assert_eq!(routing.simulate(&config, &simulation).unwrap(), report);
+
+    let invalid = SimulationConfig {
+        velocities: Spread::Uniform { min: 2.0, max: 1.0 },
+        ..simulation.clone()
+    };
+    assert!(matches!(
+        routing.simulate(&config, &invalid),
+        Err(SimulationError::Spread(..))
+    ));
+}
+
+#[test]
+fn test_simulate_buffers() {
+    // Every action takes 40% longer than planned.
+    let simulation = SimulationConfig {
+        durations: Spread::Uniform { min: 1.4, max: 1.4 },
+        runs: 1,
+        ..Default::default()
+    };
+    let report = following().simulate(&SolverConfig::default(), &simulation);
+    assert_eq!(report.unwrap().violation_rate, 1.0);
+
+    // With a buffer of half the duration, west sets off only once east is
+    // done, and both finish ahead of the plan.
+    let solver = Solver {
+        config: SolverConfig {
+            slack: 0.5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let routing = following_with(&solver);
+    let east = &routing.routes[1].1;
+    assert_eq!((east[1].t_start, east[1].t_end), (0.0, 15.0));
+    let report = routing.simulate(&solver.config, &simulation).unwrap();
+    assert_eq!(report.planned_makespan, 80.0);
+    assert_eq!(report.violation_rate, 0.0);
+    let west = &report.runs[0].delays[0];
+    assert_eq!((west.lateness, west.own), (-1.0, 4.0));
+
+    // Each agent may travel for two seconds before it reacts, which widens
+    // the distance by what the agents that move cover meanwhile.
+    let solver = Solver {
+        config: SolverConfig {
+            reaction_time: 2.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (west, east) = (agent("west", 10.0, 0), agent("east", 30.0, 1));
+    assert_eq!(solver.config.safety_distance(&west, &east), 10.0);
+    assert_eq!(
+        solver.config.safety_distance_at(&west, &east, 1.0, 0.0),
+        12.0
+    );
+    assert_eq!(
+        solver.config.safety_distance_at(&west, &east, 1.0, -1.0),
+        14.0
+    );
+    let routing = following_with(&solver);
+    let west = &routing.routes[0].1;
+    // West holds back until it can follow east at the distance of both
+    // moving.
+    assert_eq!(west[2].t_start, 4.0);
+}
+
+#[test]
+fn test_simulate_crossing() {
+    // West rushes past east, which stays put, in half a second.
+    let mut west = agent("west", 10.0, 0);
+    west.velocity = ConstVel2D { x: 100.0, y: 100.0 };
+    let east = agent("east", 30.0, 1);
+    let rush = Path {
+        moves: vec![Segment {
+            start: Coord { x: 10.0, y: 5.0 },
+            end: Coord { x: 60.0, y: 5.0 },
+            duration: 0.5,
+        }],
+        action: action(&west, 60.0, 0.0),
+        t_start: 0.0,
+        t_end: 0.5,
+        step: Some(0),
+    };
+    let routing = Routing {
+        routes: vec![(west, vec![rush]), (east, vec![])],
+        trace: None,
+    };
+    let simulation = SimulationConfig {
+        runs: 1,
+        ..Default::default()
+    };
+
+    // Both ends of the move are well clear of east, but the agents meet in
+    // between.
+    let report = routing
+        .simulate(&SolverConfig::default(), &simulation)
+        .unwrap();
+    let violation = &report.runs[0].violations[0];
+    assert_eq!(violation.t, 0.1);
+    assert_eq!(violation.distance, 0.0);
+}
@@ -0,0 +1,113 @@
+use geo::{Coord, Polygon};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::data::{CoordSerde, PolygonSerde};
+
+/// A time interval, closed at the start and open at the end.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// The start time of the window
+    pub start: f64,
+    /// The end time of the window
+    pub end: f64,
+}
+
+impl TimeWindow {
+    /// Whether the window overlaps the closed interval `[t_start, t_end]`.
+    pub fn overlaps(&self, t_start: f64, t_end: f64) -> bool {
+        self.start <= t_end && t_start < self.end
+    }
+}
+
+/// An area no agent may travel through.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Obstacle {
+    /// The name of the obstacle
+    pub name: String,
+    /// The area covered by the obstacle, represented as a polygon
+    #[serde(with = "PolygonSerde")]
+    pub area: Polygon,
+    /// The time the obstacle is present. Obstacles without window are
+    /// always present.
+    #[serde(default)]
+    pub window: Option<TimeWindow>,
+}
+
+impl Obstacle {
+    /// Whether the obstacle is present at some time in `[t_start, t_end]`.
+    pub fn present(&self, t_start: f64, t_end: f64) -> bool {
+        self.window.is_none_or(|w| w.overlaps(t_start, t_end))
+    }
+}
+
+/// A site holds what is shared by all agents operating on it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Site {
+    /// The static and temporary obstacles of the site
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    /// The road network agents with a road planner drive on
+    #[serde(default)]
+    pub roads: RoadNetwork,
+}
+
+/// A network of directed roads between named nodes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoadNetwork {
+    /// The nodes, e.g. crossings and stations
+    #[serde(default)]
+    pub nodes: Vec<RoadNode>,
+    /// The roads between the nodes. A road used both ways needs an edge in
+    /// each direction.
+    #[serde(default)]
+    pub edges: Vec<RoadEdge>,
+    /// The time a node stays reserved after an agent has left it
+    #[serde(default = "RoadNetwork::default_clearance")]
+    pub clearance: f64,
+}
+
+impl RoadNetwork {
+    fn default_clearance() -> f64 {
+        1.0
+    }
+}
+
+impl Default for RoadNetwork {
+    fn default() -> Self {
+        RoadNetwork {
+            nodes: vec![],
+            edges: vec![],
+            clearance: RoadNetwork::default_clearance(),
+        }
+    }
+}
+
+/// A node of a road network.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoadNode {
+    /// The name of the node
+    pub name: String,
+    /// The position of the node
+    #[serde(with = "CoordSerde")]
+    pub position: Coord,
+}
+
+/// A directed road from one node to another.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoadEdge {
+    /// The name of the node the road starts at
+    pub from: String,
+    /// The name of the node the road ends at
+    pub to: String,
+    /// The speed limit on the road
+    pub speed: f64,
+    /// The number of agents that may drive on the road at the same time
+    #[serde(default = "RoadEdge::default_capacity")]
+    pub capacity: usize,
+}
+
+impl RoadEdge {
+    fn default_capacity() -> usize {
+        1
+    }
+}
@@ -0,0 +1,163 @@
+use geo::Coord;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::data::{Action, ActionType, CoordSerde};
+
+/// How a conflict is resolved: the agent in the way has to move to an
+/// x-position lower or higher than the value, along the x-axis of the
+/// solver.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// The conflict is resolved by an x-pos lower than the value
+    LowerThanX(f64),
+    /// The conflict is resolved by an x-pos higher than the value
+    HigherThanX(f64),
+}
+
+/// The agent another agent waits for, at the point of its path that
+/// decides the wait.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Blocking {
+    /// The name of the agent waited for
+    pub agent: String,
+    /// The position of the agent waited for
+    #[serde(with = "CoordSerde")]
+    pub position: Coord,
+    /// The time the agent waited for is at the position
+    pub t: f64,
+    /// The distance the agents keep along the x-axis
+    pub distance: f64,
+}
+
+/// A decision of the solver.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Decision {
+    /// Another agent is in the way of the path to the target, at the target
+    /// of its last action.
+    Conflict {
+        /// The name of the agent in the way
+        blocking: String,
+        /// The position of the agent in the way
+        #[serde(with = "CoordSerde")]
+        position: Coord,
+        /// The distance the agents keep along the x-axis
+        distance: f64,
+        /// Where the agent in the way has to go
+        resolution: ConflictResolution,
+    },
+    /// The agent in the way evades to a point.
+    Evasion {
+        /// The name of the agent that evades
+        agent: String,
+        /// The point the agent evades to
+        #[serde(with = "CoordSerde")]
+        target: Coord,
+    },
+    /// The agent waits before it departs, for another agent or for a
+    /// temporary obstacle if none is given.
+    Wait {
+        /// The agent waited for
+        blocking: Option<Blocking>,
+        /// The time the wait ends
+        until: f64,
+    },
+}
+
+/// An event of the trace: a decision taken while executing an action.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    /// The index of the scheduled action the solver is executing, none when
+    /// the solver parks agents after the last action
+    pub step: Option<usize>,
+    /// The time of the event, i.e. the time the agent executing the action
+    /// is ready to depart
+    pub time: f64,
+    /// The name of the agent executing the action
+    pub agent: String,
+    /// The target of the action
+    #[serde(with = "CoordSerde")]
+    pub target: Coord,
+    /// The type of the action, which is not scheduled if the solver executes
+    /// it to resolve an earlier decision
+    pub r#type: ActionType,
+    /// The decision taken
+    pub decision: Decision,
+}
+
+impl TraceEvent {
+    pub(crate) fn new(step: Option<usize>, time: f64, action: &Action, decision: Decision) -> Self {
+        TraceEvent {
+            step,
+            time,
+            agent: action.agent.name.clone(),
+            target: action.target,
+            r#type: action.r#type.clone(),
+            decision,
+        }
+    }
+}
+
+/// The decisions of the solver in the order they were taken. Distances are
+/// measured along the x-axis of the solver, like the safety distances.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    /// The events of the trace
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    /// The trace with its positions mapped, and its distances and times
+    /// multiplied by the factors, to convert it along with a routing.
+    pub(crate) fn convert(
+        &self,
+        position: impl Fn(Coord) -> Coord,
+        length: f64,
+        time: f64,
+    ) -> Trace {
+        let resolution = |r: ConflictResolution| match r {
+            ConflictResolution::LowerThanX(x) => ConflictResolution::LowerThanX(x * length),
+            ConflictResolution::HigherThanX(x) => ConflictResolution::HigherThanX(x * length),
+        };
+        let events = self
+            .events
+            .iter()
+            .map(|e| TraceEvent {
+                time: e.time * time,
+                target: position(e.target),
+                decision: match &e.decision {
+                    Decision::Conflict {
+                        blocking,
+                        position: p,
+                        distance,
+                        resolution: r,
+                    } => Decision::Conflict {
+                        blocking: blocking.clone(),
+                        position: position(*p),
+                        distance: distance * length,
+                        resolution: resolution(*r),
+                    },
+                    Decision::Evasion { agent, target } => Decision::Evasion {
+                        agent: agent.clone(),
+                        target: position(*target),
+                    },
+                    Decision::Wait { blocking, until } => Decision::Wait {
+                        blocking: blocking.as_ref().map(|b| Blocking {
+                            agent: b.agent.clone(),
+                            position: position(b.position),
+                            t: b.t * time,
+                            distance: b.distance * length,
+                        }),
+                        until: until * time,
+                    },
+                },
+                ..e.clone()
+            })
+            .collect();
+        Trace { events }
+    }
+
+    /// The trace as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
@@ -0,0 +1,333 @@
+use geo::{AffineOps, AffineTransform, Coord};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{Action, Agent, ConstVel2D, Parking, Path, Planner, Segment},
+    site::{Site, TimeWindow},
+    Routing,
+};
+
+/// A unit of length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LengthUnit {
+    /// A thousandth of a metre
+    Millimetre,
+    /// A hundredth of a metre
+    Centimetre,
+    /// The metre
+    #[default]
+    Metre,
+    /// A thousand metres
+    Kilometre,
+}
+
+impl LengthUnit {
+    /// The length of the unit in metres.
+    pub fn metres(self) -> f64 {
+        match self {
+            LengthUnit::Millimetre => 0.001,
+            LengthUnit::Centimetre => 0.01,
+            LengthUnit::Metre => 1.0,
+            LengthUnit::Kilometre => 1000.0,
+        }
+    }
+}
+
+/// A unit of time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeUnit {
+    /// A thousandth of a second
+    Millisecond,
+    /// The second
+    #[default]
+    Second,
+    /// Sixty seconds
+    Minute,
+    /// Sixty minutes
+    Hour,
+}
+
+impl TimeUnit {
+    /// The duration of the unit in seconds.
+    pub fn seconds(self) -> f64 {
+        match self {
+            TimeUnit::Millisecond => 0.001,
+            TimeUnit::Second => 1.0,
+            TimeUnit::Minute => 60.0,
+            TimeUnit::Hour => 3600.0,
+        }
+    }
+}
+
+/// The units all numbers of a scenario are expressed in, e.g. metres and
+/// seconds for positions and durations, and metres per second for
+/// velocities. The solver works in metres and seconds, so a scenario is
+/// converted before solving and its routing converted back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Units {
+    /// The unit of positions, distances and reaches
+    #[serde(default)]
+    pub length: LengthUnit,
+    /// The unit of times and durations
+    #[serde(default)]
+    pub time: TimeUnit,
+}
+
+impl Units {
+    /// The scale from these units to metres and seconds.
+    pub fn to_si(self) -> Scale {
+        Scale {
+            length: self.length.metres(),
+            time: self.time.seconds(),
+        }
+    }
+}
+
+/// Factors by which lengths and times are multiplied to convert them to
+/// other units. Velocities are converted by their ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+    /// The factor of lengths
+    pub length: f64,
+    /// The factor of times
+    pub time: f64,
+}
+
+impl Scale {
+    /// The scale converting back.
+    pub fn inverse(&self) -> Scale {
+        Scale {
+            length: 1.0 / self.length,
+            time: 1.0 / self.time,
+        }
+    }
+
+    /// The scaling of coordinates, as a transform.
+    fn lengths(&self) -> Transform {
+        Transform {
+            a: self.length,
+            b: 0.0,
+            xoff: 0.0,
+            d: 0.0,
+            e: self.length,
+            yoff: 0.0,
+        }
+    }
+
+    fn window(&self, w: TimeWindow) -> TimeWindow {
+        TimeWindow {
+            start: w.start * self.time,
+            end: w.end * self.time,
+        }
+    }
+
+    /// The agent with its geometry, velocity, safety distance, grid cell and
+    /// unavailability converted.
+    pub fn agent(&self, a: &Agent) -> Agent {
+        let mut a = self.lengths().agent(a);
+        a.velocity = ConstVel2D {
+            x: a.velocity.x * self.length / self.time,
+            y: a.velocity.y * self.length / self.time,
+        };
+        a.safety_x *= self.length;
+        if let Planner::Grid { cell } = a.planner {
+            a.planner = Planner::Grid {
+                cell: cell * self.length,
+            };
+        }
+        for u in a.unavailable.iter_mut() {
+            u.window = self.window(u.window);
+        }
+        a
+    }
+
+    /// The site with its geometry, closures, speed limits and clearance
+    /// converted.
+    pub fn site(&self, site: &Site) -> Site {
+        let mut site = self.lengths().site(site);
+        for o in site.obstacles.iter_mut() {
+            o.window = o.window.map(|w| self.window(w));
+        }
+        for e in site.roads.edges.iter_mut() {
+            e.speed *= self.length / self.time;
+        }
+        site.roads.clearance *= self.time;
+        site
+    }
+
+    /// The config with its times and distances converted.
+    pub fn config(&self, config: &SolverConfig) -> SolverConfig {
+        SolverConfig {
+            quantum: config.quantum * self.time,
+            tolerance: config.tolerance * self.length,
+            margin: config.margin * self.length,
+            reaction_time: config.reaction_time * self.time,
+            ..*config
+        }
+    }
+
+    /// The routing with its agents, moves, targets, times and trace
+    /// converted.
+    pub fn routing(&self, routing: &Routing) -> Routing {
+        Routing {
+            routes: routing
+                .routes
+                .iter()
+                .map(|(a, paths)| {
+                    let paths = paths
+                        .iter()
+                        .map(|p| Path {
+                            moves: p
+                                .moves
+                                .iter()
+                                .map(|s| Segment {
+                                    start: s.start * self.length,
+                                    end: s.end * self.length,
+                                    duration: s.duration * self.time,
+                                })
+                                .collect(),
+                            action: Action {
+                                agent: self.agent(&p.action.agent),
+                                target: p.action.target * self.length,
+                                duration: p.action.duration * self.time,
+                                std_dev: p.action.std_dev * self.time,
+                                ..p.action.clone()
+                            },
+                            t_start: p.t_start * self.time,
+                            t_end: p.t_end * self.time,
+                            ..p.clone()
+                        })
+                        .collect();
+                    (self.agent(a), paths)
+                })
+                .collect(),
+            trace: routing
+                .trace
+                .as_ref()
+                .map(|trace| trace.convert(|c| c * self.length, self.length, self.time)),
+        }
+    }
+}
+
+/// An affine transform from site coordinates to solver coordinates:
+/// `x' = a x + b y + xoff` and `y' = d x + e y + yoff`. The solver moves the
+/// agents along its own axes, so the transform aligns the site with them,
+/// e.g. rotates a site surveyed in a national grid onto the rails of its
+/// cranes. Velocities and safety distances are given along the solver axes.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    /// The factor of x in x'
+    pub a: f64,
+    /// The factor of y in x'
+    pub b: f64,
+    /// The offset of x'
+    pub xoff: f64,
+    /// The factor of x in y'
+    pub d: f64,
+    /// The factor of y in y'
+    pub e: f64,
+    /// The offset of y'
+    pub yoff: f64,
+}
+
+impl Transform {
+    /// The transform as used by the geometry operations.
+    pub fn affine(&self) -> AffineTransform {
+        AffineTransform::new(self.a, self.b, self.xoff, self.d, self.e, self.yoff)
+    }
+
+    /// Transform a coordinate.
+    pub fn apply(&self, c: Coord) -> Coord {
+        self.affine().apply(c)
+    }
+
+    /// The transform from solver coordinates back to site coordinates, if
+    /// the transform can be inverted.
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.e - self.b * self.d;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (a, b, d, e) = (self.e / det, -self.b / det, -self.d / det, self.a / det);
+        Some(Transform {
+            a,
+            b,
+            xoff: -(a * self.xoff + b * self.yoff),
+            d,
+            e,
+            yoff: -(d * self.xoff + e * self.yoff),
+        })
+    }
+
+    /// The agent with its reach, corridors, position and parking
+    /// transformed.
+    pub fn agent(&self, a: &Agent) -> Agent {
+        let t = self.affine();
+        let mut a = a.clone();
+        a.reach = a.reach.affine_transform(&t);
+        a.corridors = a.corridors.affine_transform(&t);
+        a.position = t.apply(a.position);
+        for u in a.unavailable.iter_mut() {
+            u.parking = match &u.parking {
+                Parking::Position(c) => Parking::Position(t.apply(*c)),
+                Parking::Zone(z) => Parking::Zone(z.affine_transform(&t)),
+            };
+        }
+        a
+    }
+
+    /// The site with its obstacles and road network transformed.
+    pub fn site(&self, site: &Site) -> Site {
+        let t = self.affine();
+        let mut site = site.clone();
+        for o in site.obstacles.iter_mut() {
+            o.area = o.area.affine_transform(&t);
+        }
+        for n in site.roads.nodes.iter_mut() {
+            n.position = t.apply(n.position);
+        }
+        site
+    }
+
+    /// The routing with its agents, moves, targets and trace transformed.
+    pub fn routing(&self, routing: &Routing) -> Routing {
+        let t = self.affine();
+        Routing {
+            routes: routing
+                .routes
+                .iter()
+                .map(|(a, paths)| {
+                    let paths = paths
+                        .iter()
+                        .map(|p| Path {
+                            moves: p
+                                .moves
+                                .iter()
+                                .map(|s| Segment {
+                                    start: t.apply(s.start),
+                                    end: t.apply(s.end),
+                                    duration: s.duration,
+                                })
+                                .collect(),
+                            action: Action {
+                                agent: self.agent(&p.action.agent),
+                                target: t.apply(p.action.target),
+                                ..p.action.clone()
+                            },
+                            ..p.clone()
+                        })
+                        .collect();
+                    (self.agent(a), paths)
+                })
+                .collect(),
+            trace: routing
+                .trace
+                .as_ref()
+                .map(|trace| trace.convert(|c| t.apply(c), 1.0, 1.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
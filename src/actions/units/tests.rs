@@ -0,0 +1,84 @@
+use geo::{Coord, LineString, Polygon};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{Agent, Parking, Planner, Unavailability},
+    fixtures::agent,
+    site::TimeWindow,
+    units::{LengthUnit, TimeUnit, Transform, Units},
+};
+
+#[test]
+fn test_units() {
+    let units: Units = serde_yaml::from_str("length: Millimetre\ntime: Millisecond").unwrap();
+    assert_eq!(units.length.metres(), 0.001);
+    assert_eq!(units.time, TimeUnit::Millisecond);
+    assert_eq!(Units::default().length, LengthUnit::Metre);
+
+    let scale = units.to_si();
+    let mut crane = agent("crane", 10000.0, 0);
+    crane.position.y = 5000.0;
+    crane.safety_x = 3000.0;
+    crane.planner = Planner::Grid { cell: 500.0 };
+    crane.unavailable = vec![Unavailability {
+        window: TimeWindow {
+            start: 1000.0,
+            end: 2500.0,
+        },
+        parking: Parking::Position(Coord { x: 0.0, y: 0.0 }),
+    }];
+    let converted = scale.agent(&crane);
+    assert_eq!(converted.position, Coord { x: 10.0, y: 5.0 });
+    assert_eq!((converted.velocity.x, converted.velocity.y), (2.0, 1.0));
+    assert_eq!(converted.safety_x, 3.0);
+    assert_eq!(converted.planner, Planner::Grid { cell: 0.5 });
+    assert_eq!(converted.unavailable[0].window.end, 2.5);
+    // The default quantum and tolerance are in the units of the scenario.
+    let config = scale.config(&SolverConfig::default());
+    assert_eq!((config.quantum, config.tolerance), (0.001, 1e-9));
+    assert_eq!(scale.inverse().agent(&converted).safety_x, 3000.0);
+}
+
+#[test]
+fn test_transform() {
+    // A quarter turn followed by a shift
+    let t = Transform {
+        a: 0.0,
+        b: -1.0,
+        xoff: 100.0,
+        d: 1.0,
+        e: 0.0,
+        yoff: 0.0,
+    };
+    let c = Coord { x: 10.0, y: 5.0 };
+    assert_eq!(t.apply(c), Coord { x: 95.0, y: 10.0 });
+    assert_eq!(t.inverse().unwrap().apply(t.apply(c)), c);
+    assert_eq!(
+        Transform {
+            a: 1.0,
+            b: 2.0,
+            xoff: 0.0,
+            d: 2.0,
+            e: 4.0,
+            yoff: 0.0,
+        }
+        .inverse(),
+        None
+    );
+
+    let agent = Agent {
+        reach: Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (20.0, 0.0), (20.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        )
+        .into(),
+        position: c,
+        ..agent("crane", 10.0, 0)
+    };
+    let moved = t.agent(&agent);
+    assert_eq!(moved.position, Coord { x: 95.0, y: 10.0 });
+    assert!(moved.reach.0[0]
+        .exterior()
+        .coords()
+        .any(|&c| c == Coord { x: 90.0, y: 20.0 }));
+}
@@ -0,0 +1,531 @@
+use std::fmt;
+
+use geo::{Area, BooleanOps, Coord, Distance, Euclidean, MultiPolygon, Point, Validation};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{Agent, Parking, Planner, Schedule},
+    site::Site,
+    units::Transform,
+};
+
+/// The severity of a validation issue. Errors prevent solving, warnings
+/// indicate input that is solvable but likely not intended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    /// The input cannot be solved
+    Error,
+    /// The input can be solved, but the result may be surprising
+    Warning,
+}
+
+/// The kind of a validation issue
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssueKind {
+    /// Two agents share the same name
+    DuplicateAgentName,
+    /// Two agents share the same order, so they are never checked against
+    /// each other
+    DuplicateOrder,
+    /// An action refers to an agent that is not part of the agents
+    UnknownAgent,
+    /// The reach or a corridor of an agent is empty or invalid
+    DegenerateReach,
+    /// The velocity of an agent is not strictly positive
+    NonPositiveVelocity,
+    /// The safety distance of an agent is negative
+    NegativeSafetyDistance,
+    /// The initial position of an agent lies outside its reach
+    PositionOutsideReach,
+    /// The initial positions of two agents are closer than their safety
+    /// distance
+    SafetyDistanceViolated,
+    /// The x-ordering of the initial positions does not match the order of
+    /// the agents
+    OrderMismatch,
+    /// The target of an action lies outside the reach of its agent
+    TargetOutsideReach,
+    /// The duration of an action, or its standard deviation, is negative
+    NegativeDuration,
+    /// The agent of an action lacks capabilities the action requires
+    MissingCapabilities,
+    /// No agent has the capabilities an action without an agent requires
+    /// and can reach its target
+    NoCapableAgent,
+    /// A number of the input is NaN or infinite
+    NonFiniteValue,
+    /// An unavailability window of an agent is empty, overlaps another one
+    /// or has its parking outside the reach and corridors
+    InvalidUnavailability,
+    /// The planner of an agent is misconfigured, e.g. a grid with a
+    /// non-positive cell size
+    InvalidPlanner,
+    /// A road of the network refers to an unknown node, is degenerate or
+    /// has no positive speed or capacity, or node names are not unique
+    InvalidRoadNetwork,
+    /// The position of an agent bound to the road network is not on a node
+    PositionOffNetwork,
+    /// Agents bound to the road network share the site with agents moving
+    /// freely, which the solver does not keep apart from them
+    MixedRoadAgents,
+    /// A setting of the solver is negative, or the transform of a scenario
+    /// cannot be inverted
+    InvalidConfig,
+}
+
+/// A problem found in the input of the solver.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// The severity of the issue
+    pub severity: Severity,
+    /// The kind of the issue
+    pub kind: IssueKind,
+    /// The name of the agent concerned, if any
+    pub agent: Option<String>,
+    /// The index of the action in the schedule concerned, if any
+    pub action: Option<usize>,
+    /// A human readable description of the issue
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(kind: IssueKind, message: String) -> ValidationIssue {
+        ValidationIssue {
+            severity: Severity::Error,
+            kind,
+            agent: None,
+            action: None,
+            message,
+        }
+    }
+
+    fn warning(kind: IssueKind, message: String) -> ValidationIssue {
+        ValidationIssue {
+            severity: Severity::Warning,
+            ..ValidationIssue::error(kind, message)
+        }
+    }
+
+    fn agent(mut self, agent: &str) -> ValidationIssue {
+        self.agent = Some(agent.to_string());
+        self
+    }
+
+    fn action(mut self, action: usize) -> ValidationIssue {
+        self.action = Some(action);
+        self
+    }
+
+    /// Whether the issue prevents solving.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Check the agents and schedule for input the solver cannot handle, with
+/// the tolerance of the config. The result is empty if the input is valid.
+pub fn validate(agents: &[Agent], sched: &Schedule, config: &SolverConfig) -> Vec<ValidationIssue> {
+    let tol = config.tolerance;
+    let mut issues = Vec::new();
+    for (a1, a2) in agents.iter().tuple_combinations() {
+        if a1.name == a2.name {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::DuplicateAgentName,
+                    format!("agent name '{}' is used more than once", a1.name),
+                )
+                .agent(&a1.name),
+            );
+        }
+    }
+    for a in agents.iter() {
+        issues.extend(validate_agent(a, tol));
+    }
+    issues.extend(validate_positions(agents, config));
+    for (i, action) in sched.actions.iter().enumerate() {
+        let agent = &action.agent;
+        if !agents.iter().any(|a| a.name == agent.name) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::UnknownAgent,
+                    format!("action #{} refers to unknown agent '{}'", i, agent.name),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+        }
+        if !action.target.x.is_finite()
+            || !action.target.y.is_finite()
+            || !action.duration.is_finite()
+            || !action.std_dev.is_finite()
+        {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::NonFiniteValue,
+                    format!(
+                        "action #{} has a non-finite target, duration or standard deviation",
+                        i
+                    ),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+            continue;
+        }
+        if action.duration < 0.0 {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::NegativeDuration,
+                    format!("action #{} has negative duration {}", i, action.duration),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+        }
+        if action.std_dev < 0.0 {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::NegativeDuration,
+                    format!(
+                        "action #{} has negative standard deviation {}",
+                        i, action.std_dev
+                    ),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+        }
+        if !agent.on_roads() && !within_reach(agent, action.target, tol) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::TargetOutsideReach,
+                    format!(
+                        "target ({}, {}) of action #{} is outside the reach of agent '{}'",
+                        action.target.x, action.target.y, i, agent.name
+                    ),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+        }
+        if !agent.can_execute(&action.requires) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::MissingCapabilities,
+                    format!(
+                        "action #{} requires capabilities [{}] that agent '{}' lacks",
+                        i,
+                        agent.missing_capabilities(&action.requires).join(", "),
+                        agent.name
+                    ),
+                )
+                .agent(&agent.name)
+                .action(i),
+            );
+        }
+    }
+    issues
+}
+
+/// Check the solver config and the transform of the input. The result is
+/// empty if they are valid.
+pub fn validate_config(
+    config: &SolverConfig,
+    transform: Option<&Transform>,
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (name, value) in [
+        ("quantum", config.quantum),
+        ("tolerance", config.tolerance),
+        ("margin", config.margin),
+        ("slack", config.slack),
+        ("deviations", config.deviations),
+        ("reaction time", config.reaction_time),
+    ] {
+        if !(value >= 0.0 && value.is_finite()) {
+            issues.push(ValidationIssue::error(
+                IssueKind::InvalidConfig,
+                format!("the {} {} of the solver is invalid", name, value),
+            ));
+        }
+    }
+    if transform.is_some_and(|t| t.inverse().is_none()) {
+        issues.push(ValidationIssue::error(
+            IssueKind::InvalidConfig,
+            String::from("the transform cannot be inverted"),
+        ));
+    }
+    issues
+}
+
+/// Check the site for input the solver cannot handle, and the agents bound to
+/// its road network. The result is empty if the input is valid.
+pub fn validate_site(site: &Site, agents: &[Agent], config: &SolverConfig) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let network = &site.roads;
+    let invalid = |message: String| ValidationIssue::error(IssueKind::InvalidRoadNetwork, message);
+    for (n1, n2) in network.nodes.iter().tuple_combinations() {
+        if n1.name == n2.name {
+            issues.push(invalid(format!(
+                "road node name '{}' is used more than once",
+                n1.name
+            )));
+        }
+    }
+    for (i, e) in network.edges.iter().enumerate() {
+        let find = |name: &str| network.nodes.iter().find(|n| n.name == name);
+        let message = match (find(&e.from), find(&e.to)) {
+            (None, _) => Some(format!("starts at unknown node '{}'", e.from)),
+            (_, None) => Some(format!("ends at unknown node '{}'", e.to)),
+            (Some(n1), Some(n2)) if n1.position == n2.position => {
+                Some(format!("from '{}' to '{}' has no length", e.from, e.to))
+            }
+            _ if !(e.speed > 0.0 && e.speed.is_finite()) => {
+                Some(format!("has invalid speed {}", e.speed))
+            }
+            _ if e.capacity == 0 => Some(String::from("has no capacity")),
+            _ => None,
+        };
+        if let Some(m) = message {
+            issues.push(invalid(format!("road #{} {}", i, m)));
+        }
+    }
+    if !(network.clearance > 0.0 && network.clearance.is_finite()) {
+        issues.push(invalid(format!(
+            "the clearance of the road network {} is not positive",
+            network.clearance
+        )));
+    }
+    for a in agents.iter().filter(|a| a.on_roads()) {
+        if !network.nodes.iter().any(|n| {
+            Euclidean.distance(&Point::from(n.position), &Point::from(a.position))
+                <= config.tolerance
+        }) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::PositionOffNetwork,
+                    format!(
+                        "position ({}, {}) of agent '{}' is not a node of the road network",
+                        a.position.x, a.position.y, a.name
+                    ),
+                )
+                .agent(&a.name),
+            );
+        }
+        if let Some(free) = agents.iter().find(|a| !a.on_roads()) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::MixedRoadAgents,
+                    format!(
+                        "agent '{}' on the road network is not kept apart from agent '{}' off it",
+                        a.name, free.name
+                    ),
+                )
+                .agent(&a.name),
+            );
+        }
+    }
+    issues
+}
+
+fn validate_agent(a: &Agent, tol: f64) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if !a.on_roads() && (a.reach.0.is_empty() || !valid_area(&a.reach)) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::DegenerateReach,
+                format!("the reach of agent '{}' is empty or invalid", a.name),
+            )
+            .agent(&a.name),
+        );
+    }
+    if !valid_area(&a.corridors) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::DegenerateReach,
+                format!("a corridor of agent '{}' is empty or invalid", a.name),
+            )
+            .agent(&a.name),
+        );
+    }
+    if !(a.velocity.x.is_finite() && a.velocity.y.is_finite()) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::NonFiniteValue,
+                format!("agent '{}' has a non-finite velocity", a.name),
+            )
+            .agent(&a.name),
+        );
+    } else if !(a.velocity.x > 0.0 && a.velocity.y > 0.0) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::NonPositiveVelocity,
+                format!(
+                    "agent '{}' has non-positive velocity ({}, {})",
+                    a.name, a.velocity.x, a.velocity.y
+                ),
+            )
+            .agent(&a.name),
+        );
+    }
+    if a.safety_x < 0.0 || !a.safety_x.is_finite() {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::NegativeSafetyDistance,
+                format!(
+                    "agent '{}' has invalid safety distance {}",
+                    a.name, a.safety_x
+                ),
+            )
+            .agent(&a.name),
+        );
+    }
+    if !(a.position.x.is_finite() && a.position.y.is_finite()) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::NonFiniteValue,
+                format!("agent '{}' has a non-finite position", a.name),
+            )
+            .agent(&a.name),
+        );
+    } else if !a.on_roads() && !within(&a.region(), a.position, tol) {
+        issues.push(
+            ValidationIssue::error(
+                IssueKind::PositionOutsideReach,
+                format!(
+                    "position ({}, {}) of agent '{}' is outside its reach and corridors",
+                    a.position.x, a.position.y, a.name
+                ),
+            )
+            .agent(&a.name),
+        );
+    }
+    if let Planner::Grid { cell } = a.planner {
+        if !(cell > 0.0 && cell.is_finite()) {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::InvalidPlanner,
+                    format!("agent '{}' has invalid grid cell size {}", a.name, cell),
+                )
+                .agent(&a.name),
+            );
+        }
+    }
+    for (i, u) in a.unavailable.iter().enumerate() {
+        let w = u.window;
+        // The agent parks at any point of a zone, so all of it has to be in
+        // the region of the agent.
+        let parking_in_region = match &u.parking {
+            _ if a.on_roads() => true,
+            Parking::Position(c) => within(&a.region(), *c, tol),
+            Parking::Zone(z) => z.difference(&a.region()).unsigned_area() <= tol,
+        };
+        let message = if !(w.start.is_finite() && w.end.is_finite() && w.start < w.end) {
+            Some(format!("is empty: {} to {}", w.start, w.end))
+        } else if a.unavailable[..i]
+            .iter()
+            .any(|o| o.window.overlaps(w.start, w.end))
+        {
+            Some(String::from("overlaps an earlier window"))
+        } else if !parking_in_region {
+            Some(String::from(
+                "has its parking outside the reach and corridors",
+            ))
+        } else {
+            None
+        };
+        if let Some(m) = message {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::InvalidUnavailability,
+                    format!("unavailability #{} of agent '{}' {}", i, a.name, m),
+                )
+                .agent(&a.name),
+            );
+        }
+    }
+    issues
+}
+
+/// Whether the coordinate lies inside or on the boundary of the reach of
+/// the agent, up to the tolerance.
+pub(crate) fn within_reach(a: &Agent, c: Coord, tol: f64) -> bool {
+    within(&a.reach, c, tol)
+}
+
+fn within(area: &MultiPolygon, c: Coord, tol: f64) -> bool {
+    Euclidean.distance(area, &Point::from(c)) <= tol
+}
+
+/// Whether all parts of the area are valid polygons with a positive area.
+fn valid_area(area: &MultiPolygon) -> bool {
+    area.is_valid()
+        && area
+            .iter()
+            .all(|p| p.exterior().0.len() >= 4 && p.unsigned_area() > 0.0)
+}
+
+fn validate_positions(agents: &[Agent], config: &SolverConfig) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (a1, a2) in agents
+        .iter()
+        .filter(|a| !a.on_roads())
+        .sorted_by_key(|a| a.order)
+        .tuple_combinations()
+    {
+        if a1.order == a2.order {
+            issues.push(
+                ValidationIssue::warning(
+                    IssueKind::DuplicateOrder,
+                    format!(
+                        "agents '{}' and '{}' share order {} and are not kept apart",
+                        a1.name, a2.name, a1.order
+                    ),
+                )
+                .agent(&a2.name),
+            );
+        } else if a1.position.x > a2.position.x {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::OrderMismatch,
+                    format!(
+                        "agent '{}' (order {}) is right of agent '{}' (order {})",
+                        a1.name, a1.order, a2.name, a2.order
+                    ),
+                )
+                .agent(&a2.name),
+            );
+        } else if a2.position.x - a1.position.x < config.safety_distance(a1, a2) - config.tolerance
+        {
+            issues.push(
+                ValidationIssue::error(
+                    IssueKind::SafetyDistanceViolated,
+                    format!(
+                        "agents '{}' and '{}' start {} apart, less than their safety distance {}",
+                        a1.name,
+                        a2.name,
+                        a2.position.x - a1.position.x,
+                        config.safety_distance(a1, a2)
+                    ),
+                )
+                .agent(&a2.name),
+            );
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests;
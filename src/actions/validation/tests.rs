@@ -0,0 +1,270 @@
+use geo::{Coord, LineString, Polygon};
+
+use crate::actions::{
+    config::SolverConfig,
+    data::{Action, Agent, Parking, Planner, Schedule, Unavailability},
+    fixtures::{action, agent},
+    site::{RoadEdge, RoadNetwork, RoadNode, Site, TimeWindow},
+    units::Transform,
+    validation::{validate, validate_config, validate_site, IssueKind, Severity},
+};
+
+fn kinds(agents: &[Agent], actions: Vec<Action>) -> Vec<(Severity, IssueKind)> {
+    validate(agents, &Schedule { actions }, &SolverConfig::default())
+        .into_iter()
+        .map(|i| (i.severity, i.kind))
+        .collect()
+}
+
+#[test]
+fn test_validate_valid() {
+    let agents = vec![agent("agent-0", 10.0, 0), agent("agent-1", 20.0, 1)];
+    let actions = vec![action(&agents[0], 50.0, 5.0)];
+
+    assert_eq!(kinds(&agents, actions), vec![]);
+}
+
+#[test]
+fn test_validate_agents() {
+    let mut degenerate = agent("agent-2", 80.0, 2);
+    degenerate.reach = Polygon::new(
+        LineString::from(vec![(0.0, 0.0), (100.0, 0.0), (50.0, 0.0)]),
+        vec![],
+    )
+    .into();
+    degenerate.planner = Planner::Grid { cell: 0.0 };
+    let mut unbounded = agent("agent-3", 90.0, 3);
+    unbounded.velocity.x = f64::NAN;
+    let agents = vec![
+        agent("agent-0", 30.0, 0),
+        agent("agent-0", 25.0, 1),
+        degenerate,
+        unbounded,
+    ];
+
+    assert_eq!(
+        kinds(&agents, vec![]),
+        vec![
+            (Severity::Error, IssueKind::DuplicateAgentName),
+            (Severity::Error, IssueKind::DegenerateReach),
+            (Severity::Error, IssueKind::PositionOutsideReach),
+            (Severity::Error, IssueKind::InvalidPlanner),
+            (Severity::Error, IssueKind::NonFiniteValue),
+            (Severity::Error, IssueKind::OrderMismatch),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_corridors() {
+    let mut a = agent("agent-0", 120.0, 0);
+    a.corridors = Polygon::new(
+        LineString::from(vec![(100.0, 4.0), (130.0, 4.0), (130.0, 6.0), (100.0, 6.0)]),
+        vec![],
+    )
+    .into();
+    let actions = vec![action(&a, 50.0, 5.0), action(&a, 120.0, 5.0)];
+
+    assert_eq!(
+        kinds(std::slice::from_ref(&a), actions),
+        vec![(Severity::Error, IssueKind::TargetOutsideReach)]
+    );
+
+    a.corridors = Polygon::new(
+        LineString::from(vec![(100.0, 4.0), (130.0, 4.0), (100.0, 4.0)]),
+        vec![],
+    )
+    .into();
+    assert_eq!(
+        kinds(&[a], vec![]),
+        vec![
+            (Severity::Error, IssueKind::DegenerateReach),
+            (Severity::Error, IssueKind::PositionOutsideReach),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_parking() {
+    let rect = |x0: f64, y0: f64, x1: f64, y1: f64| {
+        Polygon::new(
+            LineString::from(vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1)]),
+            vec![],
+        )
+    };
+    let parked = |parking: Parking| {
+        let mut a = agent("agent-0", 10.0, 0);
+        a.corridors = rect(100.0, 4.0, 130.0, 6.0).into();
+        a.unavailable = vec![Unavailability {
+            window: TimeWindow {
+                start: 10.0,
+                end: 20.0,
+            },
+            parking,
+        }];
+        kinds(&[a], vec![])
+    };
+    let invalid = vec![(Severity::Error, IssueKind::InvalidUnavailability)];
+
+    assert_eq!(
+        parked(Parking::Position(Coord { x: 120.0, y: 5.0 })),
+        vec![]
+    );
+    assert_eq!(parked(Parking::Zone(rect(90.0, 4.0, 120.0, 6.0))), vec![]);
+    assert_eq!(
+        parked(Parking::Position(Coord { x: 120.0, y: 8.0 })),
+        invalid
+    );
+    // The zone overlaps the reach, but the agent might park in the part of
+    // it outside.
+    assert_eq!(parked(Parking::Zone(rect(90.0, 4.0, 120.0, 20.0))), invalid);
+}
+
+#[test]
+fn test_validate_positions() {
+    let agents = vec![
+        agent("agent-0", 10.0, 0),
+        agent("agent-1", 15.0, 1),
+        agent("agent-2", 50.0, 1),
+    ];
+
+    assert_eq!(
+        kinds(&agents, vec![]),
+        vec![
+            (Severity::Error, IssueKind::SafetyDistanceViolated),
+            (Severity::Warning, IssueKind::DuplicateOrder),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_actions() {
+    let agents = vec![agent("agent-0", 10.0, 0)];
+    let unknown = agent("agent-1", 50.0, 1);
+    let mut lifting = action(&agents[0], 50.0, 1.0);
+    lifting.requires = vec![String::from("lift")];
+    let mut uncertain = action(&agents[0], 50.0, 1.0);
+    uncertain.std_dev = -0.5;
+    let actions = vec![
+        action(&agents[0], 150.0, 1.0),
+        action(&agents[0], 50.0, -1.0),
+        action(&unknown, 50.0, 1.0),
+        lifting,
+        uncertain,
+    ];
+
+    assert_eq!(
+        kinds(&agents, actions),
+        vec![
+            (Severity::Error, IssueKind::TargetOutsideReach),
+            (Severity::Error, IssueKind::NegativeDuration),
+            (Severity::Error, IssueKind::UnknownAgent),
+            (Severity::Error, IssueKind::MissingCapabilities),
+            (Severity::Error, IssueKind::NegativeDuration),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_site() {
+    let node = |name: &str, x: f64| RoadNode {
+        name: String::from(name),
+        position: Coord { x, y: 5.0 },
+    };
+    let road = |from: &str, to: &str, speed: f64| RoadEdge {
+        from: String::from(from),
+        to: String::from(to),
+        speed,
+        capacity: 1,
+    };
+    let site = Site {
+        roads: RoadNetwork {
+            nodes: vec![node("a", 10.0), node("b", 50.0)],
+            edges: vec![
+                road("a", "b", 1.0),
+                road("b", "c", 1.0),
+                road("b", "a", 0.0),
+            ],
+            clearance: 1.0,
+        },
+        ..Site::default()
+    };
+    let mut on_node = agent("agent-0", 10.0, 0);
+    on_node.planner = Planner::Road;
+    let mut off_node = agent("agent-1", 30.0, 1);
+    off_node.planner = Planner::Road;
+
+    let kinds = validate_site(
+        &site,
+        &[on_node.clone(), off_node],
+        &SolverConfig::default(),
+    )
+    .into_iter()
+    .map(|i| (i.severity, i.kind))
+    .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (Severity::Error, IssueKind::InvalidRoadNetwork),
+            (Severity::Error, IssueKind::InvalidRoadNetwork),
+            (Severity::Error, IssueKind::PositionOffNetwork),
+        ]
+    );
+
+    // The solver only keeps agents apart that are all on or all off the
+    // road network.
+    let free = agent("agent-2", 70.0, 2);
+    let kinds = validate_site(&site, &[on_node, free], &SolverConfig::default())
+        .into_iter()
+        .map(|i| i.kind)
+        .collect::<Vec<_>>();
+    assert!(kinds.contains(&IssueKind::MixedRoadAgents));
+}
+
+#[test]
+fn test_validate_config() {
+    let config = SolverConfig {
+        quantum: -1.0,
+        tolerance: f64::NAN,
+        margin: 0.5,
+        slack: -0.1,
+        ..SolverConfig::default()
+    };
+    let singular = Transform {
+        a: 1.0,
+        b: 2.0,
+        xoff: 0.0,
+        d: 2.0,
+        e: 4.0,
+        yoff: 0.0,
+    };
+
+    assert_eq!(validate_config(&SolverConfig::default(), None), vec![]);
+    assert_eq!(
+        validate_config(&config, Some(&singular))
+            .into_iter()
+            .map(|i| i.kind)
+            .collect::<Vec<_>>(),
+        vec![IssueKind::InvalidConfig; 4]
+    );
+}
+
+#[test]
+fn test_validate_margin() {
+    // The agents keep their safety distance, but not the margin on top.
+    let agents = [agent("agent-0", 30.0, 0), agent("agent-1", 42.0, 1)];
+    let config = SolverConfig {
+        margin: 5.0,
+        ..SolverConfig::default()
+    };
+
+    assert_eq!(kinds(&agents, vec![]), vec![]);
+    assert_eq!(
+        validate(&agents, &Schedule { actions: vec![] }, &config)
+            .into_iter()
+            .map(|i| i.kind)
+            .collect::<Vec<_>>(),
+        vec![IssueKind::SafetyDistanceViolated]
+    );
+}
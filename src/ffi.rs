@@ -0,0 +1,513 @@
+//! A C API to embed the solver, declared in `include/keiro.h`. Every function
+//! returns a status code; panics are caught and reported as
+//! `KEIRO_STATUS_PANIC`. The message of the last failure is available from
+//! `keiro_solver_last_error` until a call that changes the context succeeds.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use geo::{Coord, LineString, Polygon};
+
+use crate::actions::{
+    data::{ActionType, Agent, ConstVel2D, Path},
+    scenario::{Scenario, ScenarioAction},
+    Routing,
+};
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeiroStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A pointer argument is null
+    NullPointer,
+    /// A string argument is not valid UTF-8
+    InvalidUtf8,
+    /// The serialized scenario cannot be read
+    InvalidScenario,
+    /// The scenario fails validation
+    InvalidInput,
+    /// The solver found no routing
+    NoRoute,
+    /// The solver has not found a routing yet
+    NotSolved,
+    /// An index is out of range
+    OutOfRange,
+    /// The call panicked
+    Panic,
+}
+
+/// The type of the action of a path.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeiroActionType {
+    /// A scheduled action
+    Scheduled,
+    /// An evasive action
+    Evasive,
+    /// An idle action
+    Idle,
+    /// The agent is parked during an unavailability window
+    Parked,
+}
+
+impl From<&ActionType> for KeiroActionType {
+    fn from(t: &ActionType) -> Self {
+        match t {
+            ActionType::Scheduled => KeiroActionType::Scheduled,
+            ActionType::Evasive => KeiroActionType::Evasive,
+            ActionType::Idle => KeiroActionType::Idle,
+            ActionType::Parked => KeiroActionType::Parked,
+        }
+    }
+}
+
+/// A path of an agent, without its segments.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeiroPath {
+    /// The type of the action
+    pub r#type: KeiroActionType,
+    /// The x-coordinate of the target
+    pub target_x: f64,
+    /// The y-coordinate of the target
+    pub target_y: f64,
+    /// The start time of the path
+    pub t_start: f64,
+    /// The end time of the path
+    pub t_end: f64,
+    /// The index of the scheduled action the solver was executing when it
+    /// planned the path, or -1
+    pub step: i64,
+    /// The number of segments of the path
+    pub segments: usize,
+}
+
+/// A segment of a path.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeiroSegment {
+    /// The x-coordinate of the start
+    pub start_x: f64,
+    /// The y-coordinate of the start
+    pub start_y: f64,
+    /// The x-coordinate of the end
+    pub end_x: f64,
+    /// The y-coordinate of the end
+    pub end_y: f64,
+    /// The duration of the segment
+    pub duration: f64,
+}
+
+/// A solver context: the scenario built up by the caller and the routing
+/// found for it.
+pub struct KeiroSolver {
+    scenario: Scenario,
+    routing: Option<Routing>,
+    names: Vec<CString>,
+    error: CString,
+}
+
+impl KeiroSolver {
+    fn fail(&mut self, status: KeiroStatus, message: String) -> KeiroStatus {
+        self.error = CString::new(message.replace('\0', " ")).unwrap();
+        status
+    }
+
+    fn succeed(&mut self) -> KeiroStatus {
+        self.error = CString::default();
+        KeiroStatus::Ok
+    }
+
+    fn path(&self, agent: usize, path: usize) -> Result<&Path, KeiroStatus> {
+        self.routing
+            .as_ref()
+            .ok_or(KeiroStatus::NotSolved)?
+            .routes
+            .get(agent)
+            .and_then(|(_, ps)| ps.get(path))
+            .ok_or(KeiroStatus::OutOfRange)
+    }
+}
+
+/// Run the body of a call, turning a panic into a status.
+fn guard(body: impl FnOnce() -> KeiroStatus) -> KeiroStatus {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(KeiroStatus::Panic)
+}
+
+/// The string behind a pointer.
+unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, KeiroStatus> {
+    if s.is_null() {
+        return Err(KeiroStatus::NullPointer);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| KeiroStatus::InvalidUtf8)
+}
+
+/// Create a solver context with an empty scenario. Free it with
+/// `keiro_solver_free`.
+#[no_mangle]
+pub extern "C" fn keiro_solver_new() -> *mut KeiroSolver {
+    Box::into_raw(Box::new(KeiroSolver {
+        scenario: Scenario::default(),
+        routing: None,
+        names: vec![],
+        error: CString::default(),
+    }))
+}
+
+/// Free a solver context.
+///
+/// # Safety
+///
+/// The pointer is null or was returned by `keiro_solver_new` and is not
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_free(solver: *mut KeiroSolver) {
+    if !solver.is_null() {
+        drop(Box::from_raw(solver));
+    }
+}
+
+/// The message of the last failure, empty once a later call that changes
+/// the context succeeds, and valid until the next call with the context.
+///
+/// # Safety
+///
+/// The pointer was returned by `keiro_solver_new`.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_last_error(solver: *const KeiroSolver) -> *const c_char {
+    match solver.as_ref() {
+        Some(s) => s.error.as_ptr(),
+        None => c"null solver".as_ptr(),
+    }
+}
+
+/// Replace the scenario by a serialized one, as YAML or JSON.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and the scenario is a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_load_scenario(
+    solver: *mut KeiroSolver,
+    scenario: *const c_char,
+) -> KeiroStatus {
+    guard(|| {
+        let Some(s) = solver.as_mut() else {
+            return KeiroStatus::NullPointer;
+        };
+        let text = match string(scenario) {
+            Ok(t) => t,
+            Err(status) => return s.fail(status, String::from("invalid scenario string")),
+        };
+        match serde_yaml::from_str::<Scenario>(text) {
+            Ok(scenario) => {
+                s.scenario = scenario;
+                s.routing = None;
+                s.succeed()
+            }
+            Err(e) => s.fail(KeiroStatus::InvalidScenario, e.to_string()),
+        }
+    })
+}
+
+/// Add an agent with a polygonal reach, given as `points` pairs of x- and
+/// y-coordinates.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new`, the name is a
+/// null-terminated string and the reach holds `2 * points` numbers.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn keiro_solver_add_agent(
+    solver: *mut KeiroSolver,
+    name: *const c_char,
+    reach: *const f64,
+    points: usize,
+    x: f64,
+    y: f64,
+    velocity_x: f64,
+    velocity_y: f64,
+    safety_x: f64,
+    order: i64,
+) -> KeiroStatus {
+    guard(|| {
+        let Some(s) = solver.as_mut() else {
+            return KeiroStatus::NullPointer;
+        };
+        let name = match string(name) {
+            Ok(n) => n,
+            Err(status) => return s.fail(status, String::from("invalid agent name")),
+        };
+        if reach.is_null() {
+            return s.fail(KeiroStatus::NullPointer, String::from("null reach"));
+        }
+        let Some(len) = points
+            .checked_mul(2)
+            .filter(|&n| n <= isize::MAX as usize / size_of::<f64>())
+        else {
+            return s.fail(
+                KeiroStatus::OutOfRange,
+                String::from("too many reach points"),
+            );
+        };
+        let xy = std::slice::from_raw_parts(reach, len);
+        let ring = xy.chunks(2).map(|c| (c[0], c[1])).collect::<Vec<_>>();
+        s.scenario.agents.push(Agent {
+            name: name.to_string(),
+            reach: Polygon::new(LineString::from(ring), vec![]).into(),
+            position: Coord { x, y },
+            velocity: ConstVel2D {
+                x: velocity_x,
+                y: velocity_y,
+            },
+            safety_x,
+            order,
+            ..Default::default()
+        });
+        s.routing = None;
+        s.succeed()
+    })
+}
+
+/// Add a scheduled action of the named agent, or of the agent it is assigned
+/// to if the agent is null.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and the agent is null or a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_add_action(
+    solver: *mut KeiroSolver,
+    agent: *const c_char,
+    x: f64,
+    y: f64,
+    duration: f64,
+) -> KeiroStatus {
+    guard(|| {
+        let Some(s) = solver.as_mut() else {
+            return KeiroStatus::NullPointer;
+        };
+        let agent = match agent.is_null() {
+            true => None,
+            false => match string(agent) {
+                Ok(a) => Some(a.to_string()),
+                Err(status) => return s.fail(status, String::from("invalid agent name")),
+            },
+        };
+        s.scenario.actions.push(ScenarioAction {
+            agent,
+            target: Coord { x, y },
+            duration,
+            ..Default::default()
+        });
+        s.routing = None;
+        s.succeed()
+    })
+}
+
+/// Validate and solve the scenario.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new`.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_solve(solver: *mut KeiroSolver) -> KeiroStatus {
+    guard(|| {
+        let Some(s) = solver.as_mut() else {
+            return KeiroStatus::NullPointer;
+        };
+        let (solution, _) = s.scenario.solve();
+        match solution.routing {
+            Some(routing) => {
+                s.names = routing
+                    .routes
+                    .iter()
+                    .map(|(a, _)| CString::new(a.name.replace('\0', " ")).unwrap())
+                    .collect();
+                s.routing = Some(routing);
+                s.succeed()
+            }
+            None => {
+                s.routing = None;
+                match solution.error {
+                    Some(e) => s.fail(KeiroStatus::NoRoute, e),
+                    None => {
+                        let issues = solution.issues.iter().map(|i| i.to_string());
+                        s.fail(
+                            KeiroStatus::InvalidInput,
+                            issues.collect::<Vec<_>>().join("\n"),
+                        )
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// The number of agents of the routing.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and `count` points to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_agent_count(
+    solver: *const KeiroSolver,
+    count: *mut usize,
+) -> KeiroStatus {
+    guard(|| {
+        let (Some(s), Some(count)) = (solver.as_ref(), count.as_mut()) else {
+            return KeiroStatus::NullPointer;
+        };
+        match &s.routing {
+            Some(r) => {
+                *count = r.routes.len();
+                KeiroStatus::Ok
+            }
+            None => KeiroStatus::NotSolved,
+        }
+    })
+}
+
+/// The name of an agent of the routing, valid until the context is solved
+/// again or freed.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and `name` points to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_agent_name(
+    solver: *const KeiroSolver,
+    agent: usize,
+    name: *mut *const c_char,
+) -> KeiroStatus {
+    guard(|| {
+        let (Some(s), Some(name)) = (solver.as_ref(), name.as_mut()) else {
+            return KeiroStatus::NullPointer;
+        };
+        if s.routing.is_none() {
+            return KeiroStatus::NotSolved;
+        }
+        match s.names.get(agent) {
+            Some(n) => {
+                *name = n.as_ptr();
+                KeiroStatus::Ok
+            }
+            None => KeiroStatus::OutOfRange,
+        }
+    })
+}
+
+/// The number of paths of an agent of the routing.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and `count` points to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_path_count(
+    solver: *const KeiroSolver,
+    agent: usize,
+    count: *mut usize,
+) -> KeiroStatus {
+    guard(|| {
+        let (Some(s), Some(count)) = (solver.as_ref(), count.as_mut()) else {
+            return KeiroStatus::NullPointer;
+        };
+        let Some(r) = &s.routing else {
+            return KeiroStatus::NotSolved;
+        };
+        match r.routes.get(agent) {
+            Some((_, ps)) => {
+                *count = ps.len();
+                KeiroStatus::Ok
+            }
+            None => KeiroStatus::OutOfRange,
+        }
+    })
+}
+
+/// A path of an agent of the routing.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and `out` points to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_path(
+    solver: *const KeiroSolver,
+    agent: usize,
+    path: usize,
+    out: *mut KeiroPath,
+) -> KeiroStatus {
+    guard(|| {
+        let (Some(s), Some(out)) = (solver.as_ref(), out.as_mut()) else {
+            return KeiroStatus::NullPointer;
+        };
+        match s.path(agent, path) {
+            Ok(p) => {
+                *out = KeiroPath {
+                    r#type: (&p.action.r#type).into(),
+                    target_x: p.action.target.x,
+                    target_y: p.action.target.y,
+                    t_start: p.t_start,
+                    t_end: p.t_end,
+                    step: p.step.map_or(-1, |s| s as i64),
+                    segments: p.moves.len(),
+                };
+                KeiroStatus::Ok
+            }
+            Err(status) => status,
+        }
+    })
+}
+
+/// A segment of a path of an agent of the routing.
+///
+/// # Safety
+///
+/// The solver was returned by `keiro_solver_new` and `out` points to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn keiro_solver_segment(
+    solver: *const KeiroSolver,
+    agent: usize,
+    path: usize,
+    segment: usize,
+    out: *mut KeiroSegment,
+) -> KeiroStatus {
+    guard(|| {
+        let (Some(s), Some(out)) = (solver.as_ref(), out.as_mut()) else {
+            return KeiroStatus::NullPointer;
+        };
+        match s
+            .path(agent, path)
+            .and_then(|p| p.moves.get(segment).ok_or(KeiroStatus::OutOfRange))
+        {
+            Ok(m) => {
+                *out = KeiroSegment {
+                    start_x: m.start.x,
+                    start_y: m.start.y,
+                    end_x: m.end.x,
+                    end_y: m.end.y,
+                    duration: m.duration,
+                };
+                KeiroStatus::Ok
+            }
+            Err(status) => status,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,132 @@
+use std::{ffi::CStr, ptr};
+
+use crate::ffi::{
+    keiro_solver_add_action, keiro_solver_add_agent, keiro_solver_agent_count,
+    keiro_solver_agent_name, keiro_solver_free, keiro_solver_last_error,
+    keiro_solver_load_scenario, keiro_solver_new, keiro_solver_path, keiro_solver_path_count,
+    keiro_solver_segment, keiro_solver_solve, KeiroActionType, KeiroPath, KeiroSegment,
+    KeiroStatus,
+};
+
+const RAIL: [f64; 8] = [0.0, 0.0, 100.0, 0.0, 100.0, 10.0, 0.0, 10.0];
+
+#[test]
+fn test_ffi_solve() {
+    unsafe {
+        let solver = keiro_solver_new();
+        for (name, x, order) in [(c"agent-0", 10.0, 0), (c"agent-1", 30.0, 1)] {
+            let status = keiro_solver_add_agent(
+                solver,
+                name.as_ptr(),
+                RAIL.as_ptr(),
+                4,
+                x,
+                5.0,
+                2.0,
+                1.0,
+                10.0,
+                order,
+            );
+            assert_eq!(status, KeiroStatus::Ok);
+        }
+        let mut count = 0;
+        assert_eq!(
+            keiro_solver_agent_count(solver, &mut count),
+            KeiroStatus::NotSolved
+        );
+        keiro_solver_add_action(solver, c"agent-1".as_ptr(), 40.0, 5.0, 20.0);
+        keiro_solver_add_action(solver, c"agent-0".as_ptr(), 50.0, 5.0, 0.0);
+        assert_eq!(keiro_solver_solve(solver), KeiroStatus::Ok);
+
+        assert_eq!(
+            keiro_solver_agent_count(solver, &mut count),
+            KeiroStatus::Ok
+        );
+        assert_eq!(count, 2);
+        let mut name = ptr::null();
+        keiro_solver_agent_name(solver, 1, &mut name);
+        assert_eq!(CStr::from_ptr(name), c"agent-1");
+        keiro_solver_path_count(solver, 1, &mut count);
+        assert_eq!(count, 3);
+
+        let mut path = KeiroPath {
+            r#type: KeiroActionType::Idle,
+            target_x: 0.0,
+            target_y: 0.0,
+            t_start: 0.0,
+            t_end: 0.0,
+            step: 0,
+            segments: 0,
+        };
+        assert_eq!(keiro_solver_path(solver, 1, 2, &mut path), KeiroStatus::Ok);
+        assert_eq!(path.r#type, KeiroActionType::Evasive);
+        assert_eq!((path.target_x, path.t_start, path.step), (60.0, 25.0, 1));
+        let mut segment = KeiroSegment {
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 0.0,
+            end_y: 0.0,
+            duration: 0.0,
+        };
+        assert_eq!(
+            keiro_solver_segment(solver, 1, 2, 0, &mut segment),
+            KeiroStatus::Ok
+        );
+        assert_eq!((segment.start_x, segment.end_x), (40.0, 60.0));
+        assert_eq!(
+            keiro_solver_segment(solver, 1, 2, path.segments, &mut segment),
+            KeiroStatus::OutOfRange
+        );
+        assert_eq!(
+            keiro_solver_path(solver, 1, 2, ptr::null_mut()),
+            KeiroStatus::NullPointer
+        );
+        keiro_solver_free(solver);
+    }
+}
+
+#[test]
+fn test_ffi_errors() {
+    unsafe {
+        let solver = keiro_solver_new();
+        assert_eq!(
+            keiro_solver_load_scenario(solver, c"agents: [".as_ptr()),
+            KeiroStatus::InvalidScenario
+        );
+        assert_eq!(
+            keiro_solver_load_scenario(solver, c"{\"agents\": [], \"actions\": []}".as_ptr()),
+            KeiroStatus::Ok
+        );
+        keiro_solver_add_action(solver, c"crane".as_ptr(), 40.0, 5.0, 0.0);
+        assert_eq!(keiro_solver_solve(solver), KeiroStatus::InvalidInput);
+        let error = CStr::from_ptr(keiro_solver_last_error(solver));
+        assert!(error.to_str().unwrap().contains("unknown agent 'crane'"));
+        assert_eq!(
+            keiro_solver_add_action(ptr::null_mut(), c"crane".as_ptr(), 0.0, 0.0, 0.0),
+            KeiroStatus::NullPointer
+        );
+        // The reach cannot have more points than fit into memory.
+        assert_eq!(
+            keiro_solver_add_agent(
+                solver,
+                c"crane".as_ptr(),
+                RAIL.as_ptr(),
+                usize::MAX / 2 + 1,
+                10.0,
+                5.0,
+                2.0,
+                1.0,
+                10.0,
+                0,
+            ),
+            KeiroStatus::OutOfRange
+        );
+        // A call that succeeds clears the failure.
+        assert_eq!(
+            keiro_solver_load_scenario(solver, c"{\"agents\": [], \"actions\": []}".as_ptr()),
+            KeiroStatus::Ok
+        );
+        assert_eq!(CStr::from_ptr(keiro_solver_last_error(solver)), c"");
+        keiro_solver_free(solver);
+    }
+}
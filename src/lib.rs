@@ -1,4 +1,12 @@
 pub mod actions;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "service")]
+pub mod service;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[test]
 fn test_run() {
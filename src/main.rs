@@ -1,5 +1,145 @@
-pub mod actions;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Hello, world!");
+use keiro::actions::{commands, gis, scenario::Scenario, validation::ValidationIssue, Solver};
+#[cfg(feature = "service")]
+use keiro::service;
+
+const USAGE: &str = "usage: keiro validate <scenario.yml>
+       keiro solve <scenario.yml> [--trace <trace.json>] [--geojson <routing.geojson>]
+                                  [--commands <commands.jsonl|.csv>]
+       keiro serve [<address>]";
+
+/// The files `keiro solve` writes besides the routing.
+#[derive(Default)]
+struct Outputs<'a> {
+    trace: Option<&'a str>,
+    geojson: Option<&'a str>,
+    commands: Option<&'a str>,
+}
+
+impl<'a> Outputs<'a> {
+    /// Parse the options, unless they are invalid.
+    fn parse(options: &[&'a str]) -> Option<Outputs<'a>> {
+        let mut outputs = Outputs::default();
+        for pair in options.chunks(2) {
+            match pair {
+                ["--trace", file] => outputs.trace = Some(file),
+                ["--geojson", file] => outputs.geojson = Some(file),
+                ["--commands", file] => outputs.commands = Some(file),
+                _ => return None,
+            }
+        }
+        Some(outputs)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let code = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["validate", file] => Some(run(file, None)),
+        ["solve", file, ref options @ ..] => Outputs::parse(options).map(|o| run(file, Some(o))),
+        ["serve"] => Some(serve(None)),
+        ["serve", address] => Some(serve(Some(address))),
+        _ => None,
+    };
+    code.unwrap_or_else(|| {
+        eprintln!("{}", USAGE);
+        ExitCode::FAILURE
+    })
+}
+
+/// Read and validate the scenario, and solve it if outputs are given. The
+/// routing is written as YAML to stdout, validation issues to stderr, the
+/// decisions of the solver as JSON to the trace file, the paths with the
+/// decisions as GeoJSON to the GeoJSON file and the command streams of the
+/// agents as JSON lines or, for a `.csv` file, as CSV to the commands file,
+/// if given.
+fn run(file: &str, outputs: Option<Outputs>) -> ExitCode {
+    let scenario = match std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_yaml::from_str::<Scenario>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("cannot read scenario '{}': {}", file, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Validation and solving happen in solver coordinates and units, the
+    // routing is converted back.
+    let (prepared, issues) = scenario.prepare();
+    report(&issues);
+    let Some((s, schedule)) = prepared else {
+        return ExitCode::FAILURE;
+    };
+    let Some(outputs) = outputs else {
+        return ExitCode::SUCCESS;
+    };
+    let solver = Solver {
+        site: s.site,
+        config: s.config,
+        trace: outputs.trace.is_some() || outputs.geojson.is_some(),
+    };
+    match solver.routes(&s.agents, schedule) {
+        Ok(routing) => {
+            if let Some(file) = outputs.commands {
+                let commands =
+                    scenario.commands_in_scenario_coordinates(&routing.commands(&solver.config));
+                let text = if file.ends_with(".csv") {
+                    commands::to_csv(&commands)
+                } else {
+                    commands::to_json_lines(&commands)
+                };
+                if let Err(e) = std::fs::write(file, text) {
+                    eprintln!("cannot write commands '{}': {}", file, e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            let mut routing = scenario.in_scenario_coordinates(&routing);
+            if let Some(file) = outputs.geojson {
+                if let Err(e) = std::fs::write(file, gis::routing_to_geojson(&routing)) {
+                    eprintln!("cannot write GeoJSON '{}': {}", file, e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            if let (Some(file), Some(t)) = (outputs.trace, routing.trace.take()) {
+                if let Err(e) = std::fs::write(file, t.to_json()) {
+                    eprintln!("cannot write trace '{}': {}", file, e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            print!("{}", serde_yaml::to_string(&routing).unwrap());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Serve the HTTP API until the process is stopped.
+#[cfg(feature = "service")]
+fn serve(address: Option<&str>) -> ExitCode {
+    let address = address.unwrap_or(service::ADDRESS);
+    match service::serve(address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("cannot serve on '{}': {}", address, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(feature = "service"))]
+fn serve(_: Option<&str>) -> ExitCode {
+    eprintln!("keiro is built without the service feature");
+    ExitCode::FAILURE
+}
+
+/// Print the issues to stderr.
+fn report(issues: &[ValidationIssue]) {
+    for i in issues.iter() {
+        eprintln!("{}", i);
+    }
 }
@@ -0,0 +1,384 @@
+//! Python bindings, built with maturin as the `keiro` extension module.
+
+use std::collections::HashMap;
+
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+use geojson::GeoJson;
+use pyo3::{
+    exceptions::{PyKeyError, PyTypeError, PyValueError},
+    prelude::*,
+};
+
+use crate::actions::{
+    data::{Action, ActionType, Agent, ConstVel2D, Motion, Path, Planner, PointST, Schedule},
+    routes, Routing,
+};
+
+/// A move from a point to another, with its duration.
+type Move = ((f64, f64), (f64, f64), f64);
+
+/// An agent, with its reach and corridors given as a list of `(x, y)`
+/// points, a list of such lists for several parts, or a shapely polygon or
+/// multi-polygon.
+#[pyclass(name = "Agent", module = "keiro")]
+#[derive(Clone)]
+pub struct PyAgent {
+    agent: Agent,
+}
+
+#[pymethods]
+impl PyAgent {
+    #[new]
+    #[pyo3(signature = (name, reach, position, velocity, safety_x, order, corridors=None, capabilities=vec![]))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        name: String,
+        reach: &Bound<'_, PyAny>,
+        position: [f64; 2],
+        velocity: [f64; 2],
+        safety_x: f64,
+        order: i64,
+        corridors: Option<&Bound<'_, PyAny>>,
+        capabilities: Vec<String>,
+    ) -> PyResult<Self> {
+        Ok(PyAgent {
+            agent: Agent {
+                name,
+                reach: multi_polygon(reach)?,
+                corridors: corridors
+                    .map(multi_polygon)
+                    .transpose()?
+                    .unwrap_or(MultiPolygon(vec![])),
+                position: Coord {
+                    x: position[0],
+                    y: position[1],
+                },
+                velocity: ConstVel2D {
+                    x: velocity[0],
+                    y: velocity[1],
+                },
+                motion: Motion::default(),
+                safety_x,
+                order,
+                capabilities,
+                unavailable: vec![],
+                planner: Planner::default(),
+            },
+        })
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.agent.name
+    }
+
+    #[getter]
+    fn position(&self) -> (f64, f64) {
+        self.agent.position.x_y()
+    }
+
+    /// The exterior rings of the parts of the reach.
+    #[getter]
+    fn reach(&self) -> Vec<Vec<(f64, f64)>> {
+        self.agent
+            .reach
+            .iter()
+            .map(|p| p.exterior().coords().map(|c| c.x_y()).collect())
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Agent(name={:?}, position=({}, {}))",
+            self.agent.name, self.agent.position.x, self.agent.position.y
+        )
+    }
+}
+
+/// A scheduled action of an agent.
+#[pyclass(name = "Action", module = "keiro")]
+#[derive(Clone)]
+pub struct PyAction {
+    action: Action,
+}
+
+#[pymethods]
+impl PyAction {
+    #[new]
+    #[pyo3(signature = (agent, target, duration, requires=vec![], std_dev=0.0))]
+    fn new(
+        agent: PyAgent,
+        target: [f64; 2],
+        duration: f64,
+        requires: Vec<String>,
+        std_dev: f64,
+    ) -> Self {
+        PyAction {
+            action: Action {
+                agent: agent.agent,
+                target: Coord {
+                    x: target[0],
+                    y: target[1],
+                },
+                duration,
+                r#type: ActionType::Scheduled,
+                requires,
+                std_dev,
+            },
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Action(agent={:?}, target=({}, {}), duration={})",
+            self.action.agent.name,
+            self.action.target.x,
+            self.action.target.y,
+            self.action.duration
+        )
+    }
+}
+
+/// The scheduled actions, in the order they have to be executed.
+#[pyclass(name = "Schedule", module = "keiro")]
+#[derive(Clone)]
+pub struct PySchedule {
+    actions: Vec<Action>,
+}
+
+#[pymethods]
+impl PySchedule {
+    #[new]
+    fn new(actions: Vec<PyAction>) -> Self {
+        PySchedule {
+            actions: actions.into_iter().map(|a| a.action).collect(),
+        }
+    }
+
+    fn __len__(&self) -> usize {
+        self.actions.len()
+    }
+}
+
+/// A point of a path in space and time.
+#[pyclass(name = "PointST", module = "keiro", get_all)]
+#[derive(Clone)]
+pub struct PyPointST {
+    x: f64,
+    y: f64,
+    t: f64,
+}
+
+impl From<PointST> for PyPointST {
+    fn from(p: PointST) -> Self {
+        PyPointST {
+            x: p.x,
+            y: p.y,
+            t: p.t,
+        }
+    }
+}
+
+#[pymethods]
+impl PyPointST {
+    fn __repr__(&self) -> String {
+        format!("PointST(x={}, y={}, t={})", self.x, self.y, self.t)
+    }
+}
+
+/// A path of an agent to the target of an action.
+#[pyclass(name = "Path", module = "keiro")]
+#[derive(Clone)]
+pub struct PyPath {
+    path: Path,
+}
+
+#[pymethods]
+impl PyPath {
+    /// The type of the action, e.g. `"Scheduled"` or `"Idle"`.
+    #[getter]
+    fn r#type(&self) -> String {
+        format!("{:?}", self.path.action.r#type)
+    }
+
+    #[getter]
+    fn target(&self) -> (f64, f64) {
+        self.path.action.target.x_y()
+    }
+
+    #[getter]
+    fn t_start(&self) -> f64 {
+        self.path.t_start
+    }
+
+    #[getter]
+    fn t_end(&self) -> f64 {
+        self.path.t_end
+    }
+
+    #[getter]
+    fn step(&self) -> Option<usize> {
+        self.path.step
+    }
+
+    /// The moves as `((x, y), (x, y), duration)` triples.
+    #[getter]
+    fn moves(&self) -> Vec<Move> {
+        self.path
+            .moves
+            .iter()
+            .map(|s| (s.start.x_y(), s.end.x_y(), s.duration))
+            .collect()
+    }
+
+    /// The points of the path in space and time.
+    fn points(&self) -> Vec<PyPointST> {
+        self.path
+            .to_points_st()
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+}
+
+/// The paths found for the agents.
+#[pyclass(name = "Routing", module = "keiro")]
+pub struct PyRouting {
+    routing: Routing,
+}
+
+impl PyRouting {
+    fn agent_paths(&self, agent: &str) -> PyResult<&Vec<Path>> {
+        self.routing
+            .routes
+            .iter()
+            .find(|(a, _)| a.name == agent)
+            .map(|(_, ps)| ps)
+            .ok_or_else(|| PyKeyError::new_err(agent.to_string()))
+    }
+}
+
+#[pymethods]
+impl PyRouting {
+    /// The names of the agents.
+    fn agents(&self) -> Vec<String> {
+        self.routing
+            .routes
+            .iter()
+            .map(|(a, _)| a.name.clone())
+            .collect()
+    }
+
+    /// The paths of the agent.
+    fn paths(&self, agent: &str) -> PyResult<Vec<PyPath>> {
+        Ok(self
+            .agent_paths(agent)?
+            .iter()
+            .map(|p| PyPath { path: p.clone() })
+            .collect())
+    }
+
+    /// The points of all paths of the agent in space and time.
+    fn points(&self, agent: &str) -> PyResult<Vec<PyPointST>> {
+        Ok(self
+            .agent_paths(agent)?
+            .iter()
+            .flat_map(|p| p.to_points_st())
+            .map(Into::into)
+            .collect())
+    }
+
+    /// The position of the agent at time `t` under its motion model.
+    fn position_at(&self, agent: &str, t: f64) -> PyResult<(f64, f64)> {
+        self.routing
+            .position_at(agent, t)
+            .map(|c| c.x_y())
+            .ok_or_else(|| PyKeyError::new_err(agent.to_string()))
+    }
+
+    /// The points of all agents sampled every `dt`, by agent name.
+    fn sample(&self, dt: f64) -> PyResult<HashMap<String, Vec<PyPointST>>> {
+        if dt.is_nan() || dt <= 0.0 {
+            return Err(PyValueError::new_err(
+                "the sampling interval must be positive",
+            ));
+        }
+        Ok(self
+            .routing
+            .sample(dt)
+            .into_iter()
+            .map(|t| (t.agent, t.points.into_iter().map(Into::into).collect()))
+            .collect())
+    }
+
+    /// The routing as JSON.
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.routing).unwrap()
+    }
+}
+
+/// Compute routes for the agents, given a schedule of actions.
+#[pyfunction]
+#[pyo3(name = "routes")]
+fn py_routes(agents: Vec<PyAgent>, schedule: PySchedule) -> PyResult<PyRouting> {
+    let agents = agents.into_iter().map(|a| a.agent).collect::<Vec<_>>();
+    let schedule = Schedule {
+        actions: schedule.actions,
+    };
+    routes(&agents, schedule)
+        .map(|routing| PyRouting { routing })
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// A multi-polygon from a list of points, a list of such lists or an object
+/// with a `__geo_interface__`, such as a shapely geometry.
+fn multi_polygon(value: &Bound<'_, PyAny>) -> PyResult<MultiPolygon> {
+    let ring = |points: Vec<[f64; 2]>| {
+        Polygon::new(
+            LineString::from(points.into_iter().map(|[x, y]| (x, y)).collect::<Vec<_>>()),
+            vec![],
+        )
+    };
+    if value.hasattr("__geo_interface__")? {
+        let json = value
+            .py()
+            .import("json")?
+            .call_method1("dumps", (value.getattr("__geo_interface__")?,))?
+            .extract::<String>()?;
+        let geometry = match json.parse::<GeoJson>() {
+            Ok(GeoJson::Geometry(g)) => geo::Geometry::try_from(&g).ok(),
+            _ => None,
+        };
+        return match geometry {
+            Some(geo::Geometry::Polygon(p)) => Ok(MultiPolygon(vec![p])),
+            Some(geo::Geometry::MultiPolygon(mp)) => Ok(mp),
+            _ => Err(PyValueError::new_err("not a polygon or multi-polygon")),
+        };
+    }
+    if let Ok(points) = value.extract::<Vec<[f64; 2]>>() {
+        return Ok(MultiPolygon(vec![ring(points)]));
+    }
+    if let Ok(parts) = value.extract::<Vec<Vec<[f64; 2]>>>() {
+        return Ok(MultiPolygon(parts.into_iter().map(ring).collect()));
+    }
+    Err(PyTypeError::new_err(
+        "expected a list of points, a list of such lists or a shapely polygon",
+    ))
+}
+
+/// The `keiro` Python module.
+#[pymodule]
+#[pyo3(name = "keiro")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyAgent>()?;
+    m.add_class::<PyAction>()?;
+    m.add_class::<PySchedule>()?;
+    m.add_class::<PyPointST>()?;
+    m.add_class::<PyPath>()?;
+    m.add_class::<PyRouting>()?;
+    m.add_function(wrap_pyfunction!(py_routes, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
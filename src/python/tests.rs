@@ -0,0 +1,50 @@
+use pyo3::{ffi::c_str, prelude::*, types::PyDict, wrap_pymodule};
+
+use crate::python::python_module;
+
+#[test]
+fn test_python_routes() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = PyDict::new(py);
+        globals
+            .set_item("keiro", wrap_pymodule!(python_module)(py))
+            .unwrap();
+        py.run(
+            c_str!(
+                r#"
+class Rail:
+    __geo_interface__ = {
+        "type": "Polygon",
+        "coordinates": [[[0, 0], [100, 0], [100, 10], [0, 10], [0, 0]]],
+    }
+
+crane = keiro.Agent("agent-0", Rail(), (10, 5), (2, 1), 10, 0)
+other = keiro.Agent("agent-1", [(0, 0), (100, 0), (100, 10), (0, 10)], [30, 5], [2, 1], 10, 1)
+assert crane.reach == other.reach
+
+schedule = keiro.Schedule([keiro.Action(other, (40, 5), 20), keiro.Action(crane, (50, 5), 0)])
+routing = keiro.routes([crane, other], schedule)
+assert routing.agents() == ["agent-0", "agent-1"]
+types = [p.type for p in routing.paths("agent-1")]
+assert types == ["Idle", "Scheduled", "Evasive"], types
+path = routing.paths("agent-0")[-1]
+assert (path.target, path.step) == ((50, 5), 1)
+assert routing.points("agent-0")[-1].x == 50
+assert routing.position_at("agent-1", 2.5) == (35, 5)
+samples = routing.sample(5)["agent-1"]
+assert [(p.x, p.t) for p in samples[:3]] == [(30, 0), (40, 5), (40, 10)]
+
+try:
+    keiro.routes([crane], keiro.Schedule([keiro.Action(other, (40, 5), 0)]))
+    raise AssertionError("the agent is unknown")
+except ValueError:
+    pass
+"#
+            ),
+            Some(&globals),
+            None,
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashMap, error::Error};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Response, Server};
+
+use crate::actions::{
+    scenario::{Scenario, ScenarioAction, Solution},
+    validation::ValidationIssue,
+    Routing, Solver,
+};
+
+/// The address the service listens on by default.
+pub const ADDRESS: &str = "127.0.0.1:8080";
+
+/// The answer to a scenario or to a replanning request: the solution with
+/// the session it is stored in, if any.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Answer {
+    /// The session the scenario is stored in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<u64>,
+    /// The solution of the scenario
+    #[serde(flatten)]
+    pub solution: Solution,
+}
+
+impl Answer {
+    fn new(solution: Solution, session: Option<u64>) -> Answer {
+        Answer { session, solution }
+    }
+}
+
+/// A request to replan a session with further actions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replan {
+    /// The actions to execute after those of the session, in the order they
+    /// have to be executed
+    pub actions: Vec<ScenarioAction>,
+}
+
+/// The reply to a request: an HTTP status and a JSON body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reply {
+    /// The HTTP status code
+    pub status: u16,
+    /// The body of the reply
+    pub body: String,
+}
+
+impl Reply {
+    fn json<T: Serialize>(status: u16, value: &T) -> Reply {
+        Reply {
+            status,
+            body: serde_json::to_string(value).unwrap(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Reply {
+        Reply::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+/// A scenario with its routing in solver coordinates, kept to replan.
+struct Session {
+    scenario: Scenario,
+    routing: Routing,
+    /// The validation issues of the scenario when the routing was found
+    issues: Vec<ValidationIssue>,
+}
+
+/// The solver as an HTTP service with a JSON API. Scenarios are posted as
+/// JSON, or YAML as the command line reads them:
+///
+/// - `POST /solve` solves a scenario
+/// - `POST /sessions` solves a scenario and stores it in a new session
+/// - `GET /sessions/{id}` returns the solution of a session, with the
+///   validation issues it was last solved with
+/// - `POST /sessions/{id}/replan` executes further actions after those of
+///   the session, leaving the paths found so far as they are
+/// - `DELETE /sessions/{id}` drops a session
+///
+/// Invalid or unsolvable scenarios are answered with status 422 and the
+/// issues or the error of the solver.
+#[derive(Default)]
+pub struct Service {
+    sessions: HashMap<u64, Session>,
+    next: u64,
+}
+
+impl Service {
+    /// Answer a request, given its method, URL and body.
+    pub fn handle(&mut self, method: &str, url: &str, body: &str) -> Reply {
+        let path = url.split('?').next().unwrap_or_default();
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        match (method, &segments[..]) {
+            ("POST", ["solve"]) => match serde_yaml::from_str::<Scenario>(body) {
+                Ok(scenario) => {
+                    let (solution, routing) = scenario.solve();
+                    let status = if routing.is_some() { 200 } else { 422 };
+                    Reply::json(status, &Answer::new(solution, None))
+                }
+                Err(e) => Reply::error(400, &format!("invalid scenario: {}", e)),
+            },
+            ("POST", ["sessions"]) => match serde_yaml::from_str::<Scenario>(body) {
+                Ok(scenario) => self.open(scenario),
+                Err(e) => Reply::error(400, &format!("invalid scenario: {}", e)),
+            },
+            (_, ["sessions", id, rest @ ..]) => {
+                let Some(id) = id
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|i| self.sessions.contains_key(i))
+                else {
+                    return Reply::error(404, &format!("unknown session '{}'", id));
+                };
+                match (method, rest) {
+                    ("GET", []) => {
+                        let session = &self.sessions[&id];
+                        let solution = session
+                            .scenario
+                            .solution(&session.routing, session.issues.clone());
+                        Reply::json(200, &Answer::new(solution, Some(id)))
+                    }
+                    ("DELETE", []) => {
+                        self.sessions.remove(&id);
+                        Reply {
+                            status: 204,
+                            body: String::new(),
+                        }
+                    }
+                    ("POST", ["replan"]) => match serde_yaml::from_str::<Replan>(body) {
+                        Ok(replan) => self.replan(id, replan),
+                        Err(e) => Reply::error(400, &format!("invalid replanning: {}", e)),
+                    },
+                    _ => Reply::error(404, &format!("no such endpoint: {} {}", method, path)),
+                }
+            }
+            _ => Reply::error(404, &format!("no such endpoint: {} {}", method, path)),
+        }
+    }
+
+    /// Solve the scenario and store it in a new session if a routing is
+    /// found.
+    fn open(&mut self, scenario: Scenario) -> Reply {
+        let (solution, routing) = scenario.solve();
+        let Some(routing) = routing else {
+            return Reply::json(422, &Answer::new(solution, None));
+        };
+        let id = self.next;
+        self.next += 1;
+        self.sessions.insert(
+            id,
+            Session {
+                scenario,
+                routing,
+                issues: solution.issues.clone(),
+            },
+        );
+        Reply::json(201, &Answer::new(solution, Some(id)))
+    }
+
+    /// Replan the session with further actions. Actions without an agent are
+    /// assigned as if the agents are busy with the routing of the session
+    /// first. The session is left as it is if no routing is found.
+    fn replan(&mut self, id: u64, replan: Replan) -> Reply {
+        let session = self.sessions.get_mut(&id).unwrap();
+        let scenario = Scenario {
+            actions: replan.actions.clone(),
+            ..session.scenario.clone()
+        };
+        let (prepared, issues) = scenario.prepare_after(Some(&session.routing));
+        let Some((s, schedule)) = prepared else {
+            return Reply::json(422, &Answer::new(Solution::failed(issues, None), Some(id)));
+        };
+        let solver = Solver {
+            site: s.site,
+            config: s.config,
+            trace: false,
+        };
+        match solver.replan(&session.routing, schedule) {
+            Ok(routing) => {
+                session.scenario.actions.extend(replan.actions);
+                session.routing = routing;
+                session.issues = issues.clone();
+                let solution = session.scenario.solution(&session.routing, issues);
+                Reply::json(200, &Answer::new(solution, Some(id)))
+            }
+            Err(e) => {
+                let solution = Solution::failed(issues, Some(e));
+                Reply::json(422, &Answer::new(solution, Some(id)))
+            }
+        }
+    }
+
+    /// Answer the requests to the server, one at a time, until it shuts
+    /// down.
+    pub fn run(&mut self, server: &Server) {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            let reply = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+                Err(e) => Reply::error(400, &format!("cannot read request: {}", e)),
+            };
+            let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+            let response = Response::from_string(reply.body)
+                .with_status_code(reply.status)
+                .with_header(header);
+            // A client that hung up misses its reply, the service goes on.
+            let _ = request.respond(response);
+        }
+    }
+}
+
+/// Serve the API on the address, e.g. `127.0.0.1:8080`.
+pub fn serve(address: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http(address)?;
+    Service::default().run(&server);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,204 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use geo::Coord;
+use tiny_http::Server;
+
+use crate::{
+    actions::{
+        data::ActionType,
+        fixtures::agent,
+        scenario::{Scenario, ScenarioAction},
+        validation::IssueKind,
+    },
+    service::{Answer, Replan, Service},
+};
+
+fn action(agent: &str, x: f64, duration: f64) -> ScenarioAction {
+    ScenarioAction {
+        agent: Some(String::from(agent)),
+        target: Coord { x, y: 5.0 },
+        duration,
+        requires: vec![],
+        std_dev: 0.0,
+    }
+}
+
+fn scenario() -> String {
+    let scenario = Scenario {
+        agents: vec![agent("agent-0", 10.0, 0), agent("agent-1", 30.0, 1)],
+        actions: vec![action("agent-1", 40.0, 20.0)],
+        ..Scenario::default()
+    };
+    serde_json::to_string(&scenario).unwrap()
+}
+
+fn replan(actions: Vec<ScenarioAction>) -> String {
+    serde_json::to_string(&Replan { actions }).unwrap()
+}
+
+#[test]
+fn test_service_sessions() {
+    let mut service = Service::default();
+
+    let reply = service.handle("POST", "/solve", &scenario());
+    assert_eq!(reply.status, 200);
+    let answer = serde_json::from_str::<Answer>(&reply.body).unwrap();
+    assert_eq!(answer.session, None);
+    assert_eq!(answer.solution.metrics.unwrap().makespan, 25.0);
+
+    let reply = service.handle("POST", "/sessions", &scenario());
+    assert_eq!(reply.status, 201);
+    let id = serde_json::from_str::<Answer>(&reply.body)
+        .unwrap()
+        .session
+        .unwrap();
+
+    // The crane has to wait for the other agent, which evades once it is
+    // done, while the paths found before stay as they are.
+    let reply = service.handle(
+        "POST",
+        &format!("/sessions/{}/replan", id),
+        &replan(vec![action("agent-0", 50.0, 0.0)]),
+    );
+    assert_eq!(reply.status, 200);
+    let routing = serde_json::from_str::<Answer>(&reply.body)
+        .unwrap()
+        .solution
+        .routing
+        .unwrap();
+    let (crane, other) = (&routing.routes[0].1, &routing.routes[1].1);
+    assert_eq!(other[1].t_end, 25.0);
+    assert_eq!(other[2].action.r#type, ActionType::Evasive);
+    assert_eq!(crane.last().unwrap().step, Some(1));
+
+    let reply = service.handle("GET", &format!("/sessions/{}", id), "");
+    let answer = serde_json::from_str::<Answer>(&reply.body).unwrap();
+    assert_eq!(answer.session, Some(id));
+    assert_eq!(
+        answer.solution.routing.unwrap().routes[0].1.len(),
+        crane.len()
+    );
+
+    // A failed replanning leaves the session as it is.
+    let reply = service.handle(
+        "POST",
+        &format!("/sessions/{}/replan", id),
+        &replan(vec![action("agent-2", 50.0, 0.0)]),
+    );
+    assert_eq!(reply.status, 422);
+    let answer = serde_json::from_str::<Answer>(&reply.body).unwrap();
+    assert_eq!(answer.session, Some(id));
+    assert!(answer.solution.routing.is_none());
+    assert_eq!(answer.solution.issues[0].kind, IssueKind::UnknownAgent);
+
+    assert_eq!(
+        service
+            .handle("DELETE", &format!("/sessions/{}", id), "")
+            .status,
+        204
+    );
+    assert_eq!(
+        service
+            .handle("GET", &format!("/sessions/{}", id), "")
+            .status,
+        404
+    );
+    assert_eq!(service.handle("POST", "/solve", "agents: 1").status, 400);
+    assert_eq!(service.handle("GET", "/solve", "").status, 404);
+}
+
+#[test]
+fn test_service_session_issues() {
+    // The third agent shares its order, which is only a warning.
+    let scenario = Scenario {
+        agents: vec![
+            agent("agent-0", 10.0, 0),
+            agent("agent-1", 30.0, 1),
+            agent("agent-2", 90.0, 1),
+        ],
+        actions: vec![action("agent-1", 40.0, 20.0)],
+        ..Scenario::default()
+    };
+    let mut service = Service::default();
+    let reply = service.handle(
+        "POST",
+        "/sessions",
+        &serde_json::to_string(&scenario).unwrap(),
+    );
+    assert_eq!(reply.status, 201);
+    let answer = serde_json::from_str::<Answer>(&reply.body).unwrap();
+    let id = answer.session.unwrap();
+    let issues = answer.solution.issues;
+    assert_eq!(issues[0].kind, IssueKind::DuplicateOrder);
+
+    let reply = service.handle("GET", &format!("/sessions/{}", id), "");
+    let answer = serde_json::from_str::<Answer>(&reply.body).unwrap();
+    assert_eq!(answer.solution.issues, issues);
+}
+
+#[test]
+fn test_service_replan_assignment() {
+    let scenario = Scenario {
+        agents: vec![agent("agent-0", 10.0, 0), agent("agent-1", 90.0, 1)],
+        actions: vec![action("agent-0", 20.0, 100.0)],
+        ..Scenario::default()
+    };
+    let mut service = Service::default();
+    let reply = service.handle(
+        "POST",
+        "/sessions",
+        &serde_json::to_string(&scenario).unwrap(),
+    );
+    let id = serde_json::from_str::<Answer>(&reply.body)
+        .unwrap()
+        .session
+        .unwrap();
+
+    // The nearby agent is busy with the action of the session, so the other
+    // one completes the further action first.
+    let reply = service.handle(
+        "POST",
+        &format!("/sessions/{}/replan", id),
+        &replan(vec![ScenarioAction {
+            agent: None,
+            ..action("", 40.0, 30.0)
+        }]),
+    );
+    assert_eq!(reply.status, 200);
+    let routing = serde_json::from_str::<Answer>(&reply.body)
+        .unwrap()
+        .solution
+        .routing
+        .unwrap();
+    assert_eq!(routing.routes[0].1.last().unwrap().step, Some(0));
+    assert_eq!(routing.routes[1].1.last().unwrap().step, Some(1));
+}
+
+#[test]
+fn test_service_http() {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let address = server.server_addr().to_ip().unwrap();
+    std::thread::spawn(move || Service::default().run(&server));
+
+    let body = scenario();
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /solve HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        address,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    let answer = serde_json::from_str::<Answer>(body).unwrap();
+    assert_eq!(answer.solution.routing.unwrap().routes.len(), 2);
+}
@@ -0,0 +1,66 @@
+//! WebAssembly bindings for planning in the browser. Scenarios go in and
+//! solutions come out as JSON, nothing touches the file system.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::actions::{
+    sampling::Trajectory,
+    scenario::{Scenario, Solution},
+};
+
+/// A solution with the trajectories of the agents.
+#[derive(Serialize)]
+struct Planned {
+    #[serde(flatten)]
+    solution: Solution,
+    trajectories: Vec<Trajectory>,
+}
+
+/// Solve a scenario given as JSON. Returns the solution as the service
+/// answers it, with the trajectories of the agents sampled every `dt`, or
+/// at the vertices of their paths if no interval is given.
+#[wasm_bindgen]
+pub fn solve(scenario: &str, dt: Option<f64>) -> Result<String, JsError> {
+    solve_json(scenario, dt).map_err(|e| JsError::new(&e))
+}
+
+/// Validate a scenario given as JSON. Returns the issues found.
+#[wasm_bindgen]
+pub fn validate(scenario: &str) -> Result<String, JsError> {
+    validate_json(scenario).map_err(|e| JsError::new(&e))
+}
+
+fn solve_json(scenario: &str, dt: Option<f64>) -> Result<String, String> {
+    if dt.is_some_and(|dt| dt.is_nan() || dt <= 0.0) {
+        return Err(String::from("the sampling interval must be positive"));
+    }
+    let scenario = serde_json::from_str::<Scenario>(scenario).map_err(|e| e.to_string())?;
+    let (solution, _) = scenario.solve();
+    let trajectories = match (&solution.routing, dt) {
+        (Some(r), Some(dt)) => r.sample(dt),
+        (Some(r), None) => r
+            .routes
+            .iter()
+            .map(|(a, ps)| Trajectory {
+                agent: a.name.clone(),
+                points: ps.iter().flat_map(|p| p.to_points_st()).collect(),
+            })
+            .collect(),
+        (None, _) => vec![],
+    };
+    let planned = Planned {
+        solution,
+        trajectories,
+    };
+    Ok(serde_json::to_string(&planned).unwrap())
+}
+
+fn validate_json(scenario: &str) -> Result<String, String> {
+    let scenario = serde_json::from_str::<Scenario>(scenario).map_err(|e| e.to_string())?;
+    let (_, issues) = scenario.prepare();
+    Ok(serde_json::to_string(&issues).unwrap())
+}
+
+#[cfg(test)]
+mod tests;
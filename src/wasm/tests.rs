@@ -0,0 +1,47 @@
+use serde_json::Value;
+
+use crate::wasm::{solve_json, validate_json};
+
+const SCENARIO: &str = r#"{
+    "agents": [
+        {
+            "name": "crane",
+            "reach": {
+                "exterior": [
+                    {"x": 0, "y": 0}, {"x": 100, "y": 0}, {"x": 100, "y": 10}, {"x": 0, "y": 10}
+                ],
+                "interiors": []
+            },
+            "position": {"x": 10, "y": 5},
+            "velocity": {"x": 2, "y": 1},
+            "safety_x": 10,
+            "order": 0
+        }
+    ],
+    "actions": [{"agent": "crane", "target": {"x": 50, "y": 5}, "duration": 5}]
+}"#;
+
+#[test]
+fn test_wasm_solve() {
+    let planned = serde_json::from_str::<Value>(&solve_json(SCENARIO, None).unwrap()).unwrap();
+    assert_eq!(planned["metrics"]["makespan"], 25.0);
+    let points = planned["trajectories"][0]["points"].as_array().unwrap();
+    assert_eq!(planned["trajectories"][0]["agent"], "crane");
+    assert_eq!(points.last().unwrap()["x"], 50.0);
+
+    let unknown = SCENARIO.replace(r#""agent": "crane""#, r#""agent": "gantry""#);
+    let issues = serde_json::from_str::<Value>(&validate_json(&unknown).unwrap()).unwrap();
+    assert_eq!(issues[0]["kind"], "UnknownAgent");
+    let planned = serde_json::from_str::<Value>(&solve_json(&unknown, None).unwrap()).unwrap();
+    assert!(planned["routing"].is_null());
+    assert!(solve_json("{", None).is_err());
+    assert!(solve_json(SCENARIO, Some(0.0)).is_err());
+
+    let sampled = serde_json::from_str::<Value>(&solve_json(SCENARIO, Some(1.0)).unwrap()).unwrap();
+    let points = sampled["trajectories"][0]["points"].as_array().unwrap();
+    assert_eq!(points.len(), 26);
+    assert_eq!(
+        (&points[10]["x"], &points[10]["t"]),
+        (&30.0.into(), &10.0.into())
+    );
+}
@@ -1,8 +1,22 @@
-use geo::{BooleanOps, BoundingRect, Coord, LineString, Polygon};
+use geo::{BoundingRect, Coord, LineString, MultiPolygon, Polygon, Rect};
 use itertools::Itertools;
 use keiro::actions::{
-    data::{Action, ActionType, Agent, ConstVel2D, PointST, Schedule},
+    commands::{Command, TimedCommand},
+    config::SolverConfig,
+    data::{
+        Action, ActionType, Agent, Axis, ConstVel2D, Motion, Parking, Planner, PointST, Schedule,
+        Unavailability,
+    },
+    fixtures,
+    precedence::Executor,
     routes,
+    scenario::{Scenario, ScenarioAction},
+    simulation::{Execution, SimulationConfig, Spread},
+    site::{Obstacle, RoadEdge, RoadNetwork, RoadNode, Site, TimeWindow},
+    trace::{Blocking, ConflictResolution, Decision, TraceEvent},
+    units::{LengthUnit, TimeUnit, Transform, Units},
+    validation::IssueKind,
+    RoutingError, Solver,
 };
 use proptest::prelude::*;
 
@@ -17,6 +31,8 @@ fn arb_action(agents: Vec<Agent>) -> impl Strategy<Value = Action> {
             },
             duration: f64::from(d),
             r#type: ActionType::Scheduled,
+            requires: vec![],
+            std_dev: 0.0,
         }
     })
 }
@@ -24,15 +40,6 @@ fn arb_action(agents: Vec<Agent>) -> impl Strategy<Value = Action> {
 fn arb_schedule() -> impl Strategy<Value = (Vec<Agent>, Schedule)> {
     let (x_min, x_max) = (0.0, 200.0);
     let (y_min, y_max) = (0.0, 50.0);
-    let yard = Polygon::new(
-        LineString::from(vec![
-            (x_min, y_min),
-            (x_max, y_min),
-            (x_max, y_max),
-            (x_min, y_max),
-        ]),
-        vec![],
-    );
     let num_agents = 3;
     let safe_dists = proptest::collection::vec(10.0..20.0, num_agents);
     let agents_st = safe_dists
@@ -46,48 +53,46 @@ fn arb_schedule() -> impl Strategy<Value = (Vec<Agent>, Schedule)> {
             v_rev.reverse();
             let mut sds_acc_r = vec![x_max];
             for i in 0..(v_rev.len() - 1) {
-                sds_acc_r.push(sds_acc_r.last().unwrap() - f64::max(v[i], v[i + 1]));
+                sds_acc_r.push(sds_acc_r.last().unwrap() - f64::max(v_rev[i], v_rev[i + 1]));
             }
             sds_acc_r.reverse();
 
             let mut agents = vec![];
             for i in 0..v.len() {
-                let reach = yard
-                    .difference(&Polygon::new(
-                        LineString::from(vec![
-                            (x_min, y_min),
-                            (x_min + sds_acc_l[i], y_min),
-                            (x_min + sds_acc_l[i], y_max),
-                            (x_min, y_max),
-                        ]),
-                        vec![],
-                    ))
-                    .difference(&Polygon::new(
-                        LineString::from(vec![
-                            (sds_acc_r[i], y_min),
-                            (x_max, y_min),
-                            (x_max, y_max),
-                            (sds_acc_r[i], y_max),
-                        ]),
-                        vec![],
-                    ));
+                // The reach is built from the safety distances directly, as
+                // boolean operations round its edges off.
+                let reach = Polygon::new(
+                    LineString::from(vec![
+                        (x_min + sds_acc_l[i], y_min),
+                        (sds_acc_r[i], y_min),
+                        (sds_acc_r[i], y_max),
+                        (x_min + sds_acc_l[i], y_max),
+                    ]),
+                    vec![],
+                )
+                .into();
                 agents.push(Agent {
                     name: format!("agent-{}", i),
+                    corridors: MultiPolygon(vec![]),
                     position: Coord {
                         x: sds_acc_l[i],
                         y: 10.0,
                     },
                     velocity: ConstVel2D { x: 2.0, y: 1.0 },
+                    motion: Motion::Simultaneous,
                     safety_x: v[i],
                     order: i as i64,
-                    reach: reach.0[0].clone(),
+                    capabilities: vec![],
+                    unavailable: vec![],
+                    planner: Planner::Visibility,
+                    reach,
                 });
             }
             agents
         })
         .boxed();
 
-    let action_st = agents_st
+    agents_st
         .clone()
         .prop_flat_map(|ags| {
             (
@@ -96,15 +101,72 @@ fn arb_schedule() -> impl Strategy<Value = (Vec<Agent>, Schedule)> {
                     .prop_map(|v| Schedule { actions: v }),
             )
         })
-        .boxed();
-    action_st
+        .boxed()
+}
+
+/// Agents of the schedule with a grid or visibility planner each, amid
+/// obstacles that span the middle of the yard along the y-axis, some of
+/// them closed for a while. The targets keep clear of the obstacles, so
+/// agents evade along the x-axis past them, and a cell away from the top
+/// and bottom of the yard, as grids do not reach into corners narrower
+/// than a cell.
+fn arb_obstacle_schedule() -> impl Strategy<Value = (Vec<Agent>, Schedule, Site)> {
+    let cells = proptest::collection::vec(proptest::option::of(1.0..5.0), 3);
+    let obstacles =
+        proptest::collection::vec((0.0..190.0, 1.0..10.0, proptest::option::of(0..20)), 1..4);
+    (arb_schedule(), cells, obstacles).prop_map(|((agents, schedule), cells, obstacles)| {
+        let agents = agents
+            .into_iter()
+            .zip(cells)
+            .map(|(a, cell)| Agent {
+                planner: cell.map_or(Planner::Visibility, |cell| Planner::Grid { cell }),
+                ..a
+            })
+            .collect::<Vec<_>>();
+        let actions = schedule
+            .actions
+            .into_iter()
+            .take(20)
+            .map(|a| {
+                let y = 5.0 + a.target.y * 0.3;
+                Action {
+                    agent: agents
+                        .iter()
+                        .find(|b| b.name == a.agent.name)
+                        .unwrap()
+                        .clone(),
+                    target: Coord {
+                        x: a.target.x,
+                        y: if y < 12.5 { y } else { y + 25.0 },
+                    },
+                    ..a
+                }
+            })
+            .collect();
+        let site = Site {
+            obstacles: obstacles
+                .into_iter()
+                .enumerate()
+                .map(|(i, (x, width, closure))| Obstacle {
+                    name: format!("obstacle-{}", i),
+                    area: Rect::new((x, 20.0), (x + width, 30.0)).to_polygon(),
+                    window: closure.map(|k| TimeWindow {
+                        start: f64::from(k) * 20.0,
+                        end: f64::from(k) * 20.0 + 50.0,
+                    }),
+                })
+                .collect(),
+            ..Site::default()
+        };
+        (agents, Schedule { actions }, site)
+    })
 }
 
 proptest! {
     #[test]
     fn test_safety_distances((agents, schedule) in arb_schedule()) {
         // run
-        let actual = routes(&agents, schedule);
+        let actual = routes(&agents, schedule).unwrap();
 
         // assert
         let agent_paths = actual.routes.iter()
@@ -112,16 +174,18 @@ proptest! {
             .map(|(n, paths)| (n, paths.iter().map(|p| (p.to_points_st(), p.action.r#type.clone())).collect::<Vec<(Vec<PointST>, ActionType)>>()))
             .collect::<Vec<(&Agent, Vec<(Vec<PointST>, ActionType)>)>>();
 
-        // serialize for plots
-        let ser_paths = agent_paths.clone();
-        let str = serde_yaml::to_string(&ser_paths).unwrap();
-        let _ = std::fs::write("/home/hendrik/log/paths.yml", str);
+        // serialize for plots, if asked for
+        if let Some(dir) = std::env::var_os("KEIRO_PLOT_DIR") {
+            let str = serde_yaml::to_string(&agent_paths).unwrap();
+            let _ = std::fs::write(std::path::Path::new(&dir).join("paths.yml"), str);
+        }
 
         // safety distances
+        let config = SolverConfig::default();
         for (t1, t2) in agent_paths.iter().tuple_windows() {
                 let (a1, p1) = t1;
                 let (a2, p2) = t2;
-                let sd = f64::max(a1.safety_x, a2.safety_x);
+                let sd = config.safety_distance(a1, a2);
                 let pts1 = p1.iter().flat_map(|p| p.0.clone()).collect::<Vec<PointST>>();
                 let pts2 = p2.iter().flat_map(|p| p.0.clone()).collect::<Vec<PointST>>();
 
@@ -129,23 +193,578 @@ proptest! {
                 all_first_points_outside_sd(a2, &pts2, a1, &pts1, sd);
         }
     }
+
+    #[test]
+    fn test_plan_graph_execution((agents, schedule) in arb_schedule()) {
+        let actual = routes(&agents, schedule).unwrap();
+
+        // the plan graph executes to the end, whatever order the released
+        // commands complete in
+        let config = SolverConfig::default();
+        let mut executor = Executor::new(actual.plan_graph(&config).unwrap());
+        while let Some(c) = executor.released().last().map(|c| (c.agent.clone(), c.seq)) {
+            executor.complete(&c.0, c.1).unwrap();
+        }
+        prop_assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn test_exact_simulation((agents, schedule) in arb_schedule()) {
+        let actual = routes(&agents, schedule).unwrap();
+
+        // executed as planned, on time or by events, the agents keep their
+        // distance, and on time they finish in time
+        for execution in [Execution::Timed, Execution::Events] {
+            let simulation = SimulationConfig { execution, runs: 1, ..Default::default() };
+            let report = actual.simulate(&SolverConfig::default(), &simulation).unwrap();
+            prop_assert!(report.runs[0].violations.is_empty(), "{:?}", report.runs[0].violations);
+            if execution == Execution::Timed {
+                prop_assert!(report.makespan.max <= report.planned_makespan + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_execution((agents, schedule) in arb_schedule()) {
+        let actual = routes(&agents, schedule).unwrap();
+
+        // executed by events, the agents keep their distance whatever the
+        // durations and velocities
+        let simulation = SimulationConfig {
+            durations: Spread::Uniform { min: 0.5, max: 2.0 },
+            velocities: Spread::Uniform { min: 0.5, max: 1.5 },
+            execution: Execution::Events,
+            runs: 5,
+            ..Default::default()
+        };
+        let report = actual.simulate(&SolverConfig::default(), &simulation).unwrap();
+        prop_assert_eq!(report.violation_rate, 0.0);
+    }
+
+    #[test]
+    fn test_command_streams((agents, schedule) in arb_schedule()) {
+        let actual = routes(&agents, schedule).unwrap();
+
+        // command streams are contiguous, and wait for commands planned to
+        // be completed before, or for the moves of the agents they follow
+        let config = SolverConfig::default();
+        let commands = actual.commands(&config);
+        for (_, cs) in commands.iter().chunk_by(|c| c.agent.clone()).into_iter() {
+            for (c1, c2) in cs.tuple_windows() {
+                prop_assert!(c2.seq == c1.seq + 1 && f64::abs(c2.t_start - c1.t_end) < 1e-9);
+            }
+        }
+        let moves = |c: &TimedCommand| {
+            matches!(c.command, Command::MoveTo { .. } | Command::EvadeTo { .. })
+        };
+        for c in commands.iter() {
+            for s in c.after.iter() {
+                let other = commands.iter().find(|o| o.agent == s.agent && o.seq == s.seq).unwrap();
+                prop_assert!(
+                    other.t_end <= c.t_start + config.tolerance
+                        || (moves(c) && moves(other))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sampled_positions((agents, schedule) in arb_schedule()) {
+        let actual = routes(&agents, schedule).unwrap();
+
+        // sampled positions agree with the interpolation between the vertices
+        let agent_pts = actual.routes.iter()
+            .map(|(a, paths)| (a, paths.iter().flat_map(|p| p.to_points_st()).collect::<Vec<PointST>>()))
+            .collect::<Vec<_>>();
+        let all_pts = agent_pts.iter().flat_map(|(_, pts)| pts.clone()).collect::<Vec<PointST>>();
+        for (a, pts) in agent_pts.iter() {
+            for q in all_pts.iter() {
+                if let Some(c) = interpolate(q, a, pts) {
+                    let actual = actual.position_at(&a.name, q.t).unwrap();
+                    prop_assert!(f64::abs(actual.x - c.x) < 1e-9 && f64::abs(actual.y - c.y) < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_obstacle_distances((agents, schedule, site) in arb_obstacle_schedule()) {
+        let solver = Solver { site, ..Solver::default() };
+        let actual = solver.routes(&agents, schedule).unwrap();
+
+        // the agents keep their distance at the end of every segment, as
+        // they move around the obstacles on paths of many segments
+        let config = SolverConfig::default();
+        let agent_pts = actual.routes.iter()
+            .sorted_by_key(|(a, _)| a.order)
+            .map(|(a, paths)| (a, paths.iter().flat_map(|p| p.to_points_st()).collect::<Vec<PointST>>()))
+            .collect::<Vec<_>>();
+        for ((a1, pts1), (a2, pts2)) in agent_pts.iter().tuple_windows() {
+            let sd = config.safety_distance(a1, a2);
+            all_first_points_outside_sd(a1, pts1, a2, pts2, sd);
+            all_first_points_outside_sd(a2, pts2, a1, pts1, sd);
+        }
+        let simulation = SimulationConfig { execution: Execution::Timed, runs: 1, ..Default::default() };
+        let report = actual.simulate(&config, &simulation).unwrap();
+        prop_assert!(report.runs[0].violations.is_empty(), "{:?}", report.runs[0].violations);
+    }
+}
+
+#[test]
+fn test_missing_capabilities() {
+    let mut agent = fixtures::agent("crane", 10.0, 0);
+    agent.capabilities = vec![String::from("spreader-20ft")];
+    let action = |requires: Vec<&str>| Action {
+        requires: requires.into_iter().map(String::from).collect(),
+        ..fixtures::action(&agent, 50.0, 5.0)
+    };
+    let schedule = Schedule {
+        actions: vec![action(vec!["spreader-20ft"]), action(vec!["spreader-40ft"])],
+    };
+
+    let actual = routes(&[agent.clone()], schedule);
+
+    let Err(RoutingError::Invalid(issues)) = actual else {
+        panic!("expected the schedule to be rejected");
+    };
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::MissingCapabilities);
+    assert_eq!(issues[0].action, Some(1));
+    assert_eq!(issues[0].agent.as_deref(), Some("crane"));
+    assert!(issues[0].message.contains("spreader-40ft"));
+}
+
+#[test]
+fn test_automatic_assignment() {
+    let agent = |name: &str, x: f64, order: i64, capability: &str| Agent {
+        capabilities: vec![String::from(capability)],
+        ..fixtures::agent(name, x, order)
+    };
+    let action = |x: f64, requires: &[&str]| ScenarioAction {
+        agent: None,
+        target: Coord { x, y: 5.0 },
+        duration: 5.0,
+        requires: requires.iter().map(|r| String::from(*r)).collect(),
+        std_dev: 0.0,
+    };
+    let mut scenario = Scenario {
+        agents: vec![
+            agent("crane", 10.0, 0, "spreader-20ft"),
+            agent("gantry", 90.0, 1, "spreader-40ft"),
+        ],
+        // The crane is closest to the first target, and the only one able to
+        // execute the second, while only the gantry can execute the third.
+        actions: vec![
+            action(20.0, &[]),
+            action(80.0, &["spreader-20ft"]),
+            action(30.0, &["spreader-40ft"]),
+        ],
+        ..Scenario::default()
+    };
+
+    let schedule = scenario.schedule().unwrap();
+    assert_eq!(
+        schedule
+            .actions
+            .iter()
+            .map(|a| a.agent.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["crane", "crane", "gantry"]
+    );
+
+    scenario.actions.push(action(50.0, &["lift"]));
+    let issues = scenario.schedule().unwrap_err();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::NoCapableAgent);
+    assert_eq!(issues[0].action, Some(3));
+}
+
+#[test]
+fn test_closure_blocks_path() {
+    let agent = fixtures::agent("agv", 10.0, 0);
+    let site = Site {
+        obstacles: vec![Obstacle {
+            name: String::from("maintenance"),
+            area: Polygon::new(
+                LineString::from(vec![(40.0, 0.0), (60.0, 0.0), (60.0, 10.0), (40.0, 10.0)]),
+                vec![],
+            ),
+            window: Some(TimeWindow {
+                start: 10.0,
+                end: 20.0,
+            }),
+        }],
+        ..Site::default()
+    };
+    let schedule = Schedule {
+        actions: vec![fixtures::action(&agent, 90.0, 5.0)],
+    };
+
+    let actual = Solver {
+        site,
+        ..Solver::default()
+    }
+    .routes(&[agent], schedule)
+    .unwrap();
+
+    let paths = &actual.routes[0].1;
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[1].action.r#type, ActionType::Idle);
+    assert_eq!(paths[1].t_end, 20.0);
+    assert_eq!(paths[2].t_start, 20.0);
+    assert_eq!(paths[2].t_end, 20.0 + 40.0 + 5.0);
+}
+
+#[test]
+fn test_unavailable_agent_is_parked() {
+    let mut crane = fixtures::agent("agent-1", 80.0, 1);
+    crane.unavailable = vec![Unavailability {
+        window: TimeWindow {
+            start: 10.0,
+            end: 60.0,
+        },
+        parking: Parking::Position(Coord { x: 90.0, y: 5.0 }),
+    }];
+    let agents = vec![fixtures::agent("agent-0", 10.0, 0), crane];
+    let schedule = Schedule {
+        actions: vec![
+            fixtures::action(&agents[1], 50.0, 5.0),
+            fixtures::action(&agents[0], 85.0, 5.0),
+        ],
+    };
+
+    let actual = routes(&agents, schedule).unwrap();
+
+    let crane_paths = &actual.routes[1].1;
+    let parked = crane_paths
+        .iter()
+        .find(|p| p.action.r#type == ActionType::Parked)
+        .unwrap();
+    assert!(parked.t_end - parked.action.duration <= 10.0);
+    assert_eq!(parked.t_end, 60.0);
+    assert_eq!(parked.action.target, Coord { x: 90.0, y: 5.0 });
+    assert!(crane_paths
+        .iter()
+        .filter(|p| p.action.r#type != ActionType::Parked)
+        .all(|p| p.t_end <= 10.0 || p.t_start >= 60.0 || p.moves.is_empty()));
+    let scheduled = crane_paths
+        .iter()
+        .find(|p| p.action.r#type == ActionType::Scheduled)
+        .unwrap();
+    assert_eq!(scheduled.t_start, 60.0);
+    let other = actual.routes[0]
+        .1
+        .iter()
+        .find(|p| p.action.r#type == ActionType::Scheduled)
+        .unwrap();
+    assert!(other.t_start >= 60.0);
+}
+
+/// A gantry moving y first towards another agent that works at x = 35
+/// until 22.5.
+fn gantry_schedule() -> (Vec<Agent>, Schedule) {
+    let mut gantry = fixtures::agent("agent-0", 10.0, 0);
+    gantry.motion = Motion::AxisAligned { first: Axis::Y };
+    let other = fixtures::agent("agent-1", 30.0, 1);
+    let action = |agent: &Agent, x: f64, y: f64, duration: f64| Action {
+        target: Coord { x, y },
+        ..fixtures::action(agent, x, duration)
+    };
+    let schedule = Schedule {
+        actions: vec![
+            action(&other, 35.0, 5.0, 20.0),
+            action(&other, 90.0, 5.0, 0.0),
+            action(&gantry, 60.0, 9.5, 0.0),
+        ],
+    };
+    (vec![gantry, other], schedule)
 }
 
-fn all_first_points_outside_sd(
-    a1: &&Agent,
-    p1: &Vec<PointST>,
-    a2: &&Agent,
-    p2: &Vec<PointST>,
-    sd: f64,
-) -> () {
+#[test]
+fn test_axis_aligned_motion() {
+    let (agents, schedule) = gantry_schedule();
+
+    let actual = routes(&agents, schedule).unwrap();
+
+    // The gantry may depart while the other agent still works, as it moves
+    // along y before it closes in along x.
+    let paths = &actual.routes[0].1;
+    assert_eq!(paths[1].action.r#type, ActionType::Idle);
+    assert_eq!(paths[1].t_end, 11.0);
+    let corner = Coord { x: 10.0, y: 9.5 };
+    assert_eq!(
+        paths[2].moves.iter().map(|s| s.end).collect::<Vec<_>>(),
+        vec![corner, Coord { x: 60.0, y: 9.5 }]
+    );
+    assert_eq!(paths[2].t_end, 11.0 + 4.5 + 25.0);
+}
+
+#[test]
+fn test_idle_config() {
+    // The gantry departs once the other agent is a safety distance and the
+    // margin away from where the gantry closes in along x, at 10.5 without
+    // a margin.
+    for (quantum, margin, departure) in [
+        (0.0, 0.0, 10.5),
+        (0.25, 0.0, 10.5),
+        (2.0, 0.0, 12.0),
+        (0.0, 2.0, 11.5),
+    ] {
+        let (agents, schedule) = gantry_schedule();
+        let solver = Solver {
+            config: SolverConfig {
+                quantum,
+                margin,
+                ..SolverConfig::default()
+            },
+            ..Solver::default()
+        };
+
+        let actual = solver.routes(&agents, schedule).unwrap();
+
+        let paths = &actual.routes[0].1;
+        assert_eq!(paths[1].action.r#type, ActionType::Idle);
+        assert_eq!(paths[1].t_end, departure);
+        assert_eq!(paths[2].t_end, departure + 4.5 + 25.0);
+    }
+}
+
+#[test]
+fn test_reaction_time() {
+    // The crane and the other agent of the trace, which take a second to
+    // react.
+    let crane = fixtures::agent("agent-0", 10.0, 0);
+    let other = fixtures::agent("agent-1", 30.0, 1);
+    let schedule = Schedule {
+        actions: vec![
+            fixtures::action(&other, 40.0, 20.0),
+            fixtures::action(&crane, 50.0, 0.0),
+        ],
+    };
+    let solver = Solver {
+        config: SolverConfig {
+            reaction_time: 1.0,
+            ..SolverConfig::default()
+        },
+        trace: true,
+        ..Solver::default()
+    };
+
+    let actual = solver.routes(&[crane, other], schedule).unwrap();
+
+    // The other agent evades to where it stays clear of the crane by what
+    // the crane covers in a second on top of the safety distance, and the
+    // crane follows it as both move by what both cover.
+    let events = actual.trace.unwrap().events;
+    assert_eq!(
+        events[0].decision,
+        Decision::Conflict {
+            blocking: String::from("agent-1"),
+            position: Coord { x: 40.0, y: 5.0 },
+            distance: 12.0,
+            resolution: ConflictResolution::HigherThanX(62.0),
+        }
+    );
+    assert_eq!(
+        events[2].decision,
+        Decision::Wait {
+            blocking: Some(Blocking {
+                agent: String::from("agent-1"),
+                position: Coord { x: 40.0, y: 5.0 },
+                t: 25.0,
+                distance: 14.0,
+            }),
+            until: 17.0,
+        }
+    );
+}
+
+#[test]
+fn test_scenario_units() {
+    // The gantry schedule in millimetres and milliseconds
+    let units = Units {
+        length: LengthUnit::Millimetre,
+        time: TimeUnit::Millisecond,
+    };
+    let (agents, schedule) = gantry_schedule();
+    let to_scenario = units.to_si().inverse();
+    let scenario = Scenario {
+        agents: agents.iter().map(|a| to_scenario.agent(a)).collect(),
+        actions: schedule
+            .actions
+            .iter()
+            .map(|a| ScenarioAction {
+                agent: Some(a.agent.name.clone()),
+                target: a.target * 1000.0,
+                duration: a.duration * 1000.0,
+                requires: vec![],
+                std_dev: a.std_dev * 1000.0,
+            })
+            .collect(),
+        units,
+        config: SolverConfig {
+            quantum: 250.0,
+            ..SolverConfig::default()
+        },
+        ..Scenario::default()
+    };
+
+    let solver_scenario = scenario.in_solver_coordinates();
+    let routing = Solver {
+        config: solver_scenario.config,
+        ..Solver::default()
+    }
+    .routes(&solver_scenario.agents, solver_scenario.schedule().unwrap())
+    .unwrap();
+    let actual = scenario.in_scenario_coordinates(&routing);
+
+    let (gantry, paths) = &actual.routes[0];
+    assert!((gantry.velocity.x - scenario.agents[0].velocity.x).abs() < 1e-9);
+    assert_eq!(paths[1].action.r#type, ActionType::Idle);
+    assert!((paths[1].t_end - 10500.0).abs() < 1e-6);
+    assert!((paths[2].t_end - (10500.0 + 4500.0 + 25000.0)).abs() < 1e-6);
+}
+
+fn road_site() -> Site {
+    let node = |name: &str, x: f64, y: f64| RoadNode {
+        name: String::from(name),
+        position: Coord { x, y },
+    };
+    let road = |from: &str, to: &str| RoadEdge {
+        from: String::from(from),
+        to: String::from(to),
+        speed: 1.0,
+        capacity: 1,
+    };
+    Site {
+        roads: RoadNetwork {
+            nodes: vec![
+                node("a", 0.0, 0.0),
+                node("b", 10.0, 0.0),
+                node("c", 20.0, 0.0),
+                node("d", 10.0, 10.0),
+            ],
+            edges: [("a", "b"), ("b", "c"), ("b", "d")]
+                .into_iter()
+                .flat_map(|(n1, n2)| [road(n1, n2), road(n2, n1)])
+                .collect(),
+            clearance: 1.0,
+        },
+        ..Site::default()
+    }
+}
+
+fn road_agent(name: &str, x: f64, order: i64) -> Agent {
+    Agent {
+        reach: MultiPolygon(vec![]),
+        position: Coord { x, y: 0.0 },
+        velocity: ConstVel2D { x: 5.0, y: 5.0 },
+        planner: Planner::Road,
+        ..fixtures::agent(name, x, order)
+    }
+}
+
+#[test]
+fn test_road_network() {
+    let agvs = [road_agent("agv-0", 0.0, 0), road_agent("agv-1", 20.0, 1)];
+    let schedule = Schedule {
+        actions: vec![Action {
+            target: Coord { x: 19.5, y: 0.3 },
+            ..fixtures::action(&agvs[0], 19.5, 2.0)
+        }],
+    };
+
+    let actual = Solver {
+        site: road_site(),
+        ..Solver::default()
+    }
+    .routes(&agvs, schedule)
+    .unwrap();
+
+    // The agent parked at the target clears the way to the side road, and
+    // the other one waits to pass the crossing after it.
+    let evader = &actual.routes[1].1;
+    assert_eq!(evader[1].action.r#type, ActionType::Evasive);
+    assert_eq!(evader[1].action.target, Coord { x: 10.0, y: 10.0 });
+    assert_eq!(evader[1].t_end, 20.0);
+    let paths = &actual.routes[0].1;
+    assert_eq!(paths[1].action.r#type, ActionType::Idle);
+    assert_eq!(paths[1].t_end, 1.0);
+    assert_eq!(paths[2].action.target, Coord { x: 20.0, y: 0.0 });
+    assert_eq!(
+        paths[2].moves.iter().map(|s| s.end).collect::<Vec<_>>(),
+        vec![Coord { x: 10.0, y: 0.0 }, Coord { x: 20.0, y: 0.0 }]
+    );
+    assert_eq!(paths[2].t_end, 1.0 + 20.0 + 2.0);
+}
+
+#[test]
+fn test_unavailable_agent_cannot_be_parked() {
+    let mut crane = fixtures::agent("agent-0", 10.0, 0);
+    crane.unavailable = vec![Unavailability {
+        window: TimeWindow {
+            start: 1.0,
+            end: 60.0,
+        },
+        parking: Parking::Position(Coord { x: 90.0, y: 5.0 }),
+    }];
+
+    let actual = routes(&[crane], Schedule { actions: vec![] });
+
+    assert!(matches!(
+        actual.err(),
+        Some(RoutingError::Unavailable { agent, .. }) if agent == "agent-0"
+    ));
+}
+
+#[test]
+fn test_disjoint_reach() {
+    let square = |x0: f64| {
+        Polygon::new(
+            LineString::from(vec![
+                (x0, 0.0),
+                (x0 + 10.0, 0.0),
+                (x0 + 10.0, 10.0),
+                (x0, 10.0),
+            ]),
+            vec![],
+        )
+    };
+    let mut agent = fixtures::agent("agent-0", 5.0, 0);
+    agent.reach = MultiPolygon(vec![square(0.0), square(20.0)]);
+    let schedule = |agent: &Agent| Schedule {
+        actions: vec![fixtures::action(agent, 25.0, 2.0)],
+    };
+
+    // Without a corridor, the parts of the reach are not connected.
+    let actual = routes(&[agent.clone()], schedule(&agent));
+    assert!(matches!(actual.err(), Some(RoutingError::NoPath { .. })));
+
+    agent.corridors = Polygon::new(
+        LineString::from(vec![(10.0, 4.0), (20.0, 4.0), (20.0, 6.0), (10.0, 6.0)]),
+        vec![],
+    )
+    .into();
+    let actual = routes(&[agent.clone()], schedule(&agent)).unwrap();
+
+    let paths = &actual.routes[0].1;
+    assert_eq!(
+        paths[1].moves.last().unwrap().end,
+        Coord { x: 25.0, y: 5.0 }
+    );
+    assert_eq!(paths[1].t_end, 10.0 + 2.0);
+}
+
+fn all_first_points_outside_sd(a1: &Agent, p1: &[PointST], a2: &Agent, p2: &[PointST], sd: f64) {
     for p in p1.iter() {
-        let c = interpolate(p, *a2, p2);
-        let eps = 1e-12;
-        if c.is_some() {
+        let c = interpolate(p, a2, p2);
+        // The noise of floating-point times of a few thousand seconds
+        let eps = 1e-9;
+        if let Some(c) = c {
             let cond = if a1.order < a2.order {
-                c.unwrap().x - p.x >= sd - eps
+                c.x - p.x >= sd - eps
             } else {
-                p.x - c.unwrap().x >= sd - eps
+                p.x - c.x >= sd - eps
             };
             if !cond {
                 println!("{:?}", p)
@@ -181,3 +800,85 @@ fn interpolate(p: &PointST, a: &Agent, pts: &[PointST]) -> Option<Coord> {
             }
         })
 }
+
+#[test]
+fn test_trace() {
+    let crane = fixtures::agent("agent-0", 10.0, 0);
+    let other = fixtures::agent("agent-1", 30.0, 1);
+    let schedule = Schedule {
+        actions: vec![
+            fixtures::action(&other, 40.0, 20.0),
+            fixtures::action(&crane, 50.0, 0.0),
+        ],
+    };
+    let solver = Solver {
+        trace: true,
+        ..Solver::default()
+    };
+
+    let actual = solver.routes(&[crane, other], schedule).unwrap();
+
+    // The other agent works at x = 40 until 25, so it evades to 60 and the
+    // crane waits until it can arrive at 50 as the other agent leaves.
+    let events = actual.trace.clone().unwrap().events;
+    assert_eq!(
+        events.iter().map(|e| &e.decision).collect::<Vec<_>>(),
+        vec![
+            &Decision::Conflict {
+                blocking: String::from("agent-1"),
+                position: Coord { x: 40.0, y: 5.0 },
+                distance: 10.0,
+                resolution: ConflictResolution::HigherThanX(60.0),
+            },
+            &Decision::Evasion {
+                agent: String::from("agent-1"),
+                target: Coord { x: 60.0, y: 5.0 },
+            },
+            &Decision::Wait {
+                blocking: Some(Blocking {
+                    agent: String::from("agent-1"),
+                    position: Coord { x: 40.0, y: 5.0 },
+                    t: 25.0,
+                    distance: 10.0,
+                }),
+                until: 15.0,
+            },
+        ]
+    );
+    assert!(events
+        .iter()
+        .all(|e| e.step == Some(1) && e.agent == "agent-0"));
+    assert_eq!(events[1].time, 25.0);
+
+    let json = serde_json::to_string(&events).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Vec<TraceEvent>>(&json).unwrap(),
+        events
+    );
+
+    // The trace is transformed along with the routing.
+    let quarter_turn = Transform {
+        a: 0.0,
+        b: -1.0,
+        xoff: 100.0,
+        d: 1.0,
+        e: 0.0,
+        yoff: 0.0,
+    };
+    let transformed = quarter_turn.routing(&actual).trace.unwrap().events;
+    assert_eq!(
+        transformed[1].decision,
+        Decision::Evasion {
+            agent: String::from("agent-1"),
+            target: Coord { x: 95.0, y: 60.0 },
+        }
+    );
+    assert_eq!(transformed[2].target, Coord { x: 95.0, y: 50.0 });
+
+    // Without being asked, the solver records nothing.
+    let untraced = routes(
+        &[fixtures::agent("agent-0", 10.0, 0)],
+        Schedule { actions: vec![] },
+    );
+    assert!(untraced.unwrap().trace.is_none());
+}